@@ -0,0 +1,76 @@
+use std::{sync::Arc, time::Duration};
+
+use glam::Vec2;
+use rhachis::{
+    animation::{Animation, AnimationEvent, PlayMode, SpriteSheet},
+    input::{InputState, Key},
+    renderers::Texture,
+    sprites::{Sprite, SpriteCamera, SpriteRenderer},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct AnimationExample {
+    renderer: SpriteRenderer,
+    animation: Animation,
+}
+
+impl Game for AnimationExample {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = SpriteRenderer::new(data);
+        renderer.camera = Some(SpriteCamera::default());
+        let texture = Texture::new(
+            data,
+            &image::open("examples/test.png").unwrap(),
+            &renderer.nearest_sampler,
+        );
+
+        // The image is split into quarters, which are played as frames.
+        let sheet =
+            SpriteSheet::from_grid(texture.size, texture.size / 2, Duration::from_millis(250))
+                .with_clip("cycle", vec![0, 1, 3, 2], PlayMode::Loop)
+                .with_clip("bounce", vec![0, 1, 2, 3], PlayMode::PingPong)
+                .with_clip("once", vec![0, 1, 2, 3], PlayMode::Once);
+
+        let frame_size = texture.size.as_vec2() / 2.0;
+        let texture = renderer.add_texture(texture);
+        renderer.sprites.push(
+            Sprite::new(texture, Vec2::ZERO)
+                .with_origin(Vec2::splat(0.5))
+                .with_scale(Vec2::splat(256.0) / frame_size),
+        );
+
+        let mut animation = Animation::new(Arc::new(sheet));
+        animation.play("cycle");
+        animation.on_event(|event| {
+            if !matches!(event, AnimationEvent::Frame { .. }) {
+                println!("{event:?}");
+            }
+        });
+
+        Self {
+            renderer,
+            animation,
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+        for (key, clip) in [(1, "cycle"), (2, "bounce"), (3, "once")] {
+            if input.is_key(Key::Num(key), InputState::Pressed) {
+                self.animation.play(clip);
+                self.animation.restart();
+            }
+        }
+
+        self.animation.update(data);
+        self.animation.apply(&mut self.renderer.sprites[0]);
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+}
@@ -0,0 +1,79 @@
+use glam::{UVec2, Vec2};
+use image::{DynamicImage, Rgba, RgbaImage};
+use rhachis::{
+    atlas::{Atlas, AtlasEntry},
+    input::{InputState, Key},
+    rand::Noise,
+    renderers::SimpleRenderer,
+    sprites::{Sprite, SpriteRenderer},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct AtlasExample {
+    renderer: SpriteRenderer,
+    atlas: Atlas,
+    noise: Noise,
+}
+
+impl Game for AtlasExample {
+    fn init(data: &GameData) -> Self {
+        let mut example = Self {
+            renderer: SpriteRenderer::new(data),
+            atlas: Atlas::new(SimpleRenderer::nearest_sampler(data))
+                .with_page_size(UVec2::new(256, 256)),
+            noise: Noise::new(),
+        };
+
+        let entry = example
+            .atlas
+            .add(data, &image::open("examples/test.png").unwrap())
+            .unwrap();
+        example.show(entry);
+
+        example
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        // Images can be added at any time, and new pages are made when the others are full.
+        if input.is_key(Key::Char('n'), InputState::Pressed) {
+            let size = UVec2::new(self.noise.next_range(8..64), self.noise.next_range(8..64));
+            let color = Rgba([
+                self.noise.next_range(64..256) as u8,
+                self.noise.next_range(64..256) as u8,
+                self.noise.next_range(64..256) as u8,
+                255,
+            ]);
+            let image = RgbaImage::from_pixel(size.x, size.y, color);
+            let entry = self
+                .atlas
+                .add(data, &DynamicImage::ImageRgba8(image))
+                .unwrap();
+            self.show(entry);
+        }
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+}
+
+impl AtlasExample {
+    /// Draws the image at its place on its page, with every page side by side.
+    fn show(&mut self, entry: AtlasEntry) {
+        while self.renderer.textures.len() < self.atlas.pages().len() {
+            let page = &self.atlas.pages()[self.renderer.textures.len()];
+            self.renderer.add_texture(page.clone());
+        }
+
+        let page_offset = Vec2::new(entry.page as f32 * 266.0 + 10.0, 10.0);
+        self.renderer.sprites.push(
+            Sprite::new(entry.page, page_offset + entry.rect.position).with_source(entry.rect),
+        );
+    }
+}
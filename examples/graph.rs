@@ -0,0 +1,126 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Quat, UVec2, Vec2, Vec3};
+use rhachis::{
+    graph::{AttachmentDescriptor, AttachmentSize, RenderGraph, RenderNode, SCREEN},
+    input::{InputState, Key},
+    renderers::{Model, SimpleProjection, SimpleRenderer, Transform},
+    text::{Font, Text, TextRenderer},
+    Game, GameData, GameExt,
+};
+use wgpu::LoadOp;
+
+#[rhachis::run]
+struct Graph {
+    graph: RenderGraph,
+}
+
+impl Game for Graph {
+    fn init(data: &GameData) -> Self {
+        let mut world = SimpleRenderer::new(
+            data,
+            SimpleProjection::Perspective {
+                aspect_ratio: 16.0 / 9.0,
+            },
+        );
+        world.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 2.0, 5.0), Vec3::ZERO, Vec3::Y),
+        );
+        world.models.push(Model::cube(
+            data,
+            (0..3)
+                .map(|i| Transform::translation((i as f32 * 2.0 - 2.0, 0.0, 0.0)))
+                .collect(),
+        ));
+
+        let mut hud = TextRenderer::new(data);
+        let font = hud.add_font(
+            Font::load(
+                "examples/FiraSans-Regular.ttf",
+                SimpleRenderer::linear_sampler(data),
+            )
+            .unwrap(),
+        );
+        hud.texts
+            .push(Text::new(font, "", 24.0, Vec2::new(10.0, 10.0)));
+
+        // The nodes are added out of order, and the graph works out that the world has to
+        // be drawn before it is upscaled, and that the HUD is drawn on top of that.
+        let mut graph = RenderGraph::new()
+            .with_attachment(
+                "scene",
+                AttachmentDescriptor::color()
+                    .with_size(AttachmentSize::Fixed(UVec2::new(320, 180))),
+            )
+            .with_attachment(
+                "scene_depth",
+                AttachmentDescriptor::depth()
+                    .with_size(AttachmentSize::Fixed(UVec2::new(320, 180))),
+            )
+            .with_attachment("depth", AttachmentDescriptor::depth())
+            .with_node(
+                RenderNode::new(
+                    "upscale",
+                    SimpleRenderer::new(data, SimpleProjection::Orthographic),
+                )
+                .with_input("scene")
+                .with_depth("depth", LoadOp::Clear(1.0))
+                // The low resolution scene is stretched over the screen without being
+                // smoothed. It is given the texture again whenever the graph replaces it.
+                .with_inputs_handler(
+                    SimpleRenderer::nearest_sampler(data),
+                    |data, upscale: &mut SimpleRenderer, mut inputs| {
+                        upscale.models = vec![Model::quad_texture(
+                            data,
+                            inputs.remove(0),
+                            vec![Transform::translation((-1.0, -1.0, 0.0))
+                                .with_scale((2.0, 2.0, 1.0))],
+                        )];
+                    },
+                ),
+            )
+            .with_node(RenderNode::new("hud", hud).with_color(SCREEN, LoadOp::Load))
+            .with_node(
+                RenderNode::new("world", world)
+                    .with_color(
+                        "scene",
+                        LoadOp::Clear(wgpu::Color {
+                            r: 0.3,
+                            g: 0.5,
+                            b: 0.8,
+                            a: 1.0,
+                        }),
+                    )
+                    .with_depth("scene_depth", LoadOp::Clear(1.0)),
+            );
+        graph.compile(data).unwrap();
+        println!(
+            "Drawing {}",
+            graph.order().collect::<Vec<_>>().join(", then ")
+        );
+
+        Self { graph }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        if data.input.lock().is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        let angle = data.start_time.elapsed().as_secs_f32() * TAU / 8.0;
+        let world = self.graph.renderer_mut::<SimpleRenderer>("world").unwrap();
+        world.models[0].modify_transforms(|transforms| {
+            for transform in transforms {
+                transform.rotation = Quat::from_rotation_y(angle);
+            }
+        });
+
+        let hud = self.graph.renderer_mut::<TextRenderer>("hud").unwrap();
+        hud.texts[0].string = format!("{:.0} FPS, drawn at 320x180", data.get_framerate());
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.graph
+    }
+}
@@ -0,0 +1,66 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Quat, Vec3};
+use rhachis::{
+    input::{InputState, Key},
+    materials::Material,
+    mesh::Mesh,
+    renderers::{SimpleProjection, SimpleRenderer, Transform},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct HotReload {
+    renderer: SimpleRenderer,
+}
+
+impl Game for HotReload {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = SimpleRenderer::new(data, SimpleProjection::new_perspective(data));
+        renderer.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 1.5, 4.0), Vec3::ZERO, Vec3::Y),
+        );
+
+        let material = renderer
+            .add_material(
+                data,
+                Material::color(data, "hotreload", "", [0.0f32; 4])
+                    .with_shader_file("examples/hotreload.wgsl")
+                    .unwrap(),
+            )
+            .unwrap();
+        renderer.models.push(
+            Mesh::torus(1.0, 0.4, 48, 16)
+                .color_model(data, [1.0, 0.5, 0.2, 1.0], vec![Transform::default()])
+                .with_material(material),
+        );
+
+        println!("Edit examples/hotreload.wgsl and save it to see the changes");
+        Self { renderer }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        if data.input.lock().is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        let time = data.start_time.elapsed().as_secs_f32();
+        self.renderer
+            .material_mut("hotreload")
+            .unwrap()
+            .set_uniforms([time, 0.0, 0.0, 0.0]);
+        self.renderer.models[0].modify_transforms(|transforms| {
+            transforms[0].rotation = Quat::from_rotation_x(time * TAU / 8.0)
+        });
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+
+    fn resized(&mut self, data: &GameData, _: glam::UVec2) {
+        self.renderer
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
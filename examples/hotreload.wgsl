@@ -0,0 +1,11 @@
+// This shader is reloaded whenever it is saved while the hotreload example runs. Errors are
+// printed, and the model keeps its last working shader until they are fixed.
+
+@group(1)@binding(0)
+var<uniform> time: vec4<f32>;
+
+@fragment
+fn fragment(output: ColorOutput) -> @location(0) vec4<f32> {
+    let pulse = sin(time.x * 3.0) * 0.5 + 0.5;
+    return vec4<f32>(output.color.rgb * mix(0.4, 1.0, pulse), output.color.a);
+}
@@ -0,0 +1,185 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Quat, Vec2, Vec3};
+use image::{Rgba, RgbaImage};
+use rhachis::{
+    input::{InputState, Key},
+    materials::Material,
+    math::lerp,
+    mesh::Mesh,
+    rand::{perlin_2d, Noise},
+    renderers::{SimpleProjection, SimpleRenderer, Texture, Transform},
+    Game, GameData, GameExt,
+};
+
+/// Bands of color that scroll up the model in world space, or diagonally if `DIAGONAL` is
+/// defined. Its uniforms are `[time, bands per unit, 0, 0]`.
+const STRIPES: &str = "
+@group(1)@binding(0)
+var<uniform> stripes: vec4<f32>;
+
+struct StripeOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) world: vec3<f32>,
+}
+
+@vertex
+fn stripe_vertex(input: ColorInput, transform: Transform, instance: InstanceData) -> StripeOutput {
+    let world = transform_matrix(transform) * vec4<f32>(input.pos, 1.0);
+
+    var output: StripeOutput;
+    output.pos = projection * camera * world;
+    output.color = input.color * instance.color;
+    output.world = world.xyz;
+    return output;
+}
+
+@fragment
+fn fragment(output: StripeOutput) -> @location(0) vec4<f32> {
+#ifdef DIAGONAL
+    let height = (output.world.x + output.world.y) * 0.7;
+#else
+    let height = output.world.y;
+#endif
+    let band = step(0.5, fract(height * stripes.y - stripes.x));
+    return vec4<f32>(output.color.rgb * mix(0.3, 1.0, band), output.color.a);
+}
+";
+
+/// Burns away the parts of the model where the noise texture is below the threshold, with
+/// a glowing edge. Its uniforms are `[threshold, edge width, 0, 0]`.
+const DISSOLVE: &str = "
+@group(2)@binding(0)
+var<uniform> dissolve: vec4<f32>;
+@group(3)@binding(0)
+var noise: texture_2d<f32>;
+@group(3)@binding(1)
+var noise_sampler: sampler;
+
+@fragment
+fn fragment(output: TextureOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(texture, texture_sampler, output.tex_coords) * output.color;
+    let amount = textureSample(noise, noise_sampler, output.tex_coords).r - dissolve.x;
+    if (amount < 0.0) {
+        discard;
+    }
+    let edge = 1.0 - smoothstep(0.0, dissolve.y, amount);
+    return vec4<f32>(mix(color.rgb, vec3<f32>(1.0, 0.5, 0.1), edge), color.a);
+}
+";
+
+#[rhachis::run]
+struct Materials {
+    renderer: SimpleRenderer,
+}
+
+impl Game for Materials {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = SimpleRenderer::new(data, SimpleProjection::new_perspective(data));
+        renderer.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 2.0, 7.0), Vec3::ZERO, Vec3::Y),
+        );
+
+        let stripes = renderer
+            .add_material(
+                data,
+                Material::color(data, "stripes", STRIPES, [0.0f32, 4.0, 0.0, 0.0])
+                    .with_vertex_entry_point("stripe_vertex"),
+            )
+            .unwrap();
+        // The same shader with a define is compiled again as a variant.
+        let diagonal_stripes = renderer
+            .add_material(
+                data,
+                Material::color(data, "diagonal_stripes", STRIPES, [0.0f32, 4.0, 0.0, 0.0])
+                    .with_vertex_entry_point("stripe_vertex")
+                    .with_define("DIAGONAL", ""),
+            )
+            .unwrap();
+
+        let noise = Noise::from_seed(7);
+        let noise_image = RgbaImage::from_fn(128, 128, |x, y| {
+            let perlin = perlin_2d(&noise, Vec2::new(x as f32, y as f32) / 16.0, lerp);
+            let value = ((perlin + 1.0) * 127.0) as u8;
+            Rgba([value, value, value, 255])
+        });
+        let noise_texture = Texture::new(data, &noise_image.into(), &renderer.linear_sampler);
+        let dissolve = renderer
+            .add_material(
+                data,
+                Material::texture(data, "dissolve", DISSOLVE, [0.0f32, 0.1, 0.0, 0.0])
+                    .with_texture(data, &noise_texture),
+            )
+            .unwrap();
+
+        for (x, material) in [(-2.5, stripes), (0.0, diagonal_stripes)] {
+            renderer.models.push(
+                Mesh::torus(0.8, 0.3, 48, 16)
+                    .color_model(
+                        data,
+                        [0.3, 0.8, 1.0, 1.0],
+                        vec![Transform::translation((x, 0.0, 0.0))],
+                    )
+                    .with_material(material),
+            );
+        }
+        let texture = Texture::new(
+            data,
+            &image::open("examples/test.png").unwrap(),
+            &renderer.linear_sampler,
+        );
+        renderer.models.push(
+            Mesh::sphere(1.0, 32, 16)
+                .texture_model(data, texture, vec![Transform::translation((2.5, 0.0, 0.0))])
+                .with_material(dissolve),
+        );
+
+        println!("Press M to switch between the materials and the default pipelines");
+        Self { renderer }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+        if input.is_key(Key::Char('m'), InputState::Pressed) {
+            for (i, model) in self.renderer.models.iter_mut().enumerate() {
+                model.material = match model.material {
+                    Some(_) => None,
+                    None => Some(i),
+                };
+            }
+        }
+
+        let time = data.start_time.elapsed().as_secs_f32();
+        for name in ["stripes", "diagonal_stripes"] {
+            let stripes = self.renderer.material_mut(name).unwrap();
+            let mut settings: [f32; 4] = stripes.uniforms();
+            settings[0] = time;
+            stripes.set_uniforms(settings);
+        }
+
+        let dissolve = self.renderer.material_mut("dissolve").unwrap();
+        let mut settings: [f32; 4] = dissolve.uniforms();
+        settings[0] = (time * TAU / 6.0).sin() * 0.6 + 0.5;
+        dissolve.set_uniforms(settings);
+
+        for model in &mut self.renderer.models {
+            model.modify_transforms(|transforms| {
+                transforms[0].rotation = Quat::from_rotation_y(time * TAU / 8.0)
+            });
+        }
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+
+    fn resized(&mut self, data: &GameData, _: glam::UVec2) {
+        self.renderer
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
@@ -0,0 +1,145 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Quat, UVec2, Vec3};
+use rhachis::{
+    graphics::Renderer,
+    input::{InputState, Key},
+    renderers::{Model, SimpleProjection, SimpleRenderer, Transform},
+    targets::RenderTarget,
+    Game, GameData, GameExt,
+};
+use wgpu::{CommandEncoder, RenderPass, TextureView};
+
+/// Draws the scene from above into a target, then draws the scene again to the screen with
+/// a monitor that shows the target.
+struct MonitorRenderer {
+    security_camera: SimpleRenderer,
+    target: RenderTarget,
+    screen: SimpleRenderer,
+}
+
+impl Renderer for MonitorRenderer {
+    fn pass_count(&self) -> usize {
+        2
+    }
+
+    fn make_pass<'a>(
+        &'a self,
+        pass: usize,
+        view: &'a TextureView,
+        encoder: &'a mut CommandEncoder,
+    ) -> RenderPass<'a> {
+        match pass {
+            0 => self.target.begin_pass(
+                encoder,
+                Some(wgpu::Color {
+                    r: 0.0,
+                    g: 0.05,
+                    b: 0.0,
+                    a: 1.0,
+                }),
+            ),
+            _ => self.screen.make_render_pass(view, encoder),
+        }
+    }
+
+    fn render_pass<'a, 'b: 'a>(&'b self, pass: usize, render_pass: &'a mut RenderPass<'b>) {
+        match pass {
+            0 => self.security_camera.render(render_pass),
+            _ => self.screen.render(render_pass),
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        self.security_camera.update(data);
+        self.screen.update(data);
+    }
+
+    fn resize(&mut self, data: &GameData) {
+        self.screen.resize(data);
+    }
+}
+
+#[rhachis::run]
+struct Monitor {
+    renderer: MonitorRenderer,
+}
+
+/// A ring of cubes around the origin.
+fn cubes(data: &GameData) -> Model {
+    Model::cube(
+        data,
+        (0..6)
+            .map(|i| {
+                let angle = i as f32 * TAU / 6.0;
+                Transform::translation((angle.cos() * 2.0, 0.0, angle.sin() * 2.0))
+                    .with_scale((0.5, 0.5, 0.5))
+            })
+            .collect(),
+    )
+}
+
+impl Game for Monitor {
+    fn init(data: &GameData) -> Self {
+        let mut security_camera = SimpleRenderer::new(
+            data,
+            SimpleProjection::Perspective {
+                aspect_ratio: 4.0 / 3.0,
+            },
+        );
+        security_camera.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 6.0, 0.1), Vec3::ZERO, Vec3::Y),
+        );
+        security_camera.models.push(cubes(data));
+        let target = RenderTarget::new(data, UVec2::new(400, 300), &security_camera.linear_sampler);
+
+        let mut screen = SimpleRenderer::new(data, SimpleProjection::new_perspective(data));
+        screen.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 3.0, 7.0), Vec3::ZERO, Vec3::Y),
+        );
+        screen.models.push(cubes(data));
+        screen.models.push(Model::quad_texture(
+            data,
+            target.texture.clone(),
+            vec![Transform::translation((-2.0, 1.5, -3.0)).with_scale((4.0, 3.0, 1.0))],
+        ));
+
+        Self {
+            renderer: MonitorRenderer {
+                security_camera,
+                target,
+                screen,
+            },
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        if data.input.lock().is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        let angle = data.start_time.elapsed().as_secs_f32() * TAU / 8.0;
+        for renderer in [
+            &mut self.renderer.security_camera,
+            &mut self.renderer.screen,
+        ] {
+            renderer.models[0].modify_transforms(|transforms| {
+                for transform in transforms {
+                    transform.rotation = Quat::from_rotation_y(angle);
+                }
+            });
+        }
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn Renderer {
+        &mut self.renderer
+    }
+
+    fn resized(&mut self, data: &GameData, _: UVec2) {
+        self.renderer
+            .screen
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
@@ -0,0 +1,80 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Quat, Vec3};
+use rhachis::{
+    input::{InputState, Key},
+    renderers::{Model, RenderSettings, SimpleProjection, SimpleRenderer, Transform},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct Msaa {
+    renderer: SimpleRenderer,
+}
+
+impl Game for Msaa {
+    fn init(data: &GameData) -> Self {
+        let settings = RenderSettings::default()
+            .with_sample_count(4)
+            .with_clear_color(wgpu::Color {
+                r: 0.05,
+                g: 0.05,
+                b: 0.1,
+                a: 1.0,
+            });
+        let mut renderer = SimpleRenderer::new(data, SimpleProjection::new_perspective(data))
+            .with_settings(data, settings)
+            .unwrap();
+        renderer.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 0.0, 6.0), Vec3::ZERO, Vec3::Y),
+        );
+
+        // Thin bars fanned out around the centre, which have very jagged edges without
+        // multisampling.
+        let bars = (0..24)
+            .map(|i| {
+                Transform::rotation(Quat::from_rotation_z(i as f32 * TAU / 48.0))
+                    .with_scale((4.0, 0.02, 0.02))
+            })
+            .collect();
+        renderer.models.push(Model::cube(data, bars));
+
+        println!("Press 1, 2, 4 or 8 to change the number of samples per pixel");
+        Self { renderer }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        for sample_count in [1, 2, 4, 8] {
+            let key = char::from_digit(sample_count, 10).unwrap();
+            if input.is_key(Key::Char(key), InputState::Pressed) {
+                let settings = self.renderer.settings().with_sample_count(sample_count);
+                match self.renderer.set_settings(data, settings) {
+                    Ok(()) => println!("Drawing with {sample_count} samples per pixel"),
+                    Err(err) => println!("{err}"),
+                }
+            }
+        }
+
+        let angle = data.start_time.elapsed().as_secs_f32() * TAU / 30.0;
+        self.renderer.models[0].modify_transforms(|transforms| {
+            for (i, transform) in transforms.iter_mut().enumerate() {
+                transform.rotation = Quat::from_rotation_z(angle + i as f32 * TAU / 48.0);
+            }
+        });
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+
+    fn resized(&mut self, data: &GameData, _: glam::UVec2) {
+        self.renderer
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
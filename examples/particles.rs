@@ -0,0 +1,96 @@
+use glam::{Mat4, Vec3, Vec4};
+use rhachis::{
+    input::{InputState, Key},
+    particles::{Curve, ParticleEmitter, ParticleRenderer, ParticleSimulation},
+    renderers::{BlendMode, SimpleProjection},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct Particles {
+    renderer: ParticleRenderer,
+    sparks: usize,
+}
+
+impl Game for Particles {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = ParticleRenderer::new(data, SimpleProjection::new_perspective(data));
+        renderer.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 2.0, 8.0), Vec3::new(0.0, 1.0, 0.0), Vec3::Y),
+        );
+
+        // Smoke is drawn first so that the fire is drawn over it.
+        renderer.add_emitter(
+            ParticleEmitter::new(Vec3::new(-2.0, 1.0, 0.0))
+                .with_spawn_rate(30.0)
+                .with_spawn_radius(0.2)
+                .with_lifetime(2.0..4.0)
+                .with_velocity(Vec3::new(0.2, 0.8, 0.0), Vec3::new(0.2, 0.2, 0.2))
+                .with_angular_velocity(-1.0..1.0)
+                .with_color(Curve::new(vec![
+                    (0.0, Vec4::new(0.4, 0.4, 0.4, 0.0)),
+                    (0.2, Vec4::new(0.4, 0.4, 0.4, 0.6)),
+                    (1.0, Vec4::new(0.2, 0.2, 0.2, 0.0)),
+                ]))
+                .with_size(Curve::linear(0.4, 1.5)),
+        );
+        renderer.add_emitter(
+            ParticleEmitter::new(Vec3::new(-2.0, 0.0, 0.0))
+                .with_spawn_rate(200.0)
+                .with_spawn_radius(0.3)
+                .with_lifetime(0.5..1.0)
+                .with_velocity(Vec3::new(0.0, 1.5, 0.0), Vec3::new(0.3, 0.5, 0.3))
+                .with_color(Curve::new(vec![
+                    (0.0, Vec4::new(1.0, 0.9, 0.3, 1.0)),
+                    (0.5, Vec4::new(1.0, 0.4, 0.1, 0.8)),
+                    (1.0, Vec4::new(0.5, 0.0, 0.0, 0.0)),
+                ]))
+                .with_size(Curve::linear(0.5, 0.1))
+                .with_blend_mode(BlendMode::Additive),
+        );
+
+        // Sparks are simulated by a compute shader, which copes with many more particles.
+        let sparks = renderer.add_emitter(
+            ParticleEmitter::new(Vec3::new(2.0, 1.0, 0.0))
+                .with_spawn_rate(2000.0)
+                .with_max_particles(20000)
+                .with_lifetime(1.0..3.0)
+                .with_velocity(Vec3::new(0.0, 3.0, 0.0), Vec3::new(2.0, 2.0, 2.0))
+                .with_acceleration(Vec3::new(0.0, -4.0, 0.0))
+                .with_color(Curve::linear(
+                    Vec4::new(0.4, 0.7, 1.0, 1.0),
+                    Vec4::new(0.1, 0.1, 1.0, 0.0),
+                ))
+                .with_size(Curve::constant(0.05))
+                .with_blend_mode(BlendMode::Additive)
+                .with_simulation(ParticleSimulation::Gpu),
+        );
+
+        Self { renderer, sparks }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        let sparks = &mut self.renderer.emitters[self.sparks];
+        if input.is_key(Key::Char('e'), InputState::Pressed) {
+            sparks.emitting = !sparks.emitting;
+        }
+        if input.is_key(Key::Char('b'), InputState::Pressed) {
+            sparks.burst(5000);
+        }
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+
+    fn resized(&mut self, data: &GameData, _: glam::UVec2) {
+        self.renderer
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
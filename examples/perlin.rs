@@ -3,7 +3,8 @@ use std::f32::consts::TAU;
 use glam::{Mat4, Quat, Vec2, Vec3};
 use rhachis::{
     input::{InputState, Key},
-    math::smootherstep,
+    instances::InstanceData,
+    math::{lerp, smootherstep},
     rand::{perlin_2d, Noise},
     renderers::{Model, SimpleProjection, SimpleRenderer, Transform},
     Game, GameData, GameExt,
@@ -36,16 +37,17 @@ impl Game for PerlinExample {
         renderer.set_camera(data, camera(cam_distance, cam_angle));
 
         renderer.models.push(
-            Model::from_obj(
+            Model::from_obj_mipmapped(
                 data,
                 "examples/cube.obj",
-                &renderer.nearest_sampler,
+                &SimpleRenderer::linear_mipmap_sampler(data, 16),
                 terrain_transforms(&Noise::new()),
             )
             .unwrap()
             .pop()
             .unwrap(),
         );
+        color_by_height(&mut renderer.models[0]);
 
         Self {
             renderer,
@@ -77,6 +79,10 @@ impl Game for PerlinExample {
         }
         if input.is_key(Key::Char('r'), InputState::Pressed) {
             self.renderer.models[0].set_transforms(terrain_transforms(&Noise::new()));
+            color_by_height(&mut self.renderer.models[0]);
+        }
+        if input.is_key(Key::Char('c'), InputState::Pressed) {
+            println!("Culled {} cubes", self.renderer.culled_count());
         }
         if input.is_key(Key::Escape, InputState::Pressed) {
             data.exit(None);
@@ -117,3 +123,16 @@ fn terrain_transforms(noise: &Noise) -> Vec<Transform> {
 
     to_ret
 }
+
+/// Tints every cube from green at the bottom to white at the top.
+fn color_by_height(model: &mut Model) {
+    let colors = model
+        .transforms()
+        .iter()
+        .map(|transform| {
+            let height = ((transform.translation.y + 12.0) / 24.0).clamp(0.0, 1.0);
+            InstanceData::color([lerp(0.3, 1.0, height), 1.0, lerp(0.3, 1.0, height), 1.0])
+        })
+        .collect::<Vec<_>>();
+    model.modify_instance_data(|data| data.copy_from_slice(&colors));
+}
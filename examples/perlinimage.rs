@@ -1,18 +1,27 @@
-use glam::{Vec2, Vec3};
+use glam::{UVec2, Vec2, Vec3};
 use image::{Rgba, RgbaImage};
 use rhachis::{
+    dynamic::DynamicTexture,
     graphics::Renderer,
     math::lerp,
     rand::{perlin_2d, Noise},
-    renderers::{Model, SimpleRenderer, Texture, Transform},
+    renderers::{Model, SimpleRenderer, Transform},
     *,
 };
 
 const IMAGE_WIDTH: u32 = 1200;
 const IMAGE_HEIGHT: u32 = 800;
+/// The number of rows that are redrawn with the next offset every frame.
+const ROWS_PER_FRAME: u32 = 8;
 
 #[rhachis::run]
-struct PerlinImage(SimpleRenderer);
+struct PerlinImage {
+    renderer: SimpleRenderer,
+    noise: Noise,
+    texture: DynamicTexture,
+    row: u32,
+    offset: f32,
+}
 
 impl Game for PerlinImage {
     fn init(data: &rhachis::GameData) -> Self {
@@ -20,31 +29,57 @@ impl Game for PerlinImage {
         data.window.lock().set_title("0xabadcafe");
 
         let noise = Noise::from_seed(0xabadcafe);
-        let mut image = RgbaImage::new(IMAGE_WIDTH, IMAGE_HEIGHT);
-
-        for x in 0..IMAGE_WIDTH {
-            for y in 0..IMAGE_HEIGHT {
-                let perlin = perlin_2d(&noise, Vec2::new(x as f32, y as f32) / 64.0, lerp);
-                let value = ((perlin + 1.0) * 127.0) as u8;
-                image.put_pixel(x, y, Rgba([value, value, value, 255]));
-            }
-        }
-
         let mut renderer =
             SimpleRenderer::new(data, rhachis::renderers::SimpleProjection::Orthographic);
-        let texture = Texture::new(data, &image.into(), &renderer.nearest_sampler);
+        let mut texture = DynamicTexture::new(
+            data,
+            UVec2::new(IMAGE_WIDTH, IMAGE_HEIGHT),
+            &renderer.nearest_sampler,
+        );
+        texture
+            .write_image(data, UVec2::ZERO, &noise_rows(&noise, 0, IMAGE_HEIGHT, 0.0))
+            .unwrap();
 
         renderer.models.push(Model::quad_texture(
             data,
-            texture,
+            texture.texture.clone(),
             vec![Transform::scale(Vec3::new(2.0, 2.0, 1.0))
                 .with_translation(Vec3::new(-1.0, -1.0, 0.0))],
         ));
 
-        Self(renderer)
+        Self {
+            renderer,
+            noise,
+            texture,
+            row: 0,
+            offset: 0.0,
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        // The image is redrawn from top to bottom, a few rows at a time, with the noise
+        // moved a little further each time.
+        if self.row == 0 {
+            self.offset += 0.5;
+        }
+        let rows = noise_rows(&self.noise, self.row, ROWS_PER_FRAME, self.offset);
+        self.texture
+            .write_image(data, UVec2::new(0, self.row), &rows)
+            .unwrap();
+        self.row = (self.row + ROWS_PER_FRAME) % IMAGE_HEIGHT;
     }
 
     fn get_renderer(&mut self) -> &mut dyn Renderer {
-        &mut self.0
+        &mut self.renderer
     }
 }
+
+/// An image of `height` rows of noise, starting at row `top` of the full image.
+fn noise_rows(noise: &Noise, top: u32, height: u32, offset: f32) -> RgbaImage {
+    RgbaImage::from_fn(IMAGE_WIDTH, height, |x, y| {
+        let position = Vec2::new(x as f32, (top + y) as f32) / 64.0 + offset;
+        let perlin = perlin_2d(noise, position, lerp);
+        let value = ((perlin + 1.0) * 127.0) as u8;
+        Rgba([value, value, value, 255])
+    })
+}
@@ -0,0 +1,115 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Quat, Vec3};
+use image::{DynamicImage, Rgba, RgbaImage};
+use rhachis::{
+    input::{InputState, Key},
+    mesh::Mesh,
+    postprocess::{Effect, PostProcessRenderer, HDR_FORMAT},
+    renderers::{RenderSettings, SimpleProjection, SimpleRenderer, Transform},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct PostProcess {
+    renderer: PostProcessRenderer<SimpleRenderer>,
+}
+
+/// A color grading table that turns every color to sepia.
+fn sepia_table() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(256, 16, |x, y| {
+        let [r, g, b] = [x % 16, y, x / 16].map(|value| value as f32 / 15.0);
+        let sepia = [
+            0.393 * r + 0.769 * g + 0.189 * b,
+            0.349 * r + 0.686 * g + 0.168 * b,
+            0.272 * r + 0.534 * g + 0.131 * b,
+        ];
+        let [r, g, b] = sepia.map(|value| (value.min(1.0) * 255.0) as u8);
+        Rgba([r, g, b, 255])
+    }))
+}
+
+impl Game for PostProcess {
+    fn init(data: &GameData) -> Self {
+        // The scene is drawn in the HDR format, so the white torus can be brighter than the
+        // screen can show until it is tone mapped.
+        let mut scene = SimpleRenderer::new(data, SimpleProjection::new_perspective(data))
+            .with_settings(
+                data,
+                RenderSettings::default().with_color_format(HDR_FORMAT),
+            )
+            .unwrap();
+        scene.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 3.0, 8.0), Vec3::ZERO, Vec3::Y),
+        );
+        let colors = [
+            [1.0, 0.2, 0.2, 1.0],
+            [4.0, 4.0, 4.0, 1.0],
+            [0.2, 0.4, 1.0, 1.0],
+        ];
+        for (i, color) in colors.into_iter().enumerate() {
+            scene
+                .models
+                .push(Mesh::torus(0.8, 0.25, 48, 16).color_model(
+                    data,
+                    color,
+                    vec![Transform::translation((i as f32 * 2.5 - 2.5, 0.0, 0.0))],
+                ));
+        }
+
+        // Each effect can be turned on and off with the number keys, in this order.
+        let mut renderer = PostProcessRenderer::new(data, scene)
+            .with_effect(Effect::bloom(data, 1.0, 1.5, 2.0))
+            .with_effect(Effect::tonemap(data, 1.0))
+            .with_effect(Effect::color_grading(data, &sepia_table(), 1.0))
+            .with_effect(Effect::pixelate(data, 4.0))
+            .with_effect(Effect::crt(data, 0.1, 0.4))
+            .with_effect(Effect::vignette(data, 0.8, 0.4))
+            .with_effect(Effect::fxaa(data));
+        for effect in &mut renderer.effects[2..5] {
+            effect.enabled = false;
+        }
+
+        Self { renderer }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        for (i, effect) in self.renderer.effects.iter_mut().enumerate() {
+            let key = char::from_digit(i as u32 + 1, 10).unwrap();
+            if input.is_key(Key::Char(key), InputState::Pressed) {
+                effect.enabled = !effect.enabled;
+                println!("{}: {}", effect.name, effect.enabled);
+            }
+        }
+
+        // The vignette pulses by changing its uniforms.
+        let time = data.start_time.elapsed().as_secs_f32();
+        if let Some(vignette) = self.renderer.effect_mut("vignette") {
+            let mut settings: [f32; 4] = vignette.uniforms();
+            settings[1] = 0.4 + (time * 2.0).sin() * 0.1;
+            vignette.set_uniforms(settings);
+        }
+
+        for model in &mut self.renderer.renderer.models {
+            model.modify_transforms(|transforms| {
+                transforms[0].rotation = Quat::from_rotation_x(time * TAU / 6.0)
+            });
+        }
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+
+    fn resized(&mut self, data: &GameData, _: glam::UVec2) {
+        self.renderer
+            .renderer
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
@@ -0,0 +1,89 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Quat, Vec3};
+use rhachis::{
+    input::{InputState, Key},
+    mesh::Mesh,
+    renderers::{SimpleProjection, SimpleRenderer, Transform},
+    scene::{ModelInstance, NodeId, Scene},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct SceneExample {
+    renderer: SimpleRenderer,
+    scene: Scene,
+    sun: NodeId,
+    planet: NodeId,
+}
+
+impl Game for SceneExample {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = SimpleRenderer::new(data, SimpleProjection::new_perspective(data));
+        renderer.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 6.0, 10.0), Vec3::ZERO, Vec3::Y),
+        );
+
+        // Each body is a separate model, with an instance added once it is in the scene.
+        let sphere = Mesh::sphere(1.0, 24, 12);
+        for color in [
+            [1.0, 0.8, 0.2, 1.0],
+            [0.2, 0.4, 1.0, 1.0],
+            [0.7, 0.7, 0.7, 1.0],
+        ] {
+            renderer
+                .models
+                .push(sphere.color_model(data, color, Vec::new()));
+        }
+
+        let mut scene = Scene::new();
+        let sun = scene.add(Transform::default());
+        let planet = scene
+            .add_child(
+                sun,
+                Transform::translation((4.0, 0.0, 0.0)).with_scale((0.4, 0.4, 0.4)),
+            )
+            .unwrap();
+        let moon = scene
+            .add_child(
+                planet,
+                Transform::translation((2.5, 0.0, 0.0)).with_scale((0.3, 0.3, 0.3)),
+            )
+            .unwrap();
+
+        for (model, node) in [sun, planet, moon].into_iter().enumerate() {
+            let instance = renderer.models[model].add_instance(Transform::default());
+            scene.set_instance(node, Some(ModelInstance { model, instance }));
+        }
+
+        Self {
+            renderer,
+            scene,
+            sun,
+            planet,
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        if data.input.lock().is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        let time = data.start_time.elapsed().as_secs_f32();
+        self.scene.transform_mut(self.sun).unwrap().rotation =
+            Quat::from_rotation_y(time * TAU / 10.0);
+        self.scene.transform_mut(self.planet).unwrap().rotation =
+            Quat::from_rotation_y(time * TAU / 2.0);
+        self.scene.update(&mut self.renderer.models);
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+
+    fn resized(&mut self, data: &GameData, _: glam::UVec2) {
+        self.renderer
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
@@ -0,0 +1,95 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat4, Quat, Vec2, Vec3};
+use rhachis::{
+    input::{InputState, Key},
+    instances::InstanceData,
+    mesh::Mesh,
+    renderers::{BlendMode, Model, SimpleProjection, SimpleRenderer, Texture, Transform},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct Shapes {
+    renderer: SimpleRenderer,
+}
+
+impl Game for Shapes {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = SimpleRenderer::new(data, SimpleProjection::new_perspective(data));
+        renderer.set_camera(
+            data,
+            Mat4::look_at_rh(Vec3::new(0.0, 4.0, 8.0), Vec3::ZERO, Vec3::Y),
+        );
+
+        let shapes = [
+            Mesh::sphere(0.75, 24, 12),
+            Mesh::cylinder(0.6, 1.5, 24),
+            Mesh::cone(0.75, 1.5, 24),
+            Mesh::torus(0.6, 0.2, 32, 12),
+            Mesh::capsule(0.5, 0.75, 24, 6),
+        ];
+        for (i, shape) in shapes.iter().enumerate() {
+            let transform = Transform::translation((i as f32 * 2.0 - 4.0, 0.5, 0.0));
+            let texture = Texture::new(
+                data,
+                &image::open("examples/test.png").unwrap(),
+                &renderer.linear_sampler,
+            );
+            renderer
+                .models
+                .push(shape.texture_model(data, texture, vec![transform]));
+        }
+
+        renderer
+            .models
+            .push(Mesh::plane(Vec2::new(12.0, 6.0), (12, 6)).color_model(
+                data,
+                [0.3, 0.3, 0.3, 1.0],
+                vec![Transform::translation((0.0, -0.75, 0.0))],
+            ));
+
+        // Translucent panes in front of the shapes, which are drawn after them.
+        let panes = (0..3)
+            .map(|i| {
+                Transform::translation((i as f32 * 3.0 - 3.0, 0.25, 2.0))
+                    .with_scale((0.8, 1.0, 0.05))
+            })
+            .collect();
+        let mut panes = Model::cube(data, panes).with_blend_mode(BlendMode::Alpha);
+        panes.modify_instance_data(|data| {
+            for (instance, color) in data.iter_mut().zip([
+                [1.0, 0.3, 0.3, 0.4],
+                [0.3, 1.0, 0.3, 0.4],
+                [0.3, 0.3, 1.0, 0.4],
+            ]) {
+                *instance = InstanceData::color(color);
+            }
+        });
+        renderer.models.push(panes);
+
+        Self { renderer }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        if data.input.lock().is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        let angle = data.start_time.elapsed().as_secs_f32() * TAU / 8.0;
+        // The last two models are the floor and the panes.
+        let shape_count = self.renderer.models.len() - 2;
+        for model in &mut self.renderer.models[..shape_count] {
+            model.modify_transforms(|t| t[0].rotation = Quat::from_rotation_y(angle));
+        }
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+
+    fn resized(&mut self, data: &GameData, _: glam::UVec2) {
+        self.renderer
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
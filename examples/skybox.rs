@@ -0,0 +1,110 @@
+use std::f32::consts::FRAC_PI_2;
+
+use glam::{Mat4, Vec3};
+use rhachis::{
+    graphics::Renderer,
+    input::{InputState, Key},
+    renderers::{Model, SimpleProjection, SimpleRenderer, Transform},
+    skybox::{Cubemap, Skybox},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct SkyboxGame {
+    renderers: Vec<Box<dyn Renderer>>,
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+}
+
+impl SkyboxGame {
+    fn camera(&self) -> Mat4 {
+        let eye = Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos(),
+        ) * self.distance;
+        Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y)
+    }
+
+    fn update_camera(&mut self, data: &GameData) {
+        let camera = self.camera();
+        self.renderers[0]
+            .downcast_mut::<SimpleRenderer>()
+            .unwrap()
+            .set_camera(data, camera);
+        self.renderers[1]
+            .downcast_mut::<Skybox>()
+            .unwrap()
+            .set_camera(data, camera);
+    }
+}
+
+impl Game for SkyboxGame {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = SimpleRenderer::new(data, SimpleProjection::new_perspective(data));
+        renderer
+            .models
+            .push(Model::cube(data, vec![Transform::default()]));
+
+        // The sky is drawn after the cube, so it only fills the rest of the screen.
+        let cubemap =
+            Cubemap::open(data, "examples/cubemap.png", &renderer.linear_sampler).unwrap();
+        let skybox = Skybox::new(data, cubemap, SimpleProjection::new_perspective(data));
+
+        let mut game = Self {
+            renderers: vec![Box::new(renderer), Box::new(skybox)],
+            yaw: 0.0,
+            pitch: 0.0,
+            distance: 4.0,
+        };
+        game.update_camera(data);
+        game
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let delta_time = data.delta_time.as_secs_f32();
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        if input.is_key(Key::Char('a'), InputState::Down) {
+            self.yaw -= delta_time;
+        }
+        if input.is_key(Key::Char('d'), InputState::Down) {
+            self.yaw += delta_time;
+        }
+        if input.is_key(Key::Char('w'), InputState::Down) {
+            self.pitch = (self.pitch + delta_time).min(FRAC_PI_2 - 0.01);
+        }
+        if input.is_key(Key::Char('s'), InputState::Down) {
+            self.pitch = (self.pitch - delta_time).max(0.01 - FRAC_PI_2);
+        }
+        // Moving closer to the cube doesn't move the sky.
+        if input.is_key(Key::Char('q'), InputState::Down) {
+            self.distance = (self.distance - delta_time * 2.0).max(2.0);
+        }
+        if input.is_key(Key::Char('e'), InputState::Down) {
+            self.distance += delta_time * 2.0;
+        }
+        drop(input);
+
+        self.update_camera(data);
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn Renderer {
+        &mut self.renderers
+    }
+
+    fn resized(&mut self, data: &GameData, _: glam::UVec2) {
+        self.renderers[0]
+            .downcast_mut::<SimpleRenderer>()
+            .unwrap()
+            .set_projection(data, SimpleProjection::new_perspective(data));
+        self.renderers[1]
+            .downcast_mut::<Skybox>()
+            .unwrap()
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
@@ -0,0 +1,109 @@
+use std::f32::consts::TAU;
+
+use glam::Vec2;
+use rhachis::{
+    input::{InputState, Key},
+    math::Rect,
+    rand::Noise,
+    renderers::Texture,
+    sprites::{Sprite, SpriteCamera, SpriteRenderer},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct Sprites {
+    renderer: SpriteRenderer,
+    velocities: Vec<Vec2>,
+}
+
+impl Game for Sprites {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = SpriteRenderer::new(data);
+        renderer.camera = Some(SpriteCamera::default());
+        let texture = renderer.add_texture(Texture::new(
+            data,
+            &image::open("examples/test.png").unwrap(),
+            &renderer.nearest_sampler,
+        ));
+        let size = renderer.textures[texture].size.as_vec2();
+
+        // Sprites on the same layer sharing a texture are drawn with a single draw call.
+        let mut noise = Noise::new();
+        let mut velocities = Vec::new();
+        for i in 0..5000 {
+            let quarter = Vec2::new((i % 2) as f32, (i / 2 % 2) as f32) * size / 2.0;
+            renderer.sprites.push(
+                Sprite::new(
+                    texture,
+                    Vec2::new(random(&mut noise) - 0.5, random(&mut noise) - 0.5) * 800.0,
+                )
+                .with_source(Rect {
+                    position: quarter,
+                    size: size / 2.0,
+                })
+                .with_scale(Vec2::splat(24.0) / size)
+                .with_origin(Vec2::splat(0.5))
+                .with_color([random(&mut noise), random(&mut noise), 1.0, 1.0])
+                .with_layer(i % 3)
+                .with_flip(i % 5 == 0, false),
+            );
+            velocities
+                .push(Vec2::from_angle(random(&mut noise) * TAU) * 100.0 * random(&mut noise));
+        }
+
+        Self {
+            renderer,
+            velocities,
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let delta_time = data.delta_time.as_secs_f32();
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        let camera = self.renderer.camera.as_mut().unwrap();
+        let mut movement = Vec2::ZERO;
+        if input.is_key(Key::Char('w'), InputState::Down) {
+            movement.y -= 1.0;
+        }
+        if input.is_key(Key::Char('s'), InputState::Down) {
+            movement.y += 1.0;
+        }
+        if input.is_key(Key::Char('a'), InputState::Down) {
+            movement.x -= 1.0;
+        }
+        if input.is_key(Key::Char('d'), InputState::Down) {
+            movement.x += 1.0;
+        }
+        camera.position += movement * 300.0 * delta_time / camera.zoom;
+        if input.is_key(Key::Char('e'), InputState::Down) {
+            camera.zoom *= 1.0 + delta_time;
+        }
+        if input.is_key(Key::Char('q'), InputState::Down) {
+            camera.zoom /= 1.0 + delta_time;
+        }
+
+        for (sprite, velocity) in self.renderer.sprites.iter_mut().zip(&mut self.velocities) {
+            sprite.position += *velocity * delta_time;
+            sprite.rotation += TAU / 4.0 * delta_time;
+            if sprite.position.x.abs() > 400.0 {
+                velocity.x = -velocity.x;
+            }
+            if sprite.position.y.abs() > 400.0 {
+                velocity.y = -velocity.y;
+            }
+        }
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+}
+
+/// A random number from 0 to 1.
+fn random(noise: &mut Noise) -> f32 {
+    noise.next() as f32 / u32::MAX as f32
+}
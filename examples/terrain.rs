@@ -0,0 +1,98 @@
+use std::f32::consts::TAU;
+
+use glam::{IVec2, Mat4, UVec2, Vec2, Vec3};
+use image::DynamicImage;
+use rhachis::{
+    input::{InputState, Key},
+    math::smootherstep,
+    rand::{perlin_2d, Noise},
+    renderers::{SimpleProjection, SimpleRenderer, Texture, Transform},
+    terrain::Terrain,
+    Game, GameData, GameExt,
+};
+
+const CHUNKS: i32 = 8;
+const CHUNK_CELLS: u32 = 32;
+const MAX_HEIGHT: f32 = 14.0;
+
+fn camera(angle: f32) -> Mat4 {
+    let centre = Vec3::new(1.0, 0.0, 1.0) * (CHUNKS as u32 * CHUNK_CELLS) as f32 / 2.0;
+    Mat4::look_at_rh(
+        centre + Vec3::new(angle.sin() * 150.0, 60.0, angle.cos() * 150.0),
+        centre,
+        Vec3::Y,
+    )
+}
+
+#[rhachis::run]
+struct TerrainExample {
+    renderer: SimpleRenderer,
+    cam_angle: f32,
+}
+
+impl Game for TerrainExample {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = SimpleRenderer::new(data, SimpleProjection::new_perspective(data));
+        renderer.set_camera(data, camera(0.0));
+
+        let noise = Noise::new();
+        let terrain = Terrain::new(|pos: Vec2| {
+            perlin_2d(&noise, pos / 60.0, smootherstep) * 10.0
+                + perlin_2d(&noise, pos / 15.0, smootherstep) * 3.0
+        })
+        .with_chunk_cells(CHUNK_CELLS)
+        .with_skirt_depth(2.0);
+
+        // Chunks further from the middle of the terrain are made with less detail.
+        let middle = Vec2::splat(terrain.chunk_extent() * CHUNKS as f32 / 2.0);
+        for x in 0..CHUNKS {
+            for y in 0..CHUNKS {
+                let chunk = IVec2::new(x, y);
+                let heightmap = terrain.heightmap(
+                    chunk.as_vec2() * terrain.chunk_extent(),
+                    UVec2::splat(CHUNK_CELLS + 1),
+                    -MAX_HEIGHT,
+                    MAX_HEIGHT,
+                );
+                let texture = Texture::new(
+                    data,
+                    &DynamicImage::ImageRgba8(DynamicImage::ImageLuma8(heightmap).to_rgba8()),
+                    &renderer.linear_sampler,
+                );
+
+                let lod = terrain.lod(chunk, middle, terrain.chunk_extent() * 2.0);
+                renderer
+                    .models
+                    .push(terrain.chunk_mesh(chunk, lod).texture_model(
+                        data,
+                        texture,
+                        vec![Transform::default()],
+                    ));
+            }
+        }
+
+        Self {
+            renderer,
+            cam_angle: 0.0,
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        self.cam_angle += TAU / 16.0 * data.delta_time.as_secs_f32();
+        self.renderer.set_camera(data, camera(self.cam_angle));
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+
+    fn resized(&mut self, data: &GameData, _: glam::UVec2) {
+        self.renderer
+            .set_projection(data, SimpleProjection::new_perspective(data));
+    }
+}
@@ -0,0 +1,73 @@
+use glam::Vec2;
+use rhachis::{
+    input::{InputState, Key},
+    renderers::SimpleRenderer,
+    text::{Align, Font, Text, TextLayout, TextRenderer, TextSpace},
+    Game, GameData, GameExt,
+};
+
+const PARAGRAPH: &str = "Text is wrapped at the edge of its area, so long sentences like this \
+one are broken between words.\nPress 1, 2 or 3 to align it to the left, centre or right.";
+
+#[rhachis::run]
+struct TextExample {
+    renderer: TextRenderer,
+}
+
+impl Game for TextExample {
+    fn init(data: &GameData) -> Self {
+        let mut renderer = TextRenderer::new(data);
+        let font = renderer.add_font(
+            Font::load(
+                "examples/FiraSans-Regular.ttf",
+                SimpleRenderer::linear_sampler(data),
+            )
+            .unwrap(),
+        );
+
+        renderer.texts.push(
+            Text::new(font, "", 24.0, Vec2::new(10.0, 10.0)).with_color([1.0, 1.0, 0.0, 1.0]),
+        );
+        renderer.texts.push(
+            Text::new(font, PARAGRAPH, 32.0, Vec2::new(100.0, 100.0)).with_layout(TextLayout {
+                max_width: Some(500.0),
+                ..Default::default()
+            }),
+        );
+        // Text in the world moves with the camera, which is centred on the origin.
+        renderer.texts.push(
+            Text::new(font, "Hello, world!", 48.0, Vec2::new(-150.0, 150.0))
+                .with_space(TextSpace::World)
+                .with_color([0.4, 0.8, 1.0, 1.0]),
+        );
+
+        Self { renderer }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        self.renderer.texts[0].string = format!("{:.0} FPS", data.get_framerate());
+
+        for (key, align) in [
+            ('1', Align::Left),
+            ('2', Align::Centre),
+            ('3', Align::Right),
+        ] {
+            if input.is_key(Key::Char(key), InputState::Pressed) {
+                self.renderer.texts[1].layout.align = align;
+            }
+        }
+
+        let delta_time = data.delta_time.as_secs_f32();
+        self.renderer.camera.position.x =
+            (self.renderer.camera.position.x + delta_time * 50.0).rem_euclid(400.0);
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+}
@@ -0,0 +1,85 @@
+use glam::Vec2;
+use rhachis::{
+    input::{InputState, Key},
+    sprites::SpriteCamera,
+    tiled::TiledMap,
+    tilemap::{Property, Tile, TilemapRenderer},
+    Game, GameData, GameExt,
+};
+
+#[rhachis::run]
+struct Tilemap {
+    renderer: TilemapRenderer,
+}
+
+impl Game for Tilemap {
+    fn init(data: &GameData) -> Self {
+        let map = TiledMap::load("examples/tilemap.tmx").unwrap();
+        if let Some(Property::String(title)) = map.properties.get("title") {
+            println!("{title}");
+        }
+        if let Some(Property::String(message)) = map
+            .object("sign")
+            .and_then(|sign| sign.properties.get("message"))
+        {
+            println!("The sign says: {message}");
+        }
+
+        let mut renderer = TilemapRenderer::from_map(data, &map).unwrap();
+        renderer.camera = Some(SpriteCamera {
+            position: map
+                .object("spawn")
+                .map_or(Vec2::ZERO, |spawn| spawn.position),
+            zoom: 2.0,
+        });
+
+        Self { renderer }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let delta_time = data.delta_time.as_secs_f32();
+        let input = data.input.lock();
+        if input.is_key(Key::Escape, InputState::Pressed) {
+            data.exit(None);
+        }
+
+        let camera = self.renderer.camera.as_mut().unwrap();
+        let mut movement = Vec2::ZERO;
+        if input.is_key(Key::Char('w'), InputState::Down) {
+            movement.y -= 1.0;
+        }
+        if input.is_key(Key::Char('s'), InputState::Down) {
+            movement.y += 1.0;
+        }
+        if input.is_key(Key::Char('a'), InputState::Down) {
+            movement.x -= 1.0;
+        }
+        if input.is_key(Key::Char('d'), InputState::Down) {
+            movement.x += 1.0;
+        }
+        camera.position += movement * 300.0 * delta_time / camera.zoom;
+        if input.is_key(Key::Char('e'), InputState::Down) {
+            camera.zoom *= 1.0 + delta_time;
+        }
+        if input.is_key(Key::Char('q'), InputState::Down) {
+            camera.zoom /= 1.0 + delta_time;
+        }
+
+        // Changing a tile only rebuilds the chunk it is in.
+        if input.is_key(Key::Char('r'), InputState::Pressed) {
+            let centre = camera.position;
+            if let Some(position) = self.renderer.tile_position(1, centre) {
+                let layer = self.renderer.layers().nth(1).unwrap();
+                let tile = match layer.get(position).unwrap().is_empty() {
+                    true => Tile::new(5),
+                    false => Tile::EMPTY,
+                };
+                self.renderer.set_tile(1, position, tile);
+            }
+        }
+    }
+
+    fn get_renderer(&mut self) -> &mut dyn rhachis::graphics::Renderer {
+        &mut self.renderer
+    }
+}
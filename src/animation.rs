@@ -0,0 +1,483 @@
+//! Playing animations made from the frames of a sprite sheet.
+
+use std::{collections::HashMap, fmt::Debug, path::Path, sync::Arc, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use glam::UVec2;
+use serde::Deserialize;
+
+use crate::{math::Rect, sprites::Sprite, GameData};
+
+/// A single image of an animation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    /// The area of the texture covered by the frame in pixels.
+    pub rect: Rect,
+    /// How long the frame is shown for.
+    pub duration: Duration,
+}
+
+/// What an animation does once it reaches the end of its clip.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PlayMode {
+    /// The clip starts again from the first frame.
+    #[default]
+    Loop,
+    /// The clip plays backwards to the first frame, then forwards again.
+    PingPong,
+    /// The clip stops on the last frame.
+    Once,
+}
+
+/// A named sequence of frames.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Clip {
+    /// The indices of the frames in `SpriteSheet::frames`, in the order they are played.
+    pub frames: Vec<usize>,
+    /// What happens when the clip reaches its end.
+    pub mode: PlayMode,
+}
+
+/// The frames in a texture and the clips they are played in.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpriteSheet {
+    /// Every frame in the texture.
+    pub frames: Vec<Frame>,
+    /// The clips that can be played, by name.
+    pub clips: HashMap<String, Clip>,
+}
+
+impl SpriteSheet {
+    /// Slices a texture of `texture_size` into frames of `frame_size`, going from left to
+    /// right and then top to bottom. Every frame is shown for `duration`.
+    pub fn from_grid(texture_size: UVec2, frame_size: UVec2, duration: Duration) -> Self {
+        let columns = texture_size.x / frame_size.x.max(1);
+        let rows = texture_size.y / frame_size.y.max(1);
+        let frames = (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| UVec2::new(x, y)))
+            .map(|cell| Frame {
+                rect: Rect {
+                    position: (cell * frame_size).as_vec2(),
+                    size: frame_size.as_vec2(),
+                },
+                duration,
+            })
+            .collect();
+
+        Self {
+            frames,
+            clips: HashMap::new(),
+        }
+    }
+
+    /// Reads the frames and clips from a JSON sprite sheet exported by Aseprite or
+    /// TexturePacker, with frames either in a hash or in an array. Frames without a
+    /// duration are shown for `default_duration`.
+    ///
+    /// Clips are made from Aseprite's frame tags and from TexturePacker's animations. Tags
+    /// that only repeat once are played with `PlayMode::Once`, and every other tag loops.
+    pub fn from_json(json: &str, default_duration: Duration) -> Result<Self> {
+        let sheet: JsonSheet = serde_json::from_str(json)?;
+
+        let frames = match sheet.frames {
+            JsonFrames::Array(frames) => frames,
+            JsonFrames::Hash(frames) => frames
+                .into_iter()
+                .map(|(filename, frame)| {
+                    let mut frame: JsonFrame = serde_json::from_value(frame)?;
+                    frame.filename = filename;
+                    Ok(frame)
+                })
+                .collect::<Result<_>>()?,
+        };
+
+        let mut clips = HashMap::new();
+        for tag in sheet.meta.frame_tags {
+            if tag.from > tag.to || tag.to >= frames.len() {
+                bail!("The frames of tag {} are out of range", tag.name);
+            }
+            let mut clip = Clip {
+                frames: (tag.from..=tag.to).collect(),
+                mode: PlayMode::Loop,
+            };
+            match tag.direction.as_str() {
+                "reverse" => clip.frames.reverse(),
+                "pingpong" => clip.mode = PlayMode::PingPong,
+                _ => {}
+            }
+            let repeat = tag.repeat.map(|repeat| match repeat {
+                serde_json::Value::String(repeat) => repeat.parse().ok(),
+                repeat => repeat.as_u64(),
+            });
+            if repeat == Some(Some(1)) {
+                clip.mode = PlayMode::Once;
+            }
+            clips.insert(tag.name, clip);
+        }
+        for (name, filenames) in sheet.animations {
+            let frames = filenames
+                .iter()
+                .map(|filename| {
+                    frames
+                        .iter()
+                        .position(|frame| &frame.filename == filename)
+                        .with_context(|| format!("Animation {name} has unknown frame {filename}"))
+                })
+                .collect::<Result<_>>()?;
+            clips.insert(
+                name,
+                Clip {
+                    frames,
+                    mode: PlayMode::Loop,
+                },
+            );
+        }
+
+        Ok(Self {
+            frames: frames
+                .into_iter()
+                .map(|frame| Frame {
+                    rect: Rect::new(frame.frame.x, frame.frame.y, frame.frame.w, frame.frame.h),
+                    duration: frame
+                        .duration
+                        .map_or(default_duration, Duration::from_millis),
+                })
+                .collect(),
+            clips,
+        })
+    }
+
+    /// Loads a JSON sprite sheet from a file, as described in `SpriteSheet::from_json`.
+    pub fn load<P: AsRef<Path> + Debug>(path: P, default_duration: Duration) -> Result<Self> {
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read sprite sheet {path:?}"))?;
+        Self::from_json(&json, default_duration)
+    }
+
+    /// Adds a clip that plays `frames` with `mode`, replacing any clip with the same name.
+    pub fn add_clip(&mut self, name: &str, frames: Vec<usize>, mode: PlayMode) {
+        self.clips.insert(name.to_owned(), Clip { frames, mode });
+    }
+
+    /// Adds a clip, then returns the sprite sheet.
+    pub fn with_clip(mut self, name: &str, frames: Vec<usize>, mode: PlayMode) -> Self {
+        self.add_clip(name, frames, mode);
+        self
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonSheet {
+    frames: JsonFrames,
+    #[serde(default)]
+    meta: JsonMeta,
+    #[serde(default)]
+    animations: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonFrames {
+    Array(Vec<JsonFrame>),
+    Hash(serde_json::Map<String, serde_json::Value>),
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    #[serde(default)]
+    filename: String,
+    frame: JsonRect,
+    duration: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct JsonRect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+#[derive(Default, Deserialize)]
+struct JsonMeta {
+    #[serde(default, rename = "frameTags")]
+    frame_tags: Vec<JsonTag>,
+}
+
+#[derive(Deserialize)]
+struct JsonTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+    repeat: Option<serde_json::Value>,
+}
+
+/// Something that happened while an animation was playing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// A new frame started being shown. `frame` is the position of the frame in the clip.
+    Frame { clip: String, frame: usize },
+    /// A looping or ping-pong clip went back to its first frame.
+    Looped { clip: String },
+    /// A clip played with `PlayMode::Once` reached its end.
+    Finished { clip: String },
+}
+
+type AnimationCallback = Box<dyn FnMut(&AnimationEvent) + Send>;
+
+/// Plays the clips of a sprite sheet.
+pub struct Animation {
+    sheet: Arc<SpriteSheet>,
+    clip: Option<String>,
+    position: usize,
+    elapsed: Duration,
+    forwards: bool,
+    finished: bool,
+    callbacks: Vec<AnimationCallback>,
+    /// How fast the animation plays, where 1 is the speed of the frame durations.
+    pub speed: f32,
+}
+
+impl Animation {
+    /// Create an `Animation` of `sheet` that isn't playing a clip.
+    pub fn new(sheet: Arc<SpriteSheet>) -> Self {
+        Self {
+            sheet,
+            clip: None,
+            position: 0,
+            elapsed: Duration::ZERO,
+            forwards: true,
+            finished: false,
+            callbacks: Vec::new(),
+            speed: 1.0,
+        }
+    }
+
+    /// The sprite sheet that the animation plays.
+    pub fn sheet(&self) -> &SpriteSheet {
+        &self.sheet
+    }
+
+    /// The name of the clip being played.
+    pub fn clip(&self) -> Option<&str> {
+        self.clip.as_deref()
+    }
+
+    /// Starts playing the clip called `name` from its first frame, unless it is already
+    /// playing. Returns `false` if there is no clip with that name.
+    pub fn play(&mut self, name: &str) -> bool {
+        if self.clip.as_deref() == Some(name) {
+            return true;
+        }
+        if !self.sheet.clips.contains_key(name) {
+            return false;
+        }
+        self.clip = Some(name.to_owned());
+        self.restart();
+        true
+    }
+
+    /// Goes back to the first frame of the current clip.
+    pub fn restart(&mut self) {
+        self.position = 0;
+        self.elapsed = Duration::ZERO;
+        self.forwards = true;
+        self.finished = false;
+    }
+
+    /// Returns `true` if the clip is played with `PlayMode::Once` and has reached its end.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Calls `callback` with every event that happens from now on.
+    pub fn on_event<F: FnMut(&AnimationEvent) + Send + 'static>(&mut self, callback: F) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// The frame being shown, or `None` if no clip is playing.
+    pub fn frame(&self) -> Option<&Frame> {
+        let clip = self.sheet.clips.get(self.clip.as_ref()?)?;
+        self.sheet.frames.get(*clip.frames.get(self.position)?)
+    }
+
+    /// Sets the source rectangle of `sprite` to the frame being shown.
+    pub fn apply(&self, sprite: &mut Sprite) {
+        if let Some(frame) = self.frame() {
+            sprite.source = Some(frame.rect);
+        }
+    }
+
+    /// Moves the animation forward by the time since the last update.
+    pub fn update(&mut self, data: &GameData) {
+        self.advance(data.delta_time);
+    }
+
+    /// Moves the animation forward by `time`, multiplied by the speed of the animation.
+    pub fn advance(&mut self, time: Duration) {
+        let events = self.step(time.mul_f32(self.speed.max(0.0)));
+        for event in &events {
+            for callback in &mut self.callbacks {
+                callback(event);
+            }
+        }
+    }
+
+    fn step(&mut self, time: Duration) -> Vec<AnimationEvent> {
+        let mut events = Vec::new();
+        let (name, clip) = match self
+            .clip
+            .as_ref()
+            .and_then(|name| Some((name, self.sheet.clips.get(name)?)))
+        {
+            Some(clip) => clip,
+            None => return events,
+        };
+        let len = clip.frames.len();
+        if self.finished || len == 0 {
+            return events;
+        }
+
+        self.elapsed += time;
+        loop {
+            let duration = self
+                .sheet
+                .frames
+                .get(clip.frames[self.position])
+                .map_or(Duration::ZERO, |frame| frame.duration);
+            if duration.is_zero() || self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+
+            match clip.mode {
+                PlayMode::Loop => {
+                    self.position = (self.position + 1) % len;
+                    if self.position == 0 {
+                        events.push(AnimationEvent::Looped { clip: name.clone() });
+                    }
+                }
+                PlayMode::Once => {
+                    if self.position + 1 == len {
+                        self.finished = true;
+                        self.elapsed = Duration::ZERO;
+                        events.push(AnimationEvent::Finished { clip: name.clone() });
+                        break;
+                    }
+                    self.position += 1;
+                }
+                PlayMode::PingPong => {
+                    if len == 1 {
+                        continue;
+                    }
+                    if self.forwards && self.position + 1 == len {
+                        self.forwards = false;
+                    } else if !self.forwards && self.position == 0 {
+                        self.forwards = true;
+                        events.push(AnimationEvent::Looped { clip: name.clone() });
+                    }
+                    if self.forwards {
+                        self.position += 1;
+                    } else {
+                        self.position -= 1;
+                    }
+                }
+            }
+            events.push(AnimationEvent::Frame {
+                clip: name.clone(),
+                frame: self.position,
+            });
+        }
+
+        events
+    }
+}
+
+#[test]
+fn animation_modes_test() {
+    let frame = Duration::from_millis(100);
+    let sheet = SpriteSheet::from_grid(UVec2::new(64, 32), UVec2::new(16, 16), frame)
+        .with_clip("loop", vec![0, 1, 2], PlayMode::Loop)
+        .with_clip("ping_pong", vec![0, 1, 2], PlayMode::PingPong)
+        .with_clip("once", vec![4, 5], PlayMode::Once);
+    assert_eq!(sheet.frames.len(), 8);
+    assert_eq!(sheet.frames[5].rect, Rect::new(16.0, 16.0, 16.0, 16.0));
+
+    let mut animation = Animation::new(Arc::new(sheet));
+    let positions = |animation: &mut Animation, name| {
+        animation.play(name);
+        (0..6)
+            .map(|_| {
+                animation.advance(frame);
+                animation.position
+            })
+            .collect::<Vec<usize>>()
+    };
+    assert_eq!(positions(&mut animation, "loop"), [1, 2, 0, 1, 2, 0]);
+    assert_eq!(positions(&mut animation, "ping_pong"), [1, 2, 1, 0, 1, 2]);
+    assert_eq!(positions(&mut animation, "once"), [1, 1, 1, 1, 1, 1]);
+    assert!(animation.is_finished());
+
+    animation.play("loop");
+    assert_eq!(
+        animation.step(frame * 3),
+        [
+            AnimationEvent::Frame {
+                clip: "loop".to_owned(),
+                frame: 1
+            },
+            AnimationEvent::Frame {
+                clip: "loop".to_owned(),
+                frame: 2
+            },
+            AnimationEvent::Looped {
+                clip: "loop".to_owned()
+            },
+            AnimationEvent::Frame {
+                clip: "loop".to_owned(),
+                frame: 0
+            },
+        ]
+    );
+}
+
+#[test]
+fn sprite_sheet_json_test() {
+    let aseprite = r#"{
+        "frames": {
+            "walk 0.aseprite": { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "duration": 50 },
+            "walk 1.aseprite": { "frame": { "x": 8, "y": 0, "w": 8, "h": 8 }, "duration": 150 },
+            "walk 2.aseprite": { "frame": { "x": 16, "y": 0, "w": 8, "h": 8 }, "duration": 50 }
+        },
+        "meta": {
+            "frameTags": [
+                { "name": "walk", "from": 0, "to": 2, "direction": "pingpong" },
+                { "name": "die", "from": 1, "to": 2, "direction": "reverse", "repeat": "1" }
+            ]
+        }
+    }"#;
+    let sheet = SpriteSheet::from_json(aseprite, Duration::ZERO).unwrap();
+    assert_eq!(sheet.frames[1].rect, Rect::new(8.0, 0.0, 8.0, 8.0));
+    assert_eq!(sheet.frames[1].duration, Duration::from_millis(150));
+    assert_eq!(sheet.clips["walk"].mode, PlayMode::PingPong);
+    assert_eq!(
+        sheet.clips["die"],
+        Clip {
+            frames: vec![2, 1],
+            mode: PlayMode::Once
+        }
+    );
+
+    let texture_packer = r#"{
+        "frames": [
+            { "filename": "run_1.png", "frame": { "x": 0, "y": 0, "w": 4, "h": 4 } },
+            { "filename": "run_0.png", "frame": { "x": 4, "y": 0, "w": 4, "h": 4 } }
+        ],
+        "animations": { "run": ["run_0.png", "run_1.png"] }
+    }"#;
+    let sheet = SpriteSheet::from_json(texture_packer, Duration::from_millis(80)).unwrap();
+    assert_eq!(sheet.frames[0].duration, Duration::from_millis(80));
+    assert_eq!(sheet.clips["run"].frames, [1, 0]);
+}
@@ -0,0 +1,274 @@
+//! Packing many images onto a few large textures, so that things drawn with different
+//! images can share a texture.
+
+use std::num::NonZeroU32;
+
+use anyhow::{bail, Result};
+use glam::{UVec2, Vec2};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use wgpu::Sampler;
+
+use crate::{math::Rect, renderers::Texture, GameData};
+
+/// Places rectangles in rows, called shelves, from the top of an area to the bottom. Each
+/// rectangle goes on the shelf that fits it with the least wasted height, and a new shelf is
+/// started when none of them fit.
+#[derive(Clone, Debug)]
+pub struct ShelfPacker {
+    size: UVec2,
+    shelves: Vec<Shelf>,
+}
+
+#[derive(Clone, Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    width: u32,
+}
+
+impl ShelfPacker {
+    /// Create an empty `ShelfPacker` for an area of `size`.
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            shelves: Vec::new(),
+        }
+    }
+
+    /// The size of the area rectangles are placed in.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Finds a place for a rectangle of `size` and returns the position of its top left
+    /// corner, or `None` if there isn't enough space left.
+    pub fn pack(&mut self, size: UVec2) -> Option<UVec2> {
+        if size.x > self.size.x || size.y > self.size.y {
+            return None;
+        }
+
+        let width = self.size.x;
+        if let Some(shelf) = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= size.y && width - shelf.width >= size.x)
+            .min_by_key(|shelf| shelf.height - size.y)
+        {
+            let position = UVec2::new(shelf.width, shelf.y);
+            shelf.width += size.x;
+            return Some(position);
+        }
+
+        let y = self
+            .shelves
+            .last()
+            .map_or(0, |shelf| shelf.y + shelf.height);
+        if self.size.y - y < size.y {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y,
+            height: size.y,
+            width: size.x,
+        });
+        Some(UVec2::new(0, y))
+    }
+}
+
+/// Where an image was placed in an `Atlas`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasEntry {
+    /// The index of the page that the image is on.
+    pub page: usize,
+    /// The area of the page covered by the image in pixels.
+    pub rect: Rect,
+    /// The area of the page covered by the image in texture coordinates. This can be
+    /// converted to an array for `InstanceData::uv_rect`.
+    pub uv_rect: Rect,
+}
+
+/// A set of textures, called pages, that images are packed onto as they are added. When an
+/// image doesn't fit on any page a new page is made.
+///
+/// Each image is surrounded by a border of copies of its edge pixels, so that filtering
+/// doesn't blend in the images next to it.
+pub struct Atlas {
+    pages: Vec<(wgpu::Texture, ShelfPacker)>,
+    textures: Vec<Texture>,
+    sampler: Sampler,
+    page_size: UVec2,
+    padding: u32,
+}
+
+impl Atlas {
+    /// Create an empty `Atlas` with pages of 1024 by 1024 pixels and 1 pixel of padding
+    /// around each image. Every page is drawn with `sampler`.
+    pub fn new(sampler: Sampler) -> Self {
+        Self {
+            pages: Vec::new(),
+            textures: Vec::new(),
+            sampler,
+            page_size: UVec2::new(1024, 1024),
+            padding: 1,
+        }
+    }
+
+    /// Modifies the size of pages made after this, then returns the atlas.
+    pub fn with_page_size(mut self, page_size: UVec2) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Modifies the number of pixels around images added after this, then returns the
+    /// atlas.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// The textures that images have been packed onto.
+    pub fn pages(&self) -> &[Texture] {
+        &self.textures
+    }
+
+    /// Packs `image` onto a page and writes it to the texture of that page. Returns an error
+    /// if the image and its padding are bigger than a page.
+    pub fn add(&mut self, data: &GameData, image: &DynamicImage) -> Result<AtlasEntry> {
+        let (width, height) = image.dimensions();
+        let padded = pad(&image.to_rgba8(), self.padding);
+        let padded_size = UVec2::new(padded.width(), padded.height());
+        if padded_size.x > self.page_size.x || padded_size.y > self.page_size.y {
+            bail!(
+                "An image of {width}x{height} pixels doesn't fit on an atlas page of {}x{} pixels",
+                self.page_size.x,
+                self.page_size.y
+            );
+        }
+
+        let found = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, (_, packer))| Some((index, packer.pack(padded_size)?)));
+        let (page, position) = match found {
+            Some(found) => found,
+            None => {
+                self.add_page(data);
+                let page = self.pages.len() - 1;
+                (page, self.pages[page].1.pack(padded_size).unwrap())
+            }
+        };
+
+        let (texture, packer) = &self.pages[page];
+        data.graphics.lock().queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: position.x,
+                    y: position.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &padded,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(4 * padded_size.x),
+                rows_per_image: NonZeroU32::new(padded_size.y),
+            },
+            wgpu::Extent3d {
+                width: padded_size.x,
+                height: padded_size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let rect = Rect {
+            position: (position + self.padding).as_vec2(),
+            size: Vec2::new(width as f32, height as f32),
+        };
+        let page_size = packer.size().as_vec2();
+        Ok(AtlasEntry {
+            page,
+            rect,
+            uv_rect: Rect {
+                position: rect.position / page_size,
+                size: rect.size / page_size,
+            },
+        })
+    }
+
+    fn add_page(&mut self, data: &GameData) {
+        let size = wgpu::Extent3d {
+            width: self.page_size.x,
+            height: self.page_size.y,
+            depth_or_array_layers: 1,
+        };
+        let texture = data
+            .graphics
+            .lock()
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Atlas Page"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            });
+
+        self.textures.push(Texture::from_view(
+            data,
+            &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            self.page_size,
+            &self.sampler,
+        ));
+        self.pages.push((texture, ShelfPacker::new(self.page_size)));
+    }
+}
+
+/// Surrounds `image` with `padding` pixels on every side, copied from the nearest edge.
+fn pad(image: &RgbaImage, padding: u32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return RgbaImage::new(width + padding * 2, height + padding * 2);
+    }
+
+    RgbaImage::from_fn(width + padding * 2, height + padding * 2, |x, y| {
+        *image.get_pixel(
+            x.saturating_sub(padding).min(width - 1),
+            y.saturating_sub(padding).min(height - 1),
+        )
+    })
+}
+
+#[test]
+fn shelf_packer_test() {
+    let mut packer = ShelfPacker::new(UVec2::new(10, 10));
+    assert_eq!(packer.pack(UVec2::new(6, 4)), Some(UVec2::new(0, 0)));
+    assert_eq!(packer.pack(UVec2::new(6, 3)), Some(UVec2::new(0, 4)));
+    // Fits on the first shelf next to the first rectangle.
+    assert_eq!(packer.pack(UVec2::new(4, 4)), Some(UVec2::new(6, 0)));
+    // The shorter shelf wastes less space.
+    assert_eq!(packer.pack(UVec2::new(4, 2)), Some(UVec2::new(6, 4)));
+    assert_eq!(packer.pack(UVec2::new(10, 3)), Some(UVec2::new(0, 7)));
+    assert_eq!(packer.pack(UVec2::new(1, 1)), None);
+    assert_eq!(packer.pack(UVec2::new(11, 1)), None);
+}
+
+#[test]
+fn atlas_pad_test() {
+    let image = RgbaImage::from_fn(2, 1, |x, _| image::Rgba([x as u8, 0, 0, 255]));
+    let padded = pad(&image, 1);
+    assert_eq!(padded.dimensions(), (4, 3));
+    let row = |y| {
+        (0..4)
+            .map(|x| padded.get_pixel(x, y)[0])
+            .collect::<Vec<u8>>()
+    };
+    assert_eq!(row(0), [0, 0, 1, 1]);
+    assert_eq!(row(1), [0, 0, 1, 1]);
+    assert_eq!(row(2), [0, 0, 1, 1]);
+}
@@ -0,0 +1,209 @@
+//! Bounding volumes and the camera frustum, used to skip drawing things that can't be seen.
+
+use glam::{Mat3, Mat4, Vec3, Vec4, Vec4Swizzles};
+
+/// An axis aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    /// The corner with the lowest coordinates.
+    pub min: Vec3,
+    /// The corner with the highest coordinates.
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Makes the smallest box containing every point in `points`. If there are no points
+    /// then the box is a single point at the origin.
+    pub fn from_points<I: IntoIterator<Item = Vec3>>(points: I) -> Self {
+        let mut points = points.into_iter();
+        let first = match points.next() {
+            Some(first) => first,
+            None => {
+                return Self {
+                    min: Vec3::ZERO,
+                    max: Vec3::ZERO,
+                }
+            }
+        };
+
+        points.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |aabb, point| Self {
+                min: aabb.min.min(point),
+                max: aabb.max.max(point),
+            },
+        )
+    }
+
+    /// The point in the middle of the box.
+    pub fn centre(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Half of the size of the box on each axis.
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) / 2.0
+    }
+
+    /// The smallest axis aligned box containing this box after it is transformed by
+    /// `matrix`.
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+        let centre = matrix.transform_point3(self.centre());
+        let axes = Mat3::from_mat4(matrix);
+        let half_extents = self.half_extents();
+        let half_extents = axes.x_axis.abs() * half_extents.x
+            + axes.y_axis.abs() * half_extents.y
+            + axes.z_axis.abs() * half_extents.z;
+
+        Self {
+            min: centre - half_extents,
+            max: centre + half_extents,
+        }
+    }
+}
+
+/// A sphere containing every point of a model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    /// The centre of the sphere.
+    pub centre: Vec3,
+    /// The radius of the sphere.
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Makes a sphere containing every point in `points`, centred on the middle of their
+    /// bounding box.
+    pub fn from_points<I: IntoIterator<Item = Vec3> + Clone>(points: I) -> Self {
+        let centre = Aabb::from_points(points.clone()).centre();
+        let radius = points
+            .into_iter()
+            .map(|point| point.distance(centre))
+            .fold(0.0, f32::max);
+
+        Self { centre, radius }
+    }
+
+    /// A sphere containing this sphere after it is transformed by `matrix`.
+    pub fn transformed(&self, matrix: Mat4) -> Self {
+        let axes = Mat3::from_mat4(matrix);
+        let scale = axes
+            .x_axis
+            .length()
+            .max(axes.y_axis.length())
+            .max(axes.z_axis.length());
+
+        Self {
+            centre: matrix.transform_point3(self.centre),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+/// The six planes around the space a camera can see.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// The planes, where the first three components are the normal facing into the
+    /// frustum, and the last is the distance along the normal from the plane to the origin.
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum from a combined projection and camera matrix, which maps depth
+    /// to the range 0 to 1 like the projections made by `SimpleProjection`. A projection
+    /// with no far plane, such as `Mat4::perspective_infinite_rh`, makes a far plane that
+    /// everything is inside.
+    pub fn from_matrix(view_projection: Mat4) -> Self {
+        let rows = [0, 1, 2, 3].map(|i| view_projection.row(i));
+        let planes = [
+            rows[3] + rows[0],
+            rows[3] - rows[0],
+            rows[3] + rows[1],
+            rows[3] - rows[1],
+            rows[2],
+            rows[3] - rows[2],
+        ]
+        .map(|plane| {
+            let length = plane.xyz().length();
+            if length > f32::EPSILON {
+                plane / length
+            } else {
+                plane
+            }
+        });
+
+        Self { planes }
+    }
+
+    /// Returns `true` if any part of `sphere` is inside the frustum.
+    pub fn contains_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(sphere.centre) + plane.w >= -sphere.radius)
+    }
+
+    /// Returns `true` if any part of `aabb` might be inside the frustum. Boxes near the
+    /// corners of the frustum can be counted as inside when they aren't.
+    pub fn contains_aabb(&self, aabb: &Aabb) -> bool {
+        let centre = aabb.centre();
+        let half_extents = aabb.half_extents();
+        self.planes.iter().all(|plane| {
+            let normal = plane.xyz();
+            normal.dot(centre) + plane.w >= -normal.abs().dot(half_extents)
+        })
+    }
+}
+
+#[test]
+fn frustum_test() {
+    use std::f32::consts::TAU;
+
+    let camera = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y);
+    let projection = Mat4::perspective_infinite_rh(TAU / 4.0, 1.0, 0.1);
+    let frustum = Frustum::from_matrix(projection * camera);
+
+    let sphere = |x, y, z| BoundingSphere {
+        centre: Vec3::new(x, y, z),
+        radius: 0.5,
+    };
+    assert!(frustum.contains_sphere(&sphere(0.0, 0.0, 0.0)));
+    assert!(frustum.contains_sphere(&sphere(0.0, 0.0, -10_000.0)));
+    assert!(!frustum.contains_sphere(&sphere(0.0, 0.0, 10.0)));
+    assert!(!frustum.contains_sphere(&sphere(20.0, 0.0, 0.0)));
+    assert!(!frustum.contains_sphere(&sphere(0.0, -20.0, 0.0)));
+
+    let aabb = |x: f32| Aabb {
+        min: Vec3::new(x - 0.5, -0.5, -0.5),
+        max: Vec3::new(x + 0.5, 0.5, 0.5),
+    };
+    assert!(frustum.contains_aabb(&aabb(5.0)));
+    assert!(!frustum.contains_aabb(&aabb(7.0)));
+
+    let orthographic =
+        Frustum::from_matrix(Mat4::orthographic_rh(-1.0, 1.0, -1.0, 1.0, 0.0, 100.0));
+    assert!(orthographic.contains_sphere(&sphere(0.0, 0.0, 0.0)));
+    assert!(!orthographic.contains_sphere(&sphere(2.0, 0.0, 0.0)));
+    assert!(!orthographic.contains_sphere(&sphere(0.0, 0.0, -101.0)));
+}
+
+#[test]
+fn bounds_transformed_test() {
+    let points = [Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 0.0)];
+    let aabb = Aabb::from_points(points);
+    assert_eq!(aabb.centre(), Vec3::new(0.0, 1.0, 0.0));
+
+    let matrix = Mat4::from_scale_rotation_translation(
+        Vec3::new(2.0, 1.0, 1.0),
+        glam::Quat::from_rotation_z(std::f32::consts::TAU / 4.0),
+        Vec3::new(10.0, 0.0, 0.0),
+    );
+    let transformed = aabb.transformed(matrix);
+    assert!(transformed.min.abs_diff_eq(Vec3::new(8.0, -2.0, 0.0), 1e-5));
+    assert!(transformed.max.abs_diff_eq(Vec3::new(10.0, 2.0, 0.0), 1e-5));
+
+    let sphere = BoundingSphere::from_points(points).transformed(matrix);
+    assert!((sphere.radius - 2.0 * 2.0_f32.sqrt()).abs() < 1e-5);
+}
@@ -0,0 +1,27 @@
+// The instance layout and camera bindings shared by every shader that draws models.
+
+struct Transform {
+    @location(2) data0: vec4<f32>,
+    @location(3) data1: vec4<f32>,
+    @location(4) data2: vec4<f32>,
+    @location(5) data3: vec4<f32>,
+}
+
+struct InstanceData {
+    @location(6) color: vec4<f32>,
+    @location(7) uv_rect: vec4<f32>,
+}
+
+@group(0)@binding(0)
+var<uniform> projection: mat4x4<f32>;
+@group(0)@binding(1)
+var<uniform> camera: mat4x4<f32>;
+
+fn transform_matrix(transform: Transform) -> mat4x4<f32> {
+    return mat4x4<f32>(
+        transform.data0,
+        transform.data1,
+        transform.data2,
+        transform.data3,
+    );
+}
@@ -0,0 +1,319 @@
+//! Images that are already compressed for the GPU, loaded from KTX2 and DDS files, which can
+//! be made into textures with `Texture::from_compressed`.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use glam::UVec2;
+use wgpu::TextureFormat;
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Formats that have a linear and an sRGB version.
+const SRGB_FORMATS: [(TextureFormat, TextureFormat); 8] = [
+    (TextureFormat::Rgba8Unorm, TextureFormat::Rgba8UnormSrgb),
+    (TextureFormat::Bc1RgbaUnorm, TextureFormat::Bc1RgbaUnormSrgb),
+    (TextureFormat::Bc2RgbaUnorm, TextureFormat::Bc2RgbaUnormSrgb),
+    (TextureFormat::Bc3RgbaUnorm, TextureFormat::Bc3RgbaUnormSrgb),
+    (TextureFormat::Bc7RgbaUnorm, TextureFormat::Bc7RgbaUnormSrgb),
+    (
+        TextureFormat::Etc2Rgb8Unorm,
+        TextureFormat::Etc2Rgb8UnormSrgb,
+    ),
+    (
+        TextureFormat::Etc2Rgb8A1Unorm,
+        TextureFormat::Etc2Rgb8A1UnormSrgb,
+    ),
+    (
+        TextureFormat::Etc2Rgba8Unorm,
+        TextureFormat::Etc2Rgba8UnormSrgb,
+    ),
+];
+
+/// A 2D image and its mipmaps, in a format the GPU can use without converting it. Only
+/// BC and ETC2 compressed formats, 8 bit RGBA and 16 bit float RGBA are supported, and
+/// compressed formats need a GPU that supports them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompressedImage {
+    /// The format of the image, which also says whether it is sRGB.
+    pub format: TextureFormat,
+    /// The size of the full image in pixels.
+    pub size: UVec2,
+    /// The data of each mipmap, starting with the full size image.
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    /// Loads a KTX2 or DDS file, depending on what the file starts with.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("Can't read {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("Can't load {}", path.display()))
+    }
+
+    /// Loads the contents of a KTX2 or DDS file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(&KTX2_IDENTIFIER) {
+            Self::from_ktx2(bytes)
+        } else if bytes.starts_with(b"DDS ") {
+            Self::from_dds(bytes)
+        } else {
+            bail!("Not a KTX2 or DDS file");
+        }
+    }
+
+    /// Loads the contents of a KTX2 file. Supercompressed files, such as Basis Universal
+    /// ones, aren't supported.
+    pub fn from_ktx2(bytes: &[u8]) -> Result<Self> {
+        let format = match read_u32(bytes, 12)? {
+            37 => TextureFormat::Rgba8Unorm,
+            43 => TextureFormat::Rgba8UnormSrgb,
+            97 => TextureFormat::Rgba16Float,
+            131 | 133 => TextureFormat::Bc1RgbaUnorm,
+            132 | 134 => TextureFormat::Bc1RgbaUnormSrgb,
+            135 => TextureFormat::Bc2RgbaUnorm,
+            136 => TextureFormat::Bc2RgbaUnormSrgb,
+            137 => TextureFormat::Bc3RgbaUnorm,
+            138 => TextureFormat::Bc3RgbaUnormSrgb,
+            139 => TextureFormat::Bc4RUnorm,
+            140 => TextureFormat::Bc4RSnorm,
+            141 => TextureFormat::Bc5RgUnorm,
+            142 => TextureFormat::Bc5RgSnorm,
+            143 => TextureFormat::Bc6hRgbUfloat,
+            144 => TextureFormat::Bc6hRgbSfloat,
+            145 => TextureFormat::Bc7RgbaUnorm,
+            146 => TextureFormat::Bc7RgbaUnormSrgb,
+            147 => TextureFormat::Etc2Rgb8Unorm,
+            148 => TextureFormat::Etc2Rgb8UnormSrgb,
+            149 => TextureFormat::Etc2Rgb8A1Unorm,
+            150 => TextureFormat::Etc2Rgb8A1UnormSrgb,
+            151 => TextureFormat::Etc2Rgba8Unorm,
+            152 => TextureFormat::Etc2Rgba8UnormSrgb,
+            153 => TextureFormat::EacR11Unorm,
+            154 => TextureFormat::EacR11Snorm,
+            155 => TextureFormat::EacRg11Unorm,
+            156 => TextureFormat::EacRg11Snorm,
+            vk_format => bail!("Vulkan format {vk_format} isn't supported"),
+        };
+        let size = UVec2::new(read_u32(bytes, 20)?, read_u32(bytes, 24)?);
+        if read_u32(bytes, 28)? > 1 || read_u32(bytes, 32)? > 1 || read_u32(bytes, 36)? != 1 {
+            bail!("Only 2D textures without layers or faces are supported");
+        }
+        if read_u32(bytes, 44)? != 0 {
+            bail!("Supercompressed files aren't supported");
+        }
+
+        // A level count of 0 asks for mipmaps to be generated, which only gives one level.
+        let level_count = read_u32(bytes, 40)?.max(1);
+        let levels = (0..level_count)
+            .map(|level| {
+                let index = 80 + level as usize * 24;
+                let offset = read_u64(bytes, index)? as usize;
+                let len = read_u64(bytes, index + 8)? as usize;
+                if len != level_len(format, size, level)? {
+                    bail!("Mipmap {level} is the wrong size");
+                }
+                read_bytes(bytes, offset, len)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            format,
+            size,
+            levels,
+        })
+    }
+
+    /// Loads the contents of a DDS file. Files without a DX10 header don't say whether they
+    /// are sRGB, so they are loaded as linear, which `CompressedImage::with_srgb` can change.
+    pub fn from_dds(bytes: &[u8]) -> Result<Self> {
+        const FOURCC: u32 = 0x4;
+        const RGB: u32 = 0x40;
+        const MIPMAP_COUNT: u32 = 0x20000;
+
+        let size = UVec2::new(read_u32(bytes, 16)?, read_u32(bytes, 12)?);
+        let level_count = match read_u32(bytes, 8)? & MIPMAP_COUNT {
+            0 => 1,
+            _ => read_u32(bytes, 28)?.max(1),
+        };
+        if read_u32(bytes, 112)? != 0 {
+            bail!("Only 2D textures without faces or depth are supported");
+        }
+
+        let flags = read_u32(bytes, 80)?;
+        let mut offset = 128;
+        let format = if flags & FOURCC != 0 {
+            match &read_bytes(bytes, 84, 4)?[..] {
+                b"DXT1" => TextureFormat::Bc1RgbaUnorm,
+                b"DXT2" | b"DXT3" => TextureFormat::Bc2RgbaUnorm,
+                b"DXT4" | b"DXT5" => TextureFormat::Bc3RgbaUnorm,
+                b"ATI1" | b"BC4U" => TextureFormat::Bc4RUnorm,
+                b"BC4S" => TextureFormat::Bc4RSnorm,
+                b"ATI2" | b"BC5U" => TextureFormat::Bc5RgUnorm,
+                b"BC5S" => TextureFormat::Bc5RgSnorm,
+                [113, 0, 0, 0] => TextureFormat::Rgba16Float,
+                b"DX10" => {
+                    offset += 20;
+                    if read_u32(bytes, 132)? != 3 || read_u32(bytes, 140)? > 1 {
+                        bail!("Only 2D textures without layers are supported");
+                    }
+                    if read_u32(bytes, 136)? & 0x4 != 0 {
+                        bail!("Cube maps aren't supported");
+                    }
+                    match read_u32(bytes, 128)? {
+                        10 => TextureFormat::Rgba16Float,
+                        28 => TextureFormat::Rgba8Unorm,
+                        29 => TextureFormat::Rgba8UnormSrgb,
+                        71 => TextureFormat::Bc1RgbaUnorm,
+                        72 => TextureFormat::Bc1RgbaUnormSrgb,
+                        74 => TextureFormat::Bc2RgbaUnorm,
+                        75 => TextureFormat::Bc2RgbaUnormSrgb,
+                        77 => TextureFormat::Bc3RgbaUnorm,
+                        78 => TextureFormat::Bc3RgbaUnormSrgb,
+                        80 => TextureFormat::Bc4RUnorm,
+                        81 => TextureFormat::Bc4RSnorm,
+                        83 => TextureFormat::Bc5RgUnorm,
+                        84 => TextureFormat::Bc5RgSnorm,
+                        95 => TextureFormat::Bc6hRgbUfloat,
+                        96 => TextureFormat::Bc6hRgbSfloat,
+                        98 => TextureFormat::Bc7RgbaUnorm,
+                        99 => TextureFormat::Bc7RgbaUnormSrgb,
+                        dxgi_format => bail!("DXGI format {dxgi_format} isn't supported"),
+                    }
+                }
+                four_cc => bail!("{} isn't supported", String::from_utf8_lossy(four_cc)),
+            }
+        } else {
+            let masks = [
+                read_u32(bytes, 92)?,
+                read_u32(bytes, 96)?,
+                read_u32(bytes, 100)?,
+                read_u32(bytes, 104)?,
+            ];
+            if flags & RGB == 0
+                || read_u32(bytes, 88)? != 32
+                || masks != [0xFF, 0xFF00, 0xFF0000, 0xFF000000]
+            {
+                bail!("Only uncompressed files in RGBA order are supported");
+            }
+            TextureFormat::Rgba8Unorm
+        };
+
+        let levels = (0..level_count)
+            .map(|level| {
+                let len = level_len(format, size, level)?;
+                let level = read_bytes(bytes, offset, len)?;
+                offset += len;
+                Ok(level)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            format,
+            size,
+            levels,
+        })
+    }
+
+    /// Changes the format to its sRGB or linear version if it has one, then returns itself.
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        for (linear, srgb_format) in SRGB_FORMATS {
+            if self.format == linear || self.format == srgb_format {
+                self.format = if srgb { srgb_format } else { linear };
+            }
+        }
+        self
+    }
+}
+
+/// The number of bytes in mipmap `level` of an image of `size` in `format`, which fails if
+/// the image is too big to load.
+fn level_len(format: TextureFormat, size: UVec2, level: u32) -> Result<usize> {
+    let info = format.describe();
+    let (block_width, block_height) = info.block_dimensions;
+    let width = size.x.checked_shr(level).unwrap_or(0).max(1);
+    let height = size.y.checked_shr(level).unwrap_or(0).max(1);
+    (width.div_ceil(block_width as u32) as usize)
+        .checked_mul(height.div_ceil(block_height as u32) as usize)
+        .and_then(|blocks| blocks.checked_mul(info.block_size as usize))
+        .context("The image is too big")
+}
+
+fn read_bytes(bytes: &[u8], offset: usize, len: usize) -> Result<Vec<u8>> {
+    offset
+        .checked_add(len)
+        .and_then(|end| bytes.get(offset..end))
+        .map(<[u8]>::to_vec)
+        .context("The file ends too early")
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    Ok(u32::from_le_bytes(
+        read_bytes(bytes, offset, 4)?.try_into().unwrap(),
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64> {
+    Ok(u64::from_le_bytes(
+        read_bytes(bytes, offset, 8)?.try_into().unwrap(),
+    ))
+}
+
+#[test]
+fn ktx2_test() {
+    // An 8x4 BC1 image with two mipmaps, which take up two blocks and then one.
+    let mut bytes = KTX2_IDENTIFIER.to_vec();
+    for value in [134, 1, 8, 4, 0, 0, 1, 2, 0, 0, 0, 0, 0] {
+        bytes.extend(u32::to_le_bytes(value));
+    }
+    bytes.extend([0; 16]);
+    for (offset, len) in [(128, 16), (144, 8)] {
+        for value in [offset, len, len] {
+            bytes.extend(u64::to_le_bytes(value));
+        }
+    }
+    bytes.resize(128, 0);
+    bytes.extend((0..24).collect::<Vec<u8>>());
+
+    let image = CompressedImage::from_bytes(&bytes).unwrap();
+    assert_eq!(image.format, TextureFormat::Bc1RgbaUnormSrgb);
+    assert_eq!(image.size, UVec2::new(8, 4));
+    assert_eq!(
+        image.levels,
+        [(0..16).collect(), (16..24).collect::<Vec<u8>>()]
+    );
+    assert_eq!(image.with_srgb(false).format, TextureFormat::Bc1RgbaUnorm);
+
+    let mut overflowing = bytes.clone();
+    overflowing[80..88].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(CompressedImage::from_bytes(&overflowing).is_err());
+    bytes.truncate(140);
+    assert!(CompressedImage::from_bytes(&bytes).is_err());
+    assert!(CompressedImage::from_bytes(b"not a texture").is_err());
+}
+
+#[test]
+fn dds_test() {
+    // A 4x4 DXT5 image, which is one block.
+    let mut bytes = b"DDS ".to_vec();
+    bytes.resize(128, 0);
+    bytes[12..16].copy_from_slice(&4u32.to_le_bytes());
+    bytes[16..20].copy_from_slice(&4u32.to_le_bytes());
+    bytes[80..84].copy_from_slice(&4u32.to_le_bytes());
+    bytes[84..88].copy_from_slice(b"DXT5");
+    bytes.extend([7; 16]);
+
+    let image = CompressedImage::from_bytes(&bytes).unwrap();
+    assert_eq!(image.format, TextureFormat::Bc3RgbaUnorm);
+    assert_eq!(image.size, UVec2::new(4, 4));
+    assert_eq!(image.levels, [vec![7; 16]]);
+    assert_eq!(
+        image.with_srgb(true).format,
+        TextureFormat::Bc3RgbaUnormSrgb
+    );
+
+    bytes[84..88].copy_from_slice(b"ETC1");
+    assert!(CompressedImage::from_bytes(&bytes).is_err());
+}
@@ -0,0 +1,227 @@
+//! Textures that keep their GPU texture so that their pixels can be written from the CPU
+//! while the game runs, for things like procedural images, video frames and minimaps.
+
+use std::num::NonZeroU32;
+
+use anyhow::{bail, Result};
+use glam::UVec2;
+use image::RgbaImage;
+use wgpu::{Sampler, TextureFormat};
+
+use crate::{renderers::Texture, GameData};
+
+/// A texture whose pixels can be replaced a rectangle at a time.
+///
+/// A double buffered texture is written to a second texture that isn't drawn, and every
+/// rectangle written since the last call to `DynamicTexture::present` is copied to the drawn
+/// texture at once by it. This keeps a frame from drawing an image that is only partly
+/// written, and writing doesn't have to wait for the drawn texture to stop being used.
+pub struct DynamicTexture {
+    /// The size of the texture in pixels.
+    pub size: UVec2,
+    /// The format of the texture, which must not be compressed or a depth format.
+    pub format: TextureFormat,
+    /// The texture to draw. It stays the same however often the texture is written to.
+    pub texture: Texture,
+    front: wgpu::Texture,
+    back: Option<wgpu::Texture>,
+    /// The smallest and largest corners of what has been written to `back` but not copied.
+    outdated: Option<(UVec2, UVec2)>,
+}
+
+impl DynamicTexture {
+    /// Create a `DynamicTexture` that is `Rgba8UnormSrgb` and isn't double buffered.
+    pub fn new(data: &GameData, size: UVec2, sampler: &Sampler) -> Self {
+        Self::with_format(data, size, TextureFormat::Rgba8UnormSrgb, false, sampler).unwrap()
+    }
+
+    /// Create a `DynamicTexture` of `format`, which is double buffered if `double_buffered`
+    /// is true. Fails if `format` is compressed or a depth format, since their pixels can't
+    /// be written one at a time.
+    pub fn with_format(
+        data: &GameData,
+        size: UVec2,
+        format: TextureFormat,
+        double_buffered: bool,
+        sampler: &Sampler,
+    ) -> Result<Self> {
+        check_format(format)?;
+        let size = size.max(UVec2::ONE);
+        let make_texture = |usage| {
+            data.graphics
+                .lock()
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: None,
+                    size: extent(size),
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::COPY_DST | usage,
+                })
+        };
+
+        let front = make_texture(wgpu::TextureUsages::TEXTURE_BINDING);
+        Ok(Self {
+            size,
+            format,
+            texture: Texture::from_view(
+                data,
+                &front.create_view(&wgpu::TextureViewDescriptor::default()),
+                size,
+                sampler,
+            ),
+            front,
+            back: double_buffered.then(|| make_texture(wgpu::TextureUsages::COPY_SRC)),
+            outdated: None,
+        })
+    }
+
+    /// Whether writes only appear once the texture is presented.
+    pub fn double_buffered(&self) -> bool {
+        self.back.is_some()
+    }
+
+    /// Replaces the pixels in the rectangle of `size` at `position` with `pixels`, which
+    /// are in rows from the top left. Fails if the rectangle isn't inside the texture, or
+    /// `pixels` isn't the right length for it.
+    pub fn write(
+        &mut self,
+        data: &GameData,
+        position: UVec2,
+        size: UVec2,
+        pixels: &[u8],
+    ) -> Result<()> {
+        let bytes_per_pixel = self.format.describe().block_size as u32;
+        check_write(self.size, position, size, bytes_per_pixel, pixels.len())?;
+        if size.x == 0 || size.y == 0 {
+            return Ok(());
+        }
+
+        data.graphics.lock().queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: self.back.as_ref().unwrap_or(&self.front),
+                mip_level: 0,
+                origin: origin(position),
+                aspect: wgpu::TextureAspect::All,
+            },
+            pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(bytes_per_pixel * size.x),
+                rows_per_image: NonZeroU32::new(size.y),
+            },
+            extent(size),
+        );
+
+        if self.back.is_some() {
+            let (min, max) = self.outdated.unwrap_or((position, position + size));
+            self.outdated = Some((min.min(position), max.max(position + size)));
+        }
+        Ok(())
+    }
+
+    /// Replaces the pixels of the texture from `position` onwards with `image`, like
+    /// `DynamicTexture::write`.
+    pub fn write_image(
+        &mut self,
+        data: &GameData,
+        position: UVec2,
+        image: &RgbaImage,
+    ) -> Result<()> {
+        let size = UVec2::new(image.width(), image.height());
+        self.write(data, position, size, image.as_raw())
+    }
+
+    /// Copies everything written since the last call to the texture that is drawn, if the
+    /// texture is double buffered.
+    pub fn present(&mut self, data: &GameData) {
+        let (back, (min, max)) = match (&self.back, self.outdated.take()) {
+            (Some(back), Some(outdated)) => (back, outdated),
+            _ => return,
+        };
+
+        let graphics = data.graphics.lock();
+        let mut encoder = graphics
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("dynamic_texture_present"),
+            });
+        let copy = |texture| wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: origin(min),
+            aspect: wgpu::TextureAspect::All,
+        };
+        encoder.copy_texture_to_texture(copy(back), copy(&self.front), extent(max - min));
+        graphics.queue.submit(Some(encoder.finish()));
+    }
+}
+
+/// Checks that a write of a rectangle of `size` at `position` fits in a texture of
+/// `texture_size`, and that `len` bytes fill it.
+fn check_write(
+    texture_size: UVec2,
+    position: UVec2,
+    size: UVec2,
+    bytes_per_pixel: u32,
+    len: usize,
+) -> Result<()> {
+    let fits = |position: u32, size: u32, texture_size: u32| {
+        position
+            .checked_add(size)
+            .is_some_and(|end| end <= texture_size)
+    };
+    if !fits(position.x, size.x, texture_size.x) || !fits(position.y, size.y, texture_size.y) {
+        bail!("A {size} rectangle at {position} doesn't fit in a {texture_size} texture");
+    }
+    let expected = size.x as usize * size.y as usize * bytes_per_pixel as usize;
+    if len != expected {
+        bail!("A {size} rectangle needs {expected} bytes, not {len}");
+    }
+    Ok(())
+}
+
+/// Checks that the pixels of `format` can be written one at a time.
+fn check_format(format: TextureFormat) -> Result<()> {
+    let info = format.describe();
+    if info.block_dimensions != (1, 1) || info.sample_type == wgpu::TextureSampleType::Depth {
+        bail!("A DynamicTexture can't be {format:?}, because it is compressed or a depth format");
+    }
+    Ok(())
+}
+
+fn extent(size: UVec2) -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+    }
+}
+
+fn origin(position: UVec2) -> wgpu::Origin3d {
+    wgpu::Origin3d {
+        x: position.x,
+        y: position.y,
+        z: 0,
+    }
+}
+
+#[test]
+fn check_write_test() {
+    let size = UVec2::new(16, 8);
+    assert!(check_write(size, UVec2::ZERO, size, 4, 16 * 8 * 4).is_ok());
+    assert!(check_write(size, UVec2::new(14, 6), UVec2::new(2, 2), 4, 16).is_ok());
+    assert!(check_write(size, UVec2::new(15, 6), UVec2::new(2, 2), 4, 16).is_err());
+    assert!(check_write(size, UVec2::new(u32::MAX, 0), UVec2::new(2, 2), 4, 16).is_err());
+    assert!(check_write(size, UVec2::ZERO, UVec2::new(2, 2), 4, 15).is_err());
+}
+
+#[test]
+fn check_format_test() {
+    assert!(check_format(TextureFormat::Rgba8UnormSrgb).is_ok());
+    assert!(check_format(TextureFormat::R32Float).is_ok());
+    assert!(check_format(TextureFormat::Bc1RgbaUnorm).is_err());
+    assert!(check_format(TextureFormat::Depth32Float).is_err());
+}
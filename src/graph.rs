@@ -0,0 +1,759 @@
+//! A render graph, which draws renderers into named attachments in an order worked out from
+//! what each of them reads and writes.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use glam::UVec2;
+use wgpu::{LoadOp, Sampler, TextureFormat, TextureView};
+
+use crate::{graphics::Renderer, renderers::Texture, GameData};
+
+/// The name of the attachment that is drawn to the screen.
+pub const SCREEN: &str = "screen";
+
+/// How large the textures of an attachment are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttachmentSize {
+    /// The size of the screen, so the texture is replaced when the window is resized.
+    Screen,
+    /// The size of the screen multiplied by a scale.
+    Scaled(f32),
+    /// A fixed size in pixels, which doesn't change when the window is resized.
+    Fixed(UVec2),
+}
+
+impl AttachmentSize {
+    fn pixels(self, screen: UVec2) -> UVec2 {
+        match self {
+            Self::Screen => screen,
+            Self::Scaled(scale) => (screen.as_vec2() * scale).as_uvec2(),
+            Self::Fixed(size) => size,
+        }
+        .max(UVec2::ONE)
+    }
+}
+
+/// A description of the texture behind an attachment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttachmentDescriptor {
+    /// How large the texture is.
+    pub size: AttachmentSize,
+    /// The format of the texture, or `None` for the format of the screen.
+    pub format: Option<TextureFormat>,
+}
+
+impl AttachmentDescriptor {
+    /// A color attachment the size and format of the screen.
+    pub fn color() -> Self {
+        Self {
+            size: AttachmentSize::Screen,
+            format: None,
+        }
+    }
+
+    /// A `Depth32Float` depth attachment the size of the screen, which every pipeline of
+    /// Rhachis that tests depth can use.
+    pub fn depth() -> Self {
+        Self {
+            size: AttachmentSize::Screen,
+            format: Some(TextureFormat::Depth32Float),
+        }
+    }
+
+    /// Modifies the size of the attachment, then returns the descriptor.
+    pub fn with_size(mut self, size: AttachmentSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Modifies the format of the attachment, then returns the descriptor.
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+}
+
+/// Gives a renderer the textures of the inputs of its node.
+type InputsHandler = Box<dyn FnMut(&GameData, &mut dyn Renderer, Vec<Texture>) + Send + Sync>;
+
+/// A renderer in a `RenderGraph`, along with the attachments it draws to and samples.
+pub struct RenderNode {
+    /// The name of the node, to find it in the graph.
+    pub name: String,
+    /// The renderer that draws the node.
+    pub renderer: Box<dyn Renderer>,
+    /// The color attachment the node draws to, and whether it is cleared first.
+    pub color: (String, LoadOp<wgpu::Color>),
+    /// The depth attachment the node uses, if it has one, and whether it is cleared first.
+    pub depth: Option<(String, LoadOp<f32>)>,
+    /// The attachments the renderer samples, which are drawn before this node.
+    pub inputs: Vec<String>,
+    inputs_handler: Option<(Sampler, InputsHandler)>,
+}
+
+impl RenderNode {
+    /// Create a `RenderNode` that clears the screen to black and draws `renderer` to it.
+    pub fn new<R: Renderer>(name: &str, renderer: R) -> Self {
+        Self {
+            name: name.to_string(),
+            renderer: Box::new(renderer),
+            color: (SCREEN.to_string(), LoadOp::Clear(wgpu::Color::BLACK)),
+            depth: None,
+            inputs: Vec::new(),
+            inputs_handler: None,
+        }
+    }
+
+    /// Modifies the color attachment of the node, then returns the node.
+    pub fn with_color(mut self, attachment: &str, load: LoadOp<wgpu::Color>) -> Self {
+        self.color = (attachment.to_string(), load);
+        self
+    }
+
+    /// Modifies the depth attachment of the node, then returns the node. Renderers with
+    /// pipelines that test depth, like `SimpleRenderer`, need one.
+    pub fn with_depth(mut self, attachment: &str, load: LoadOp<f32>) -> Self {
+        self.depth = Some((attachment.to_string(), load));
+        self
+    }
+
+    /// Modifies the node to sample `attachment`, then returns the node.
+    pub fn with_input(mut self, attachment: &str) -> Self {
+        self.inputs.push(attachment.to_string());
+        self
+    }
+
+    /// Modifies the node to call `handler` with its renderer, if it is an `R`, and the
+    /// textures of its inputs whenever the graph is compiled, then returns the node. The
+    /// textures are sampled with `sampler` and are in the same order as `inputs`. Textures
+    /// sized by the screen are replaced when the window is resized, so this is how a
+    /// renderer keeps drawing the current ones.
+    pub fn with_inputs_handler<R, F>(mut self, sampler: Sampler, mut handler: F) -> Self
+    where
+        R: Renderer,
+        F: FnMut(&GameData, &mut R, Vec<Texture>) + Send + Sync + 'static,
+    {
+        self.inputs_handler = Some((
+            sampler,
+            Box::new(move |data, renderer, textures| {
+                if let Some(renderer) = renderer.downcast_mut() {
+                    handler(data, renderer, textures);
+                }
+            }),
+        ));
+        self
+    }
+
+    /// The attachments the node draws to.
+    fn outputs(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.color.0.as_str()).chain(self.depth.as_ref().map(|(a, _)| a.as_str()))
+    }
+}
+
+/// A texture that one or more attachments are drawn to, as long as they are never needed
+/// at the same time.
+struct PhysicalTexture {
+    format: TextureFormat,
+    size: UVec2,
+    view: TextureView,
+}
+
+/// A node as it was when the graph was last compiled.
+struct CompiledNode {
+    /// The index of the node in the graph.
+    index: usize,
+    color: (String, LoadOp<wgpu::Color>),
+    depth: Option<(String, LoadOp<f32>)>,
+}
+
+/// A renderer made of other renderers, drawn in an order where everything an attachment
+/// needs is drawn before it is sampled. Nodes that draw to the same attachment are drawn in
+/// the order they were added, so a node that loads the screen draws on top of the nodes
+/// before it.
+///
+/// The textures of attachments are allocated by the graph, and attachments that are never
+/// needed at the same time share textures. What is drawn to an attachment only lasts until
+/// the end of the frame.
+///
+/// If a change to the graph makes it invalid, the error is printed and the graph keeps
+/// drawing the way it did after the last compile that succeeded.
+pub struct RenderGraph {
+    nodes: Vec<RenderNode>,
+    attachments: HashMap<String, AttachmentDescriptor>,
+    /// The nodes in the order they are drawn.
+    order: Vec<CompiledNode>,
+    /// The physical texture of each attachment.
+    assignments: HashMap<String, usize>,
+    textures: Vec<PhysicalTexture>,
+    outdated: bool,
+}
+
+impl Default for RenderGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderGraph {
+    /// Create a `RenderGraph` with no nodes or attachments.
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            attachments: HashMap::new(),
+            order: Vec::new(),
+            assignments: HashMap::new(),
+            textures: Vec::new(),
+            outdated: true,
+        }
+    }
+
+    /// Declares an attachment that nodes can draw to and sample.
+    pub fn add_attachment(&mut self, name: &str, descriptor: AttachmentDescriptor) {
+        self.attachments.insert(name.to_string(), descriptor);
+        self.outdated = true;
+    }
+
+    /// Modifies the graph by declaring an attachment, then returns the graph.
+    pub fn with_attachment(mut self, name: &str, descriptor: AttachmentDescriptor) -> Self {
+        self.add_attachment(name, descriptor);
+        self
+    }
+
+    /// Adds a node to the graph.
+    pub fn add_node(&mut self, node: RenderNode) {
+        self.nodes.push(node);
+        self.outdated = true;
+    }
+
+    /// Modifies the graph by adding a node, then returns the graph.
+    pub fn with_node(mut self, node: RenderNode) -> Self {
+        self.add_node(node);
+        self
+    }
+
+    /// Removes the node called `name` and returns it.
+    pub fn remove_node(&mut self, name: &str) -> Option<RenderNode> {
+        let index = self.nodes.iter().position(|node| node.name == name)?;
+        self.outdated = true;
+        // The last compile keeps drawing the other nodes if the next one fails.
+        self.order.retain(|compiled| compiled.index != index);
+        for compiled in &mut self.order {
+            if compiled.index > index {
+                compiled.index -= 1;
+            }
+        }
+        Some(self.nodes.remove(index))
+    }
+
+    /// The node called `name`.
+    pub fn node(&self, name: &str) -> Option<&RenderNode> {
+        self.nodes.iter().find(|node| node.name == name)
+    }
+
+    /// The node called `name`, to be changed. The graph is compiled again before the next
+    /// frame in case its attachments were changed.
+    pub fn node_mut(&mut self, name: &str) -> Option<&mut RenderNode> {
+        self.outdated = true;
+        self.nodes.iter_mut().find(|node| node.name == name)
+    }
+
+    /// The renderer of the node called `name`, if it is an `R`.
+    pub fn renderer<R: Renderer>(&self, name: &str) -> Option<&R> {
+        self.node(name)?.renderer.downcast_ref()
+    }
+
+    /// The renderer of the node called `name`, if it is an `R`, to be changed.
+    pub fn renderer_mut<R: Renderer>(&mut self, name: &str) -> Option<&mut R> {
+        self.nodes
+            .iter_mut()
+            .find(|node| node.name == name)?
+            .renderer
+            .downcast_mut()
+    }
+
+    /// The names of the nodes in the order they are drawn, as of the last compile.
+    pub fn order(&self) -> impl Iterator<Item = &str> {
+        self.order
+            .iter()
+            .map(|compiled| self.nodes[compiled.index].name.as_str())
+    }
+
+    /// Makes a texture of an attachment so that it can be sampled, or `None` if the
+    /// attachment hasn't been allocated by a compile. Textures of attachments sized by
+    /// the screen are replaced when the window is resized, which nodes are told about by
+    /// `RenderNode::with_inputs_handler`.
+    pub fn texture(&self, data: &GameData, attachment: &str, sampler: &Sampler) -> Option<Texture> {
+        let texture = &self.textures[*self.assignments.get(attachment)?];
+        Some(Texture::from_view(
+            data,
+            &texture.view,
+            texture.size,
+            sampler,
+        ))
+    }
+
+    /// Works out the order of the nodes and allocates the textures of the attachments, then
+    /// gives nodes the textures of their inputs. This is done automatically before a frame
+    /// if the graph has changed, but calling it directly allows errors in the graph to be
+    /// handled, including renderers with settings that can't draw to the attachments of
+    /// their node. If there is an error the graph is left as it was.
+    pub fn compile(&mut self, data: &GameData) -> Result<()> {
+        let order = order(&self.nodes, &self.attachments)?;
+        let screen_format = data.graphics.lock().config.format;
+        for node in &self.nodes {
+            self.check_settings(node, screen_format)?;
+        }
+
+        // Each attachment is needed from the first node that uses it to the last.
+        let mut lifetimes: Vec<(&str, usize, usize)> = Vec::new();
+        for (position, node) in order.iter().map(|index| &self.nodes[*index]).enumerate() {
+            for attachment in node.outputs().chain(node.inputs.iter().map(String::as_str)) {
+                if attachment == SCREEN {
+                    continue;
+                }
+                match lifetimes.iter_mut().find(|(name, ..)| *name == attachment) {
+                    Some((_, _, end)) => *end = position,
+                    None => lifetimes.push((attachment, position, position)),
+                }
+            }
+        }
+
+        let graphics = data.graphics.lock();
+        let screen = UVec2::new(graphics.config.width, graphics.config.height);
+        drop(graphics);
+        let kinds = lifetimes
+            .iter()
+            .map(|(name, start, end)| {
+                let descriptor = self.attachments[*name];
+                (
+                    (
+                        descriptor.format.unwrap_or(screen_format),
+                        descriptor.size.pixels(screen),
+                    ),
+                    *start,
+                    *end,
+                )
+            })
+            .collect::<Vec<_>>();
+        let slots = alias(&kinds);
+
+        // Textures from the last compile are kept if they are still the right kind.
+        let mut old_textures = std::mem::take(&mut self.textures)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        let slot_count = slots.iter().map(|slot| slot + 1).max().unwrap_or(0);
+        for slot in 0..slot_count {
+            let (format, size) = kinds[slots.iter().position(|s| *s == slot).unwrap()].0;
+            let texture = old_textures
+                .iter_mut()
+                .find(|texture| {
+                    texture
+                        .as_ref()
+                        .is_some_and(|t| t.format == format && t.size == size)
+                })
+                .and_then(Option::take)
+                .unwrap_or_else(|| PhysicalTexture {
+                    format,
+                    size,
+                    view: make_texture(data, format, size),
+                });
+            self.textures.push(texture);
+        }
+
+        self.assignments = lifetimes
+            .iter()
+            .zip(slots)
+            .map(|((name, ..), slot)| (name.to_string(), slot))
+            .collect();
+        self.order = order
+            .into_iter()
+            .map(|index| {
+                let node = &self.nodes[index];
+                CompiledNode {
+                    index,
+                    color: node.color.clone(),
+                    depth: node.depth.clone(),
+                }
+            })
+            .collect();
+        self.outdated = false;
+
+        for index in 0..self.nodes.len() {
+            let node = &self.nodes[index];
+            let textures = match &node.inputs_handler {
+                Some((sampler, _)) => node
+                    .inputs
+                    .iter()
+                    .filter_map(|input| self.texture(data, input, sampler))
+                    .collect(),
+                None => continue,
+            };
+            let node = &mut self.nodes[index];
+            if let Some((_, handler)) = &mut node.inputs_handler {
+                handler(data, node.renderer.as_mut(), textures);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the last pass of the renderer of `node`, which the graph makes, has a
+    /// sample count and formats that match the attachments of the node.
+    fn check_settings(&self, node: &RenderNode, screen_format: TextureFormat) -> Result<()> {
+        let settings = node.renderer.pass_settings();
+        if settings.sample_count != 1 {
+            bail!(
+                "Node {} draws with {} samples per pixel, but attachments only have 1",
+                node.name,
+                settings.sample_count
+            );
+        }
+
+        let format = |attachment: &str| match attachment {
+            SCREEN => Some(screen_format),
+            _ => self
+                .attachments
+                .get(attachment)
+                .map(|descriptor| descriptor.format.unwrap_or(screen_format)),
+        };
+        let color_format = settings.color_format.unwrap_or(screen_format);
+        if let Some(format) = format(&node.color.0) {
+            if format != color_format {
+                bail!(
+                    "Node {} draws in {color_format:?}, but {} is {format:?}",
+                    node.name,
+                    node.color.0
+                );
+            }
+        }
+        if let Some((depth, _)) = &node.depth {
+            if let Some(format) = format(depth) {
+                if format != settings.depth_format {
+                    bail!(
+                        "Node {} tests depth in {:?}, but {depth} is {format:?}",
+                        node.name,
+                        settings.depth_format
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles the graph, printing the error if it is invalid. An invalid graph isn't
+    /// compiled again until it is changed.
+    fn compile_or_print(&mut self, data: &GameData) {
+        if let Err(err) = self.compile(data) {
+            self.outdated = false;
+            eprintln!("The render graph can't be compiled: {err}");
+        }
+    }
+
+    /// The node and its pass that draws the pass numbered `pass`.
+    fn locate(&self, mut pass: usize) -> (&CompiledNode, &RenderNode, usize) {
+        for compiled in &self.order {
+            let node = &self.nodes[compiled.index];
+            let count = node.renderer.pass_count();
+            if pass < count {
+                return (compiled, node, pass);
+            }
+            pass -= count;
+        }
+        panic!("The render graph has no pass {pass}")
+    }
+
+    fn view<'a>(&'a self, attachment: &str, screen: &'a TextureView) -> &'a TextureView {
+        match attachment {
+            SCREEN => screen,
+            _ => &self.textures[self.assignments[attachment]].view,
+        }
+    }
+}
+
+fn make_texture(data: &GameData, format: TextureFormat, size: UVec2) -> TextureView {
+    data.graphics
+        .lock()
+        .device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// Works out an order to draw `nodes` in, where the nodes that draw to an attachment are
+/// drawn in the order they were added, and before any node that samples it.
+fn order(
+    nodes: &[RenderNode],
+    attachments: &HashMap<String, AttachmentDescriptor>,
+) -> Result<Vec<usize>> {
+    let mut dependencies = vec![Vec::new(); nodes.len()];
+    let mut last_writers: HashMap<&str, usize> = HashMap::new();
+    for (index, node) in nodes.iter().enumerate() {
+        for attachment in node.outputs() {
+            if attachment != SCREEN && !attachments.contains_key(attachment) {
+                bail!(
+                    "Node {} draws to undeclared attachment {attachment}",
+                    node.name
+                );
+            }
+            if node.inputs.iter().any(|input| input == attachment) {
+                bail!(
+                    "Node {} samples {attachment} while drawing to it",
+                    node.name
+                );
+            }
+            if let Some(writer) = last_writers.insert(attachment, index) {
+                dependencies[index].push(writer);
+            }
+        }
+    }
+    for (index, node) in nodes.iter().enumerate() {
+        for input in &node.inputs {
+            if input == SCREEN {
+                bail!("Node {} can't sample the screen", node.name);
+            }
+            if !attachments.contains_key(input) {
+                bail!("Node {} samples undeclared attachment {input}", node.name);
+            }
+            for (writer, other) in nodes.iter().enumerate() {
+                if other.outputs().any(|output| output == input) {
+                    dependencies[index].push(writer);
+                }
+            }
+        }
+    }
+
+    // The first node that is ready is always drawn next, so unrelated nodes are drawn in
+    // the order they were added.
+    let mut order = Vec::new();
+    let mut drawn = vec![false; nodes.len()];
+    while order.len() < nodes.len() {
+        let next = (0..nodes.len()).find(|index| {
+            !drawn[*index]
+                && dependencies[*index]
+                    .iter()
+                    .all(|dependency| drawn[*dependency])
+        });
+        match next {
+            Some(next) => {
+                drawn[next] = true;
+                order.push(next);
+            }
+            None => {
+                let stuck = (0..nodes.len())
+                    .filter(|index| !drawn[*index])
+                    .map(|index| nodes[index].name.as_str())
+                    .collect::<Vec<_>>();
+                bail!("The render graph has a cycle between {}", stuck.join(", "))
+            }
+        }
+    }
+    Ok(order)
+}
+
+/// Gives each attachment a texture, given the kind of texture it needs and the first and
+/// last position in the order it is used. Attachments of the same kind that are never used
+/// at the same time share a texture.
+fn alias<K: PartialEq>(attachments: &[(K, usize, usize)]) -> Vec<usize> {
+    let mut slots: Vec<(&K, usize)> = Vec::new();
+    let mut by_start = (0..attachments.len()).collect::<Vec<_>>();
+    by_start.sort_by_key(|index| attachments[*index].1);
+
+    let mut assigned = vec![0; attachments.len()];
+    for index in by_start {
+        let (kind, start, end) = &attachments[index];
+        let free = slots
+            .iter()
+            .position(|(slot_kind, slot_end)| *slot_kind == kind && slot_end < start);
+        assigned[index] = match free {
+            Some(slot) => {
+                slots[slot].1 = *end;
+                slot
+            }
+            None => {
+                slots.push((kind, *end));
+                slots.len() - 1
+            }
+        };
+    }
+    assigned
+}
+
+impl Renderer for RenderGraph {
+    fn pass_count(&self) -> usize {
+        self.order
+            .iter()
+            .map(|compiled| self.nodes[compiled.index].renderer.pass_count())
+            .sum()
+    }
+
+    fn make_pass<'a>(
+        &'a self,
+        pass: usize,
+        view: &'a TextureView,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        let (compiled, node, pass) = self.locate(pass);
+        // Passes of the renderer before its last one draw wherever it wants them to.
+        if pass + 1 < node.renderer.pass_count() {
+            return node.renderer.make_pass(pass, view, encoder);
+        }
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&node.name),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.view(&compiled.color.0, view),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: compiled.color.1,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: compiled.depth.as_ref().map(|(attachment, load)| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view: self.view(attachment, view),
+                    depth_ops: Some(wgpu::Operations {
+                        load: *load,
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }
+            }),
+        })
+    }
+
+    fn render_pass<'a, 'b: 'a>(&'b self, pass: usize, render_pass: &'a mut wgpu::RenderPass<'b>) {
+        let (_, node, pass) = self.locate(pass);
+        node.renderer.render_pass(pass, render_pass);
+    }
+
+    fn update(&mut self, data: &GameData) {
+        if self.outdated {
+            self.compile_or_print(data);
+        }
+        for node in &mut self.nodes {
+            node.renderer.update(data);
+        }
+    }
+
+    fn resize(&mut self, data: &GameData) {
+        // The nodes are resized after their inputs, so they can use the new textures.
+        self.compile_or_print(data);
+        for node in &mut self.nodes {
+            node.renderer.resize(data);
+        }
+    }
+}
+
+#[test]
+fn render_graph_order_test() {
+    use crate::graphics::EmptyRenderer;
+
+    let attachments = ["scene", "depth", "bloom"]
+        .map(|name| (name.to_string(), AttachmentDescriptor::color()))
+        .into_iter()
+        .collect();
+    // The composite and the HUD both draw to the screen, so they are drawn in the order they
+    // were added, after the nodes whose attachments the composite reads.
+    let nodes = vec![
+        RenderNode::new("composite", EmptyRenderer)
+            .with_input("scene")
+            .with_input("bloom"),
+        RenderNode::new("hud", EmptyRenderer).with_color(SCREEN, LoadOp::Load),
+        RenderNode::new("bloom", EmptyRenderer)
+            .with_color("bloom", LoadOp::Clear(wgpu::Color::BLACK))
+            .with_input("scene"),
+        RenderNode::new("world", EmptyRenderer)
+            .with_color("scene", LoadOp::Clear(wgpu::Color::BLACK))
+            .with_depth("depth", LoadOp::Clear(1.0)),
+    ];
+    let sorted = order(&nodes, &attachments).unwrap();
+    let names = sorted
+        .iter()
+        .map(|index| nodes[*index].name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["world", "bloom", "composite", "hud"]);
+
+    let cycle = vec![
+        RenderNode::new("a", EmptyRenderer)
+            .with_color("scene", LoadOp::Load)
+            .with_input("bloom"),
+        RenderNode::new("b", EmptyRenderer)
+            .with_color("bloom", LoadOp::Load)
+            .with_input("scene"),
+    ];
+    assert!(order(&cycle, &attachments).is_err());
+    let undeclared = vec![RenderNode::new("a", EmptyRenderer).with_input("missing")];
+    assert!(order(&undeclared, &attachments).is_err());
+}
+
+#[test]
+fn render_graph_alias_test() {
+    // The first two attachments overlap, but the third can reuse the first's texture. The
+    // depth attachment is a different kind, and gets a texture as soon as it starts.
+    assert_eq!(
+        alias(&[
+            ("color", 0, 1),
+            ("color", 1, 2),
+            ("color", 2, 3),
+            ("depth", 0, 3)
+        ]),
+        [0, 2, 0, 1]
+    );
+}
+
+#[test]
+fn render_graph_settings_test() {
+    use crate::renderers::RenderSettings;
+
+    struct Settings(RenderSettings);
+    impl Renderer for Settings {
+        fn pass_settings(&self) -> RenderSettings {
+            self.0
+        }
+    }
+
+    const HDR: TextureFormat = TextureFormat::Rgba16Float;
+    let screen_format = TextureFormat::Bgra8UnormSrgb;
+    let graph = RenderGraph::new()
+        .with_attachment("hdr", AttachmentDescriptor::color().with_format(HDR))
+        .with_attachment("depth", AttachmentDescriptor::depth());
+    let node = |settings| {
+        RenderNode::new("node", Settings(settings)).with_depth("depth", LoadOp::Clear(1.0))
+    };
+
+    let settings = RenderSettings::default();
+    assert!(graph.check_settings(&node(settings), screen_format).is_ok());
+    assert!(graph
+        .check_settings(
+            &node(settings.with_color_format(HDR)).with_color("hdr", LoadOp::Load),
+            screen_format
+        )
+        .is_ok());
+    assert!(graph
+        .check_settings(&node(settings.with_sample_count(4)), screen_format)
+        .is_err());
+    assert!(graph
+        .check_settings(
+            &node(settings.with_depth_format(TextureFormat::Depth24Plus)),
+            screen_format
+        )
+        .is_err());
+    assert!(graph
+        .check_settings(
+            &node(settings).with_color("hdr", LoadOp::Load),
+            screen_format
+        )
+        .is_err());
+}
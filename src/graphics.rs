@@ -1,13 +1,17 @@
 //! Code specialised in handling graphics. Most of this is universally applicable.
 
 use downcast_rs::{impl_downcast, DowncastSync};
-use wgpu::{CommandEncoder, Device, Queue, RenderPass, Surface, SurfaceConfiguration, TextureView};
+use wgpu::{
+    Adapter, CommandEncoder, Device, Queue, RenderPass, Surface, SurfaceConfiguration,
+    TextureFormat, TextureFormatFeatureFlags, TextureView,
+};
 use winit::{dpi::PhysicalSize, window::Window};
 
-use crate::GameData;
+use crate::{renderers::RenderSettings, GameData};
 
 /// A handler over all core graphics components.
 pub struct Graphics {
+    pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
     pub surface: Surface,
@@ -33,7 +37,12 @@ impl Graphics {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    features: wgpu::Features::empty(),
+                    // Without the first, only the sample counts every device supports can be
+                    // used. The others allow compressed textures to be loaded.
+                    features: adapter.features()
+                        & (wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                            | wgpu::Features::TEXTURE_COMPRESSION_BC
+                            | wgpu::Features::TEXTURE_COMPRESSION_ETC2),
                     limits: wgpu::Limits::default(),
                 },
                 None,
@@ -51,6 +60,7 @@ impl Graphics {
         surface.configure(&device, &config);
 
         Self {
+            adapter,
             device,
             queue,
             surface,
@@ -58,6 +68,22 @@ impl Graphics {
         }
     }
 
+    /// Whether textures of `format` can be multisampled with `sample_count` samples per
+    /// pixel, and resolved if it is a color format.
+    pub fn supports_sample_count(&self, format: TextureFormat, sample_count: u32) -> bool {
+        let adapter_specific = self
+            .device
+            .features()
+            .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        let features = if adapter_specific {
+            self.adapter.get_texture_format_features(format)
+        } else {
+            format.describe().guaranteed_format_features
+        };
+        let depth = format.describe().sample_type == wgpu::TextureSampleType::Depth;
+        sample_count_supported(sample_count, features.flags, depth, adapter_specific)
+    }
+
     pub(crate) fn render(&mut self, renderer: &mut dyn Renderer) {
         let output = self.surface.get_current_texture().unwrap();
         let view = output
@@ -68,9 +94,9 @@ impl Graphics {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        {
-            let mut render_pass = renderer.make_render_pass(&view, &mut encoder);
-            renderer.render(&mut render_pass);
+        for pass in 0..renderer.pass_count() {
+            let mut render_pass = renderer.make_pass(pass, &view, &mut encoder);
+            renderer.render_pass(pass, &mut render_pass);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
@@ -84,6 +110,27 @@ impl Graphics {
     }
 }
 
+/// Only 1 and 4 samples are guaranteed to work on every device, so 2 and 8 are only allowed
+/// when the features of the adapter itself are known.
+fn sample_count_supported(
+    sample_count: u32,
+    flags: TextureFormatFeatureFlags,
+    depth: bool,
+    adapter_specific: bool,
+) -> bool {
+    let needed = if depth {
+        TextureFormatFeatureFlags::MULTISAMPLE
+    } else {
+        TextureFormatFeatureFlags::MULTISAMPLE | TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE
+    };
+    match sample_count {
+        1 => true,
+        4 => flags.contains(needed),
+        2 | 8 => adapter_specific && flags.contains(needed),
+        _ => false,
+    }
+}
+
 #[allow(unused)]
 /// This trait must be implemented on all renderers. It exposes API for rendering a frame.
 pub trait Renderer: DowncastSync {
@@ -93,7 +140,7 @@ pub trait Renderer: DowncastSync {
         &'a self,
         view: &'a TextureView,
         encoder: &'a mut CommandEncoder,
-    ) -> RenderPass {
+    ) -> RenderPass<'a> {
         encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("render_pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -110,6 +157,38 @@ pub trait Renderer: DowncastSync {
 
     /// This is called every frame once the renderpass has been created.
     fn render<'a, 'b: 'a>(&'b self, render_pass: &'a mut RenderPass<'b>) {}
+
+    /// The number of passes the renderer draws each frame, which are made and drawn in
+    /// order. The last pass draws to the screen, and the passes before it usually draw to
+    /// a `RenderTarget` that the last pass samples. A renderer without any passes draws
+    /// nothing.
+    fn pass_count(&self) -> usize {
+        1
+    }
+
+    /// Make the render pass numbered `pass`. By default every pass is made by
+    /// `make_render_pass`.
+    fn make_pass<'a>(
+        &'a self,
+        pass: usize,
+        view: &'a TextureView,
+        encoder: &'a mut CommandEncoder,
+    ) -> RenderPass<'a> {
+        self.make_render_pass(view, encoder)
+    }
+
+    /// Draw the render pass numbered `pass`. By default every pass is drawn by `render`.
+    fn render_pass<'a, 'b: 'a>(&'b self, pass: usize, render_pass: &'a mut RenderPass<'b>) {
+        self.render(render_pass)
+    }
+
+    /// The settings the last pass of the renderer draws with. Renderers that draw in the
+    /// same pass, like those in a `Vec`, need compatible settings. Renderers that can't
+    /// change their settings draw with the default ones.
+    fn pass_settings(&self) -> RenderSettings {
+        RenderSettings::default()
+    }
+
     /// This is called every frame after the game updates. This is for any state that the renderer
     /// itself will have to maintain.
     fn update(&mut self, data: &GameData) {}
@@ -124,3 +203,23 @@ impl_downcast!(sync Renderer);
 pub struct EmptyRenderer;
 
 impl Renderer for EmptyRenderer {}
+
+#[test]
+fn sample_count_supported_test() {
+    let msaa = TextureFormatFeatureFlags::MULTISAMPLE;
+    let resolve = msaa | TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE;
+
+    assert!(sample_count_supported(
+        1,
+        TextureFormatFeatureFlags::empty(),
+        false,
+        false
+    ));
+    assert!(sample_count_supported(4, resolve, false, false));
+    assert!(!sample_count_supported(8, resolve, false, false));
+    assert!(sample_count_supported(8, resolve, false, true));
+    assert!(!sample_count_supported(4, msaa, false, true));
+    assert!(sample_count_supported(4, msaa, true, false));
+    assert!(!sample_count_supported(3, resolve, false, true));
+    assert!(!sample_count_supported(16, resolve, false, true));
+}
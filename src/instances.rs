@@ -0,0 +1,510 @@
+//! Storage of the per-instance values of models, and the buffers they are written to.
+
+use std::{mem::size_of, ops::Range};
+
+use downcast_rs::{impl_downcast, DowncastSync};
+use wgpu::Buffer;
+
+use crate::GameData;
+
+/// A handle to an instance of a `Model`. Unlike an index into the model's transforms, a
+/// handle keeps referring to the same instance when other instances are removed, and a
+/// handle to a removed instance is never reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InstanceId {
+    pub(crate) slot: u32,
+    pub(crate) generation: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+/// Attributes of an instance other than its transform, which are used by the
+/// `SimpleRenderer` pipelines.
+pub struct InstanceData {
+    /// A color that the color of every vertex or texture pixel is multiplied by.
+    pub color: [f32; 4],
+    /// The region of the texture used by the instance, as the position of its top left
+    /// corner followed by its size. Texture coordinates of the model are scaled by the size
+    /// and then offset by the position, so `[0.0, 0.0, 1.0, 1.0]` uses the whole texture.
+    pub uv_rect: [f32; 4],
+}
+
+impl InstanceData {
+    /// Returns the default instance data with its color set to `color`.
+    pub fn color(color: [f32; 4]) -> Self {
+        Self {
+            color,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the default instance data with its texture region set to `uv_rect`.
+    pub fn uv_rect(uv_rect: [f32; 4]) -> Self {
+        Self {
+            uv_rect,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the color, then returns the instance data.
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Sets the texture region, then returns the instance data.
+    pub fn with_uv_rect(mut self, uv_rect: [f32; 4]) -> Self {
+        self.uv_rect = uv_rect;
+        self
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Self>() as u64,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 6,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x4,
+                    offset: size_of::<[f32; 4]>() as u64,
+                    shader_location: 7,
+                },
+            ],
+        }
+    }
+}
+
+impl Default for InstanceData {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0, 1.0],
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// Keeps track of which index in a list of instances each `InstanceId` refers to.
+#[derive(Default)]
+pub(crate) struct InstanceSlots {
+    /// The generation and current index of each slot.
+    slots: Vec<(u32, Option<usize>)>,
+    /// The slot of each index.
+    owners: Vec<u32>,
+    /// Slots that are not in use.
+    free: Vec<u32>,
+}
+
+impl InstanceSlots {
+    pub(crate) fn with_len(len: usize) -> Self {
+        let mut slots = Self::default();
+        slots.reset(len);
+        slots
+    }
+
+    /// Makes a handle for an instance added to the end of the list.
+    pub(crate) fn insert(&mut self) -> InstanceId {
+        let index = self.owners.len();
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot as usize].1 = Some(index);
+                slot
+            }
+            None => {
+                self.slots.push((0, Some(index)));
+                self.slots.len() as u32 - 1
+            }
+        };
+        self.owners.push(slot);
+        InstanceId {
+            slot,
+            generation: self.slots[slot as usize].0,
+        }
+    }
+
+    /// The index of the instance of `id`.
+    pub(crate) fn get(&self, id: InstanceId) -> Option<usize> {
+        match self.slots.get(id.slot as usize) {
+            Some((generation, index)) if *generation == id.generation => *index,
+            _ => None,
+        }
+    }
+
+    /// Removes the handle, and moves the handle of the last instance to take its index
+    /// the same way as `Vec::swap_remove`. Returns the index that was removed.
+    pub(crate) fn swap_remove(&mut self, id: InstanceId) -> Option<usize> {
+        let index = self.get(id)?;
+        let slot = &mut self.slots[id.slot as usize];
+        slot.0 += 1;
+        slot.1 = None;
+        self.free.push(id.slot);
+
+        self.owners.swap_remove(index);
+        if let Some(moved) = self.owners.get(index) {
+            self.slots[*moved as usize].1 = Some(index);
+        }
+        Some(index)
+    }
+
+    /// Invalidates every handle, then makes new handles for `len` instances.
+    pub(crate) fn reset(&mut self, len: usize) {
+        for slot in self.owners.drain(..) {
+            let slot = &mut self.slots[slot as usize];
+            slot.0 += 1;
+            slot.1 = None;
+        }
+        self.free = (0..self.slots.len() as u32).rev().collect();
+        for _ in 0..len {
+            self.insert();
+        }
+    }
+
+    /// The handles of every instance in order of their index.
+    pub(crate) fn ids(&self) -> impl Iterator<Item = InstanceId> + '_ {
+        self.owners.iter().map(|slot| InstanceId {
+            slot: *slot,
+            generation: self.slots[*slot as usize].0,
+        })
+    }
+}
+
+#[test]
+fn instance_slots_test() {
+    let mut slots = InstanceSlots::with_len(3);
+    let ids = slots.ids().collect::<Vec<_>>();
+    assert_eq!(
+        ids.iter().map(|id| slots.get(*id)).collect::<Vec<_>>(),
+        [Some(0), Some(1), Some(2)]
+    );
+
+    // Removing the first instance moves the last into its place.
+    assert_eq!(slots.swap_remove(ids[0]), Some(0));
+    assert_eq!(slots.get(ids[0]), None);
+    assert_eq!(slots.get(ids[2]), Some(0));
+    assert_eq!(slots.get(ids[1]), Some(1));
+    assert_eq!(slots.swap_remove(ids[0]), None);
+
+    // The freed slot is reused with a new generation.
+    let new = slots.insert();
+    assert_eq!(slots.get(new), Some(2));
+    assert_eq!(slots.get(ids[0]), None);
+
+    slots.reset(1);
+    assert!(ids.iter().all(|id| slots.get(*id).is_none()));
+    assert_eq!(slots.ids().count(), 1);
+}
+
+/// A list with a value for every instance of a model, and the vertex buffer the values are
+/// written to. Only the values that have changed are written to the buffer.
+pub(crate) struct InstanceBuffer<T> {
+    pub(crate) values: Vec<T>,
+    pub(crate) buffer: Buffer,
+    /// The number of values that fit in `buffer`.
+    capacity: usize,
+    /// The size in bytes of a single value in `buffer`.
+    stride: usize,
+    /// Converts values to the bytes written to `buffer`.
+    bytes: fn(&[T]) -> Vec<u8>,
+    outdated: Outdated,
+    /// Set when changes were reported by `InstanceBuffer::defer` without being written.
+    deferred: bool,
+}
+
+/// Which values of an `InstanceBuffer` need to be written to its buffer.
+#[derive(Default)]
+struct Outdated {
+    /// The ranges of values that have changed since the buffer was last written.
+    ranges: Vec<Range<usize>>,
+    /// Set when every value needs to be rewritten.
+    all: bool,
+    /// The number of values when the buffer was last written.
+    written_len: usize,
+}
+
+impl Outdated {
+    fn mark(&mut self, index: usize) {
+        match self.ranges.last_mut() {
+            Some(last) if last.end == index => last.end += 1,
+            Some(last) if last.contains(&index) => {}
+            _ => self.ranges.push(index..index + 1),
+        }
+    }
+
+    /// Whether anything has changed since the buffer was last written, including the number
+    /// of values, which changes without marking anything when the last value is removed.
+    fn any(&self, len: usize) -> bool {
+        self.all || !self.ranges.is_empty() || self.written_len != len
+    }
+
+    fn clear(&mut self, len: usize) {
+        self.ranges.clear();
+        self.all = false;
+        self.written_len = len;
+    }
+}
+
+impl<T: bytemuck::Pod> InstanceBuffer<T> {
+    /// Makes a buffer of values that are written as they are.
+    pub(crate) fn new_pod(data: &GameData, values: Vec<T>) -> Self {
+        Self::new(data, values, size_of::<T>(), |values| {
+            bytemuck::cast_slice(values).to_vec()
+        })
+    }
+}
+
+impl<T> InstanceBuffer<T> {
+    pub(crate) fn new(
+        data: &GameData,
+        values: Vec<T>,
+        stride: usize,
+        bytes: fn(&[T]) -> Vec<u8>,
+    ) -> Self {
+        // The buffer always has room for at least one value, as empty buffers can't be bound.
+        let capacity = values.len().max(1);
+        let mut buffer = Self {
+            buffer: Self::make_buffer(data, capacity, stride),
+            values,
+            capacity,
+            stride,
+            bytes,
+            outdated: Outdated {
+                all: true,
+                ..Default::default()
+            },
+            deferred: false,
+        };
+        buffer.update(data);
+        buffer
+    }
+
+    fn make_buffer(data: &GameData, capacity: usize, stride: usize) -> Buffer {
+        data.graphics
+            .lock()
+            .device
+            .create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: (capacity * stride) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+    }
+
+    /// Marks a single value as needing to be written to the buffer.
+    pub(crate) fn mark(&mut self, index: usize) {
+        self.outdated.mark(index);
+    }
+
+    /// Marks every value as needing to be written to the buffer.
+    pub(crate) fn mark_all(&mut self) {
+        self.outdated.all = true;
+    }
+
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.values.len() {
+            self.mark(index);
+        }
+        self.values.get_mut(index)
+    }
+
+    pub(crate) fn push(&mut self, value: T) {
+        self.values.push(value);
+        self.mark(self.values.len() - 1);
+    }
+
+    pub(crate) fn swap_remove(&mut self, index: usize) -> T {
+        let value = self.values.swap_remove(index);
+        if index < self.values.len() {
+            self.mark(index);
+        }
+        value
+    }
+
+    /// Returns `true` if anything has changed since the buffer was last written or deferred,
+    /// without writing it. The next call to `InstanceBuffer::update` writes every value.
+    pub(crate) fn defer(&mut self) -> bool {
+        let changed = self.outdated.any(self.values.len());
+        if changed {
+            self.deferred = true;
+            self.outdated.clear(self.values.len());
+        }
+        changed
+    }
+
+    /// Writes the values that have changed to the buffer, making a bigger buffer if there
+    /// are more values than fit in it. Returns `true` if anything was written or the number
+    /// of values changed.
+    pub(crate) fn update(&mut self, data: &GameData) -> bool {
+        if self.deferred {
+            self.deferred = false;
+            self.outdated.all = true;
+        }
+        if self.values.len() > self.capacity {
+            self.capacity = self.values.len().next_power_of_two();
+            self.buffer = Self::make_buffer(data, self.capacity, self.stride);
+            self.outdated.all = true;
+        }
+
+        let changed = self.outdated.any(self.values.len());
+        let graphics = data.graphics.lock();
+        if self.outdated.all {
+            if !self.values.is_empty() {
+                graphics
+                    .queue
+                    .write_buffer(&self.buffer, 0, &(self.bytes)(&self.values));
+            }
+        } else {
+            for range in merge_ranges(&mut self.outdated.ranges) {
+                let range = range.start..range.end.min(self.values.len());
+                if range.is_empty() {
+                    continue;
+                }
+                graphics.queue.write_buffer(
+                    &self.buffer,
+                    (range.start * self.stride) as u64,
+                    &(self.bytes)(&self.values[range]),
+                );
+            }
+        }
+
+        self.outdated.clear(self.values.len());
+        changed
+    }
+}
+
+impl<T: Clone> InstanceBuffer<T> {
+    /// The bytes of the values at `indices`, in the same layout as the buffer.
+    pub(crate) fn gather(&self, indices: &[usize]) -> Vec<u8> {
+        (self.bytes)(
+            &indices
+                .iter()
+                .map(|index| self.values[*index].clone())
+                .collect::<Vec<T>>(),
+        )
+    }
+}
+
+impl<T: Default> InstanceBuffer<T> {
+    /// Replaces every value with the default value, keeping the same number of values.
+    pub(crate) fn reset(&mut self, len: usize) {
+        self.values.clear();
+        self.values.resize_with(len, T::default);
+        self.mark_all();
+    }
+}
+
+/// A type erased `InstanceBuffer` of custom values, so that a model can hold any type of
+/// custom instance data.
+pub(crate) trait CustomInstances: DowncastSync {
+    fn push_default(&mut self);
+    fn swap_remove(&mut self, index: usize);
+    fn reset(&mut self, len: usize);
+    fn defer(&mut self) -> bool;
+    fn update(&mut self, data: &GameData) -> bool;
+    fn gather(&self, indices: &[usize]) -> Vec<u8>;
+    fn buffer(&self) -> &Buffer;
+}
+
+impl_downcast!(sync CustomInstances);
+
+impl<T: bytemuck::Pod + Default + Send + Sync> CustomInstances for InstanceBuffer<T> {
+    fn push_default(&mut self) {
+        self.push(T::default());
+    }
+
+    fn swap_remove(&mut self, index: usize) {
+        InstanceBuffer::swap_remove(self, index);
+    }
+
+    fn reset(&mut self, len: usize) {
+        InstanceBuffer::reset(self, len);
+    }
+
+    fn defer(&mut self) -> bool {
+        InstanceBuffer::defer(self)
+    }
+
+    fn update(&mut self, data: &GameData) -> bool {
+        InstanceBuffer::update(self, data)
+    }
+
+    fn gather(&self, indices: &[usize]) -> Vec<u8> {
+        InstanceBuffer::gather(self, indices)
+    }
+
+    fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+}
+
+/// A vertex buffer that is entirely rewritten whenever it changes, such as for the
+/// instances of a model that are left after culling.
+pub(crate) struct StreamingBuffer {
+    pub(crate) buffer: Buffer,
+    /// The size of `buffer` in bytes.
+    capacity: usize,
+}
+
+impl StreamingBuffer {
+    pub(crate) fn new(data: &GameData, contents: &[u8]) -> Self {
+        let mut buffer = Self {
+            buffer: InstanceBuffer::<u8>::make_buffer(data, 4, 1),
+            capacity: 4,
+        };
+        buffer.write(data, contents);
+        buffer
+    }
+
+    /// Replaces the contents of the buffer, making a bigger buffer if they don't fit.
+    pub(crate) fn write(&mut self, data: &GameData, contents: &[u8]) {
+        if contents.len() > self.capacity {
+            self.capacity = contents.len().next_power_of_two();
+            self.buffer = InstanceBuffer::<u8>::make_buffer(data, self.capacity, 1);
+        }
+        if !contents.is_empty() {
+            data.graphics
+                .lock()
+                .queue
+                .write_buffer(&self.buffer, 0, contents);
+        }
+    }
+}
+
+/// Sorts `ranges` and joins together the ones that overlap or touch.
+fn merge_ranges(ranges: &mut [Range<usize>]) -> Vec<Range<usize>> {
+    ranges.sort_unstable_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges.iter() {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range.clone()),
+        }
+    }
+    merged
+}
+
+#[test]
+fn merge_ranges_test() {
+    assert_eq!(
+        merge_ranges(&mut [4..5, 0..2, 1..3, 3..4, 8..9]),
+        [0..5, 8..9]
+    );
+}
+
+#[test]
+fn outdated_remove_last_test() {
+    let mut outdated = Outdated::default();
+    let mut values = vec![0, 1, 2];
+    assert!(outdated.any(values.len()));
+    outdated.clear(values.len());
+    assert!(!outdated.any(values.len()));
+
+    // Removing the last value marks nothing, but the buffer still needs updating.
+    values.swap_remove(2);
+    assert!(outdated.ranges.is_empty());
+    assert!(outdated.any(values.len()));
+    outdated.clear(values.len());
+    assert!(!outdated.any(values.len()));
+}
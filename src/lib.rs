@@ -1,9 +1,29 @@
 #![doc = include_str!("../README.md")]
+pub mod animation;
+pub mod atlas;
+pub mod bounds;
+pub mod compressed;
+pub mod dynamic;
+pub mod graph;
 pub mod graphics;
 pub mod input;
+pub mod instances;
+pub mod materials;
 pub mod math;
+pub mod mesh;
+pub mod particles;
+pub mod postprocess;
 pub mod rand;
 pub mod renderers;
+pub mod scene;
+pub mod shaders;
+pub mod skybox;
+pub mod sprites;
+pub mod targets;
+pub mod terrain;
+pub mod text;
+pub mod tiled;
+pub mod tilemap;
 
 use std::{
     sync::Arc,
@@ -14,6 +34,7 @@ use glam::UVec2;
 use graphics::{Graphics, Renderer};
 use input::Input;
 use parking_lot::Mutex;
+use shaders::ShaderLibrary;
 use winit::{
     dpi::PhysicalSize,
     event::{Event, WindowEvent},
@@ -36,6 +57,8 @@ pub struct GameData {
     pub graphics: Arc<Mutex<Graphics>>,
     /// A handle to the input handler.
     pub input: Arc<Mutex<Input>>,
+    /// A handle to the shaders that can be included, and the modules compiled from them.
+    pub shaders: Arc<Mutex<ShaderLibrary>>,
     /// A handle to the winit window.
     pub window: Arc<Mutex<Window>>,
     /// A handle to the Exit code. It is recommended to use `GameData::exit` instead
@@ -104,6 +127,7 @@ where
             start_time: Instant::now(),
             graphics: Arc::new(Mutex::new(pollster::block_on(Graphics::new(&window)))),
             input: Arc::new(Mutex::new(Input::new())),
+            shaders: Arc::new(Mutex::new(ShaderLibrary::new())),
             window: Arc::new(Mutex::new(window)),
             exit_code: Arc::new(Mutex::new(None)),
         };
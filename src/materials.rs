@@ -0,0 +1,295 @@
+//! Materials, which draw the models of a `SimpleRenderer` with a custom shader instead of
+//! the default pipelines.
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use bytemuck::Pod;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupLayout, Buffer, RenderPass, RenderPipeline,
+};
+
+use crate::{
+    renderers::{BlendMode, ModelShader, RenderSettings, Texture},
+    shaders::{self, ShaderFile},
+    GameData,
+};
+
+/// A custom shader for models, with a set of uniforms and any number of extra bind groups.
+///
+/// The shader of a material includes `simple.wgsl`, which gives it the vertex and instance
+/// structs, the `projection` and `camera` uniforms in group 0, and the `color_vertex` and
+/// `texture_vertex` entry points. Materials for textured models also get the texture of the
+/// model in group 1. The uniforms of the material are bound to binding 0 of the group after
+/// that, which is given by `Material::group`, and each texture or bind group added to the
+/// material takes the next group.
+///
+/// The shader is preprocessed by the `ShaderLibrary` with the defines of the material. The
+/// fragment entry point is `fragment` unless it is changed, and is used for every blend
+/// mode, but `ALPHA_CUTOUT` is defined for `BlendMode::AlphaCutout` models.
+pub struct Material {
+    /// The name of the material, to find it in a `SimpleRenderer`.
+    pub name: String,
+    /// The shader, which is preprocessed after `simple.wgsl`.
+    source: String,
+    shader_file: Option<ShaderFile>,
+    defines: Vec<(String, String)>,
+    textured: bool,
+    vertex_entry_point: String,
+    fragment_entry_point: String,
+    uniforms: Vec<u8>,
+    uniforms_outdated: bool,
+    uniform_buffer: Buffer,
+    uniform_bind_group: BindGroup,
+    bind_groups: Vec<(BindGroupLayout, Arc<BindGroup>)>,
+    pub(crate) pipelines: Option<[RenderPipeline; 5]>,
+}
+
+impl Material {
+    /// Create a `Material` for models with color vertices.
+    pub fn color<T: Pod>(data: &GameData, name: &str, shader: &str, uniforms: T) -> Self {
+        Self::new(data, name, shader, false, uniforms)
+    }
+
+    /// Create a `Material` for models with texture vertices.
+    pub fn texture<T: Pod>(data: &GameData, name: &str, shader: &str, uniforms: T) -> Self {
+        Self::new(data, name, shader, true, uniforms)
+    }
+
+    fn new<T: Pod>(data: &GameData, name: &str, shader: &str, textured: bool, uniforms: T) -> Self {
+        let uniforms = bytemuck::bytes_of(&uniforms).to_vec();
+        // Uniform buffers are padded to a multiple of 16 bytes by WGSL.
+        let mut contents = uniforms.clone();
+        contents.resize(contents.len().div_ceil(16).max(1) * 16, 0);
+        let uniform_buffer =
+            data.graphics
+                .lock()
+                .device
+                .create_buffer_init(&BufferInitDescriptor {
+                    label: None,
+                    contents: &contents,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+        let uniform_bind_group =
+            data.graphics
+                .lock()
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    layout: &Self::uniform_bind_group_layout(data),
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    }],
+                });
+
+        Self {
+            name: name.to_string(),
+            source: shader.to_string(),
+            shader_file: None,
+            defines: Vec::new(),
+            textured,
+            vertex_entry_point: match textured {
+                true => "texture_vertex",
+                false => "color_vertex",
+            }
+            .to_string(),
+            fragment_entry_point: "fragment".to_string(),
+            uniforms,
+            uniforms_outdated: false,
+            uniform_buffer,
+            uniform_bind_group,
+            bind_groups: Vec::new(),
+            pipelines: None,
+        }
+    }
+
+    /// Modifies the vertex entry point of the material, then returns the material.
+    pub fn with_vertex_entry_point(mut self, entry_point: &str) -> Self {
+        self.vertex_entry_point = entry_point.to_string();
+        self.pipelines = None;
+        self
+    }
+
+    /// Modifies the fragment entry point of the material, then returns the material.
+    pub fn with_fragment_entry_point(mut self, entry_point: &str) -> Self {
+        self.fragment_entry_point = entry_point.to_string();
+        self.pipelines = None;
+        self
+    }
+
+    /// Replaces the shader of the material with the WGSL file at `path`, which is reloaded
+    /// whenever it changes once the material is added to a `SimpleRenderer`. If the file is
+    /// changed to have an error it is printed, and the last pipelines without errors are
+    /// kept.
+    pub fn with_shader_file<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let mut file = ShaderFile::new(&path);
+        let shader = file
+            .poll()
+            .with_context(|| format!("Can't read {}", path.as_ref().display()))?;
+        self.source = shader;
+        self.shader_file = Some(file);
+        Ok(self)
+    }
+
+    /// Defines `name` as `value` when the shader is preprocessed, then returns the material.
+    pub fn with_define(mut self, name: &str, value: &str) -> Self {
+        self.defines.push((name.to_string(), value.to_string()));
+        self.pipelines = None;
+        self
+    }
+
+    /// Adds a texture in the next group, at bindings 0 and 1 like the texture of a model,
+    /// then returns the material.
+    pub fn with_texture(self, data: &GameData, texture: &Texture) -> Self {
+        self.with_bind_group(Texture::bind_group_layout(data), texture.diffuse.clone())
+    }
+
+    /// Adds a bind group made with `layout` in the next group, then returns the material.
+    pub fn with_bind_group(mut self, layout: BindGroupLayout, bind_group: Arc<BindGroup>) -> Self {
+        self.bind_groups.push((layout, bind_group));
+        self.pipelines = None;
+        self
+    }
+
+    /// Whether the material draws models with texture vertices.
+    pub fn textured(&self) -> bool {
+        self.textured
+    }
+
+    /// The group the uniforms of the material are bound to.
+    pub fn group(&self) -> u32 {
+        1 + self.textured as u32
+    }
+
+    /// The uniforms of the material. Panics if `T` isn't the size of the uniforms the
+    /// material was made with.
+    pub fn uniforms<T: Pod>(&self) -> T {
+        bytemuck::pod_read_unaligned(&self.uniforms)
+    }
+
+    /// Replaces the uniforms of the material, which are written before the next frame.
+    /// Panics if `T` isn't the size of the uniforms the material was made with.
+    pub fn set_uniforms<T: Pod>(&mut self, uniforms: T) {
+        let uniforms = bytemuck::bytes_of(&uniforms);
+        assert_eq!(
+            uniforms.len(),
+            self.uniforms.len(),
+            "The uniforms of a material can't change size"
+        );
+        self.uniforms.copy_from_slice(uniforms);
+        self.uniforms_outdated = true;
+    }
+
+    /// The layout of the group with the uniforms of a material.
+    pub fn uniform_bind_group_layout(data: &GameData) -> BindGroupLayout {
+        data.graphics
+            .lock()
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            })
+    }
+
+    /// Makes the pipelines of the material for every blend mode, drawing with the formats
+    /// and sample count of `settings`, or keeps them as they were if the shader has an error.
+    pub(crate) fn make_pipelines(
+        &mut self,
+        data: &GameData,
+        settings: &RenderSettings,
+    ) -> Result<()> {
+        self.pipelines = Some(self.build_pipelines(data, settings)?);
+        Ok(())
+    }
+
+    /// Makes the pipelines of the material for `settings` without replacing its own.
+    pub(crate) fn build_pipelines(
+        &self,
+        data: &GameData,
+        settings: &RenderSettings,
+    ) -> Result<[RenderPipeline; 5]> {
+        let uniform_bind_group_layout = Self::uniform_bind_group_layout(data);
+        let mut bind_group_layouts = vec![&uniform_bind_group_layout];
+        bind_group_layouts.extend(self.bind_groups.iter().map(|(layout, _)| layout));
+
+        let source = format!("#include \"simple.wgsl\"\n{}", self.source);
+        let mut shaders = data.shaders.lock();
+        let modules = BlendMode::ALL.map(|blend_mode| {
+            let defines: Vec<_> = self
+                .defines
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .chain(blend_mode.defines().iter().copied())
+                .collect();
+            shaders.module(data, &self.name, &source, &defines)
+        });
+        drop(shaders);
+        let modules = modules.into_iter().collect::<Result<Vec<_>>>()?;
+
+        shaders::validate(data, || {
+            BlendMode::ALL.map(|blend_mode| {
+                ModelShader {
+                    label: &self.name,
+                    module: &modules[blend_mode as usize],
+                    vertex_entry_point: &self.vertex_entry_point,
+                    fragment_entry_point: &self.fragment_entry_point,
+                    textured: self.textured,
+                    bind_group_layouts: &bind_group_layouts,
+                }
+                .pipeline(data, blend_mode, settings)
+            })
+        })
+    }
+
+    /// Reloads the shader file if it has changed, printing any error in it, and writes the
+    /// uniforms if they have changed.
+    pub(crate) fn update(&mut self, data: &GameData, settings: &RenderSettings) {
+        if let Some(shader) = self.shader_file.as_mut().and_then(ShaderFile::poll) {
+            let old_source = std::mem::replace(&mut self.source, shader);
+            if let Err(err) = self.make_pipelines(data, settings) {
+                let path = &self.shader_file.as_ref().unwrap().path;
+                eprintln!("{}: {err}", path.display());
+                self.source = old_source;
+            }
+        }
+
+        if self.uniforms_outdated {
+            data.graphics
+                .lock()
+                .queue
+                .write_buffer(&self.uniform_buffer, 0, &self.uniforms);
+            self.uniforms_outdated = false;
+        }
+    }
+
+    /// Sets the pipeline for `blend_mode` and the bind groups of the material. Returns
+    /// `false` if the pipelines haven't been made.
+    pub(crate) fn bind<'a>(
+        &'a self,
+        render_pass: &mut RenderPass<'a>,
+        blend_mode: BlendMode,
+    ) -> bool {
+        let pipelines = match &self.pipelines {
+            Some(pipelines) => pipelines,
+            None => return false,
+        };
+        render_pass.set_pipeline(&pipelines[blend_mode as usize]);
+        render_pass.set_bind_group(self.group(), &self.uniform_bind_group, &[]);
+        for (i, (_, bind_group)) in self.bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(self.group() + 1 + i as u32, bind_group, &[]);
+        }
+        true
+    }
+}
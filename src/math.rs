@@ -2,6 +2,8 @@
 
 use std::ops::{Add, Mul, Sub};
 
+use glam::Vec2;
+
 /// An implementation of linear interpolation.
 pub fn lerp<T, U>(a: T, b: T, weight: U) -> T
 where
@@ -40,3 +42,43 @@ where
 {
     (b - a) * ((weight * (weight * 6.0 - 15.0) + 10.0) * weight * weight * weight) + a
 }
+
+/// An axis aligned rectangle.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    /// The corner with the lowest coordinates.
+    pub position: Vec2,
+    /// The width and height of the rectangle.
+    pub size: Vec2,
+}
+
+impl Rect {
+    /// Create a `Rect` from the position of its lowest corner and its size.
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            position: Vec2::new(x, y),
+            size: Vec2::new(width, height),
+        }
+    }
+
+    /// The corner with the highest coordinates.
+    pub fn end(&self) -> Vec2 {
+        self.position + self.size
+    }
+
+    /// Returns `true` if `point` is inside the rectangle.
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.position).all() && point.cmplt(self.end()).all()
+    }
+
+    /// Returns `true` if the rectangle and `other` cover any of the same area.
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.position.cmplt(other.end()).all() && other.position.cmplt(self.end()).all()
+    }
+}
+
+impl From<Rect> for [f32; 4] {
+    fn from(rect: Rect) -> Self {
+        [rect.position.x, rect.position.y, rect.size.x, rect.size.y]
+    }
+}
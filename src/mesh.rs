@@ -0,0 +1,303 @@
+//! Generation of meshes on the CPU which can then be turned into a `Model`.
+
+use std::f32::consts::{PI, TAU};
+
+use glam::{Vec2, Vec3};
+
+use crate::{
+    renderers::{ColorVertex, Model, Texture, TextureVertex, Transform, VertexSlice},
+    GameData,
+};
+
+/// A single vertex of a `Mesh`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshVertex {
+    /// The position of the vertex.
+    pub pos: Vec3,
+    /// The direction the surface faces at this vertex.
+    pub normal: Vec3,
+    /// The texture coordinate of the vertex.
+    pub tex_coords: Vec2,
+}
+
+/// A list of vertices and the indices of the triangles they make. Triangles are wound
+/// counter-clockwise when looked at from the front, the same as the `SimpleRenderer`
+/// pipelines expect.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    /// The vertices of the mesh.
+    pub vertices: Vec<MeshVertex>,
+    /// Every three indices is a triangle made of the vertices at those indices.
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    /// Makes a UV sphere centred on the origin. `sectors` is the number of segments around
+    /// the Y axis and `stacks` is the number of segments from the top to the bottom.
+    pub fn sphere(radius: f32, sectors: u32, stacks: u32) -> Self {
+        let sectors = sectors.max(3);
+        let stacks = stacks.max(2);
+
+        Self::grid(sectors, stacks, |sector, stack| {
+            let normal = sphere_normal(
+                TAU * sector as f32 / sectors as f32,
+                PI * stack as f32 / stacks as f32,
+            );
+            (normal * radius, normal)
+        })
+    }
+
+    /// Makes a cylinder standing on the Y axis and centred on the origin, with caps on
+    /// both ends.
+    pub fn cylinder(radius: f32, height: f32, segments: u32) -> Self {
+        let segments = segments.max(3);
+
+        let mut mesh = Self::grid(segments, 1, |segment, row| {
+            let normal = ring_normal(TAU * segment as f32 / segments as f32);
+            let y = height / 2.0 - height * row as f32;
+            (normal * radius + Vec3::Y * y, normal)
+        });
+        mesh.append(Self::disc(radius, height / 2.0, segments, true));
+        mesh.append(Self::disc(radius, -height / 2.0, segments, false));
+        mesh
+    }
+
+    /// Makes a cone standing on the Y axis and centred on the origin, with the point at
+    /// the top and a cap on the base.
+    pub fn cone(radius: f32, height: f32, segments: u32) -> Self {
+        let segments = segments.max(3);
+
+        let mut mesh = Self::grid(segments, 1, |segment, row| {
+            let angle = TAU * segment as f32 / segments as f32;
+            let ring = ring_normal(angle);
+            let normal = (ring * height + Vec3::Y * radius).normalize();
+            let pos = ring * radius * row as f32 + Vec3::Y * (height / 2.0 - height * row as f32);
+            (pos, normal)
+        });
+        mesh.append(Self::disc(radius, -height / 2.0, segments, false));
+        mesh
+    }
+
+    /// Makes a torus lying flat on the XZ plane and centred on the origin. `radius` is the
+    /// distance from the origin to the centre of the tube, and `tube_radius` is the radius
+    /// of the tube itself.
+    pub fn torus(radius: f32, tube_radius: f32, segments: u32, tube_segments: u32) -> Self {
+        let segments = segments.max(3);
+        let tube_segments = tube_segments.max(3);
+
+        Self::grid(segments, tube_segments, |segment, tube_segment| {
+            let ring = ring_normal(TAU * segment as f32 / segments as f32);
+            let angle = TAU * tube_segment as f32 / tube_segments as f32;
+            let normal = ring * angle.cos() - Vec3::Y * angle.sin();
+            (ring * radius + normal * tube_radius, normal)
+        })
+    }
+
+    /// Makes a flat grid on the XZ plane facing up and centred on the origin. `size` is
+    /// the length of the grid on the X and Z axes, and `subdivisions` is the number of
+    /// cells along each of those axes.
+    pub fn plane(size: Vec2, subdivisions: (u32, u32)) -> Self {
+        let columns = subdivisions.0.max(1);
+        let rows = subdivisions.1.max(1);
+
+        Self::grid(columns, rows, |column, row| {
+            let offset = Vec2::new(column as f32 / columns as f32, row as f32 / rows as f32);
+            let pos = (offset - 0.5) * size;
+            (Vec3::new(pos.x, 0.0, pos.y), Vec3::Y)
+        })
+    }
+
+    /// Makes a capsule standing on the Y axis and centred on the origin. `length` is the
+    /// length of the cylindrical section, so the full height of the capsule is
+    /// `length + radius * 2.0`. `rings` is the number of segments in each hemisphere.
+    pub fn capsule(radius: f32, length: f32, segments: u32, rings: u32) -> Self {
+        let segments = segments.max(3);
+        let rings = rings.max(1);
+
+        Self::grid(segments, rings * 2 + 1, |segment, row| {
+            let (angle, y) = if row <= rings {
+                (PI / 2.0 * row as f32 / rings as f32, length / 2.0)
+            } else {
+                let row = row - rings - 1;
+                (PI / 2.0 * (1.0 + row as f32 / rings as f32), -length / 2.0)
+            };
+            let normal = sphere_normal(TAU * segment as f32 / segments as f32, angle);
+            (normal * radius + Vec3::Y * y, normal)
+        })
+    }
+
+    /// Adds the vertices and triangles of `other` to this mesh.
+    pub fn append(&mut self, other: Mesh) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices);
+        self.indices
+            .extend(other.indices.into_iter().map(|index| index + offset));
+    }
+
+    /// Converts the mesh into a list of `ColorVertex`s which are all `color`.
+    pub fn color_vertices(&self, color: [f32; 4]) -> Vec<ColorVertex> {
+        self.vertices
+            .iter()
+            .map(|vertex| ColorVertex {
+                pos: vertex.pos.into(),
+                color,
+            })
+            .collect()
+    }
+
+    /// Converts the mesh into a list of `TextureVertex`s.
+    pub fn texture_vertices(&self) -> Vec<TextureVertex> {
+        self.vertices
+            .iter()
+            .map(|vertex| TextureVertex {
+                pos: vertex.pos.into(),
+                tex_coords: vertex.tex_coords.into(),
+            })
+            .collect()
+    }
+
+    /// Makes a model of the mesh where every vertex is `color`.
+    pub fn color_model(
+        &self,
+        data: &GameData,
+        color: [f32; 4],
+        transforms: Vec<Transform>,
+    ) -> Model {
+        Model::new(
+            data,
+            VertexSlice::ColorVertices(&self.color_vertices(color)),
+            &self.indices,
+            transforms,
+        )
+    }
+
+    /// Makes a model of the mesh with `texture` mapped on to it.
+    pub fn texture_model(
+        &self,
+        data: &GameData,
+        texture: Texture,
+        transforms: Vec<Transform>,
+    ) -> Model {
+        Model::new(
+            data,
+            VertexSlice::TextureVertices(&self.texture_vertices(), texture),
+            &self.indices,
+            transforms,
+        )
+    }
+
+    /// Makes a grid of `(columns + 1) * (rows + 1)` vertices, with positions and normals
+    /// decided by `vertex`. Seen from the front, columns go from left to right and rows go
+    /// from top to bottom. Triangles with no area, such as at the poles of a sphere, are
+    /// left out.
+    pub(crate) fn grid<F: Fn(u32, u32) -> (Vec3, Vec3)>(
+        columns: u32,
+        rows: u32,
+        vertex: F,
+    ) -> Self {
+        let mut vertices = Vec::with_capacity(((columns + 1) * (rows + 1)) as usize);
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (pos, normal) = vertex(column, row);
+                vertices.push(MeshVertex {
+                    pos,
+                    normal,
+                    tex_coords: Vec2::new(column as f32 / columns as f32, row as f32 / rows as f32),
+                });
+            }
+        }
+
+        let mut indices = Vec::with_capacity((columns * rows * 6) as usize);
+        for row in 0..rows {
+            for column in 0..columns {
+                let top_left = row * (columns + 1) + column;
+                let bottom_left = top_left + columns + 1;
+                for triangle in [
+                    [top_left, bottom_left, top_left + 1],
+                    [top_left + 1, bottom_left, bottom_left + 1],
+                ] {
+                    let [a, b, c] = triangle.map(|index| vertices[index as usize].pos);
+                    if (b - a).cross(c - a).length_squared() > f32::EPSILON * f32::EPSILON {
+                        indices.extend(triangle);
+                    }
+                }
+            }
+        }
+
+        Self { vertices, indices }
+    }
+
+    /// Makes a flat circle on the XZ plane at height `y`, facing up if `up` is `true` and
+    /// down otherwise.
+    fn disc(radius: f32, y: f32, segments: u32, up: bool) -> Self {
+        let normal = if up { Vec3::Y } else { Vec3::NEG_Y };
+        let mut vertices = vec![MeshVertex {
+            pos: Vec3::Y * y,
+            normal,
+            tex_coords: Vec2::splat(0.5),
+        }];
+        let mut indices = Vec::with_capacity(segments as usize * 3);
+
+        for segment in 0..=segments {
+            let ring = ring_normal(TAU * segment as f32 / segments as f32);
+            vertices.push(MeshVertex {
+                pos: ring * radius + Vec3::Y * y,
+                normal,
+                tex_coords: Vec2::new(0.5 + ring.x / 2.0, 0.5 + ring.z / 2.0),
+            });
+
+            if segment != 0 {
+                if up {
+                    indices.extend([0, segment, segment + 1]);
+                } else {
+                    indices.extend([0, segment + 1, segment]);
+                }
+            }
+        }
+
+        Self { vertices, indices }
+    }
+}
+
+/// The direction outwards from the Y axis at `angle` radians around it.
+fn ring_normal(angle: f32) -> Vec3 {
+    Vec3::new(angle.sin(), 0.0, angle.cos())
+}
+
+/// The direction outwards from the centre of a sphere, where `angle` is around the Y axis
+/// and `pitch` is the angle down from the top.
+fn sphere_normal(angle: f32, pitch: f32) -> Vec3 {
+    ring_normal(angle) * pitch.sin() + Vec3::Y * pitch.cos()
+}
+
+#[cfg(test)]
+fn assert_winding(mesh: &Mesh) {
+    for triangle in mesh.indices.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+        let face = (b.pos - a.pos).cross(c.pos - a.pos);
+        let normal = a.normal + b.normal + c.normal;
+        assert!(face.dot(normal) > 0.0, "{triangle:?} is wound backwards");
+    }
+}
+
+#[test]
+fn mesh_winding_test() {
+    assert_winding(&Mesh::sphere(1.0, 16, 8));
+    assert_winding(&Mesh::cylinder(1.0, 2.0, 16));
+    assert_winding(&Mesh::cone(1.0, 2.0, 16));
+    assert_winding(&Mesh::torus(1.0, 0.25, 16, 8));
+    assert_winding(&Mesh::plane(Vec2::new(4.0, 2.0), (4, 2)));
+    assert_winding(&Mesh::capsule(0.5, 1.0, 16, 4));
+}
+
+#[test]
+fn mesh_size_test() {
+    let plane = Mesh::plane(Vec2::ONE, (4, 2));
+    assert_eq!(plane.vertices.len(), 15);
+    assert_eq!(plane.indices.len(), 48);
+
+    // The triangles touching the poles are skipped.
+    let sphere = Mesh::sphere(1.0, 8, 4);
+    assert_eq!(sphere.vertices.len(), 45);
+    assert_eq!(sphere.indices.len(), (8 * 4 * 2 - 16) * 3);
+}
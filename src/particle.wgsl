@@ -0,0 +1,45 @@
+#include "common.wgsl"
+
+struct ParticleInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct ParticleOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn particle_vertex(input: ParticleInput, transform: Transform, instance: InstanceData) -> ParticleOutput {
+    // The rotation and scale of the transform are applied facing the camera, and only the
+    // translation is moved by the camera.
+    let offset = transform_matrix(transform) * vec4<f32>(input.pos, 0.0);
+    let centre = camera * vec4<f32>(transform.data3.xyz, 1.0);
+
+    var output: ParticleOutput;
+    output.pos = projection * (centre + vec4<f32>(offset.xy, 0.0, 0.0));
+    output.tex_coords = instance.uv_rect.xy + input.tex_coords * instance.uv_rect.zw;
+    output.color = instance.color;
+    return output;
+}
+
+@group(1)@binding(0)
+var texture: texture_2d<f32>;
+@group(1)@binding(1)
+var texture_sampler: sampler;
+
+@fragment
+fn particle_fragment(output: ParticleOutput) -> @location(0) vec4<f32> {
+    return textureSample(texture, texture_sampler, output.tex_coords) * output.color;
+}
+
+@fragment
+fn particle_cutout_fragment(output: ParticleOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(texture, texture_sampler, output.tex_coords) * output.color;
+    if (color.a < 0.5) {
+        discard;
+    }
+    return color;
+}
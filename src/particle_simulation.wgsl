@@ -0,0 +1,79 @@
+struct Particle {
+    // The age is stored in `w`.
+    position: vec4<f32>,
+    // The lifetime is stored in `w`.
+    velocity: vec4<f32>,
+    // The rotation followed by the angular velocity.
+    rotation: vec4<f32>,
+}
+
+struct InstanceData {
+    color: vec4<f32>,
+    uv_rect: vec4<f32>,
+}
+
+struct Params {
+    // The time since the last step is stored in `w`.
+    acceleration: vec4<f32>,
+    // The color and size over the life of a particle, sampled at even steps.
+    colors: array<vec4<f32>, 8>,
+    sizes: array<vec4<f32>, 2>,
+}
+
+@group(0)@binding(0)
+var<storage, read_write> particles: array<Particle>;
+@group(0)@binding(1)
+var<storage, read_write> transforms: array<mat4x4<f32>>;
+@group(0)@binding(2)
+var<storage, read_write> instances: array<InstanceData>;
+@group(0)@binding(3)
+var<uniform> params: Params;
+
+fn size(index: u32) -> f32 {
+    return params.sizes[index / 4u][index % 4u];
+}
+
+@compute @workgroup_size(64)
+fn simulate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= arrayLength(&particles)) {
+        return;
+    }
+
+    var particle = particles[index];
+    let delta_time = params.acceleration.w;
+    particle.position.w = particle.position.w + delta_time;
+    if (particle.position.w >= particle.velocity.w) {
+        // Dead particles are scaled to nothing so that they aren't drawn.
+        transforms[index] = mat4x4<f32>(
+            vec4<f32>(0.0),
+            vec4<f32>(0.0),
+            vec4<f32>(0.0),
+            vec4<f32>(0.0, 0.0, 0.0, 1.0),
+        );
+        particles[index] = particle;
+        return;
+    }
+
+    particle.velocity = vec4<f32>(particle.velocity.xyz + params.acceleration.xyz * delta_time, particle.velocity.w);
+    particle.position = vec4<f32>(particle.position.xyz + particle.velocity.xyz * delta_time, particle.position.w);
+    particle.rotation.x = particle.rotation.x + particle.rotation.y * delta_time;
+    particles[index] = particle;
+
+    let life = clamp(particle.position.w / particle.velocity.w, 0.0, 1.0) * 7.0;
+    let first = u32(floor(life));
+    let next = min(first + 1u, 7u);
+    let amount = life - floor(life);
+    let color = mix(params.colors[first], params.colors[next], amount);
+    let scale = mix(size(first), size(next), amount);
+
+    let c = cos(particle.rotation.x) * scale;
+    let s = sin(particle.rotation.x) * scale;
+    transforms[index] = mat4x4<f32>(
+        vec4<f32>(c, s, 0.0, 0.0),
+        vec4<f32>(-s, c, 0.0, 0.0),
+        vec4<f32>(0.0, 0.0, scale, 0.0),
+        vec4<f32>(particle.position.xyz, 1.0),
+    );
+    instances[index] = InstanceData(color, vec4<f32>(0.0, 0.0, 1.0, 1.0));
+}
@@ -0,0 +1,985 @@
+//! Emitters of many small, short lived billboards, such as sparks, smoke and explosions.
+
+use std::{
+    mem::size_of,
+    ops::{Add, Mul, Range},
+};
+
+use glam::{Mat4, Quat, Vec3, Vec4, Vec4Swizzles};
+use image::{DynamicImage, Rgba, RgbaImage};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, Buffer, ComputePipeline, RenderPipeline, Sampler, TextureView,
+};
+
+use crate::{
+    graphics::Renderer,
+    instances::{InstanceBuffer, InstanceData},
+    rand::Noise,
+    renderers::{BlendMode, SimpleProjection, SimpleRenderer, Texture, TextureVertex, Transform},
+    GameData,
+};
+
+/// The number of points that curves are sampled at for particles simulated on the GPU.
+const CURVE_SAMPLES: usize = 8;
+
+/// A value that changes over the life of a particle, made of keys that are blended between.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve<T> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: Copy + Add<Output = T> + Mul<f32, Output = T>> Curve<T> {
+    /// Create a `Curve` from a list of keys, where each key is a point in the life of a
+    /// particle from 0 to 1 and the value at that point. Panics if there are no keys.
+    pub fn new(mut keys: Vec<(f32, T)>) -> Self {
+        assert!(!keys.is_empty(), "A curve needs at least one key");
+        keys.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { keys }
+    }
+
+    /// Create a `Curve` that is always `value`.
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![(0.0, value)])
+    }
+
+    /// Create a `Curve` that blends from `start` to `end`.
+    pub fn linear(start: T, end: T) -> Self {
+        Self::new(vec![(0.0, start), (1.0, end)])
+    }
+
+    /// The value at `time`, blended linearly between the keys either side of it.
+    pub fn sample(&self, time: f32) -> T {
+        let next = self.keys.partition_point(|(key, _)| *key <= time);
+        if next == 0 {
+            return self.keys[0].1;
+        }
+        let (start, from) = self.keys[next - 1];
+        match self.keys.get(next) {
+            Some((end, to)) => {
+                let amount = (time - start) / (end - start);
+                from * (1.0 - amount) + *to * amount
+            }
+            None => from,
+        }
+    }
+
+    /// Samples the curve at `CURVE_SAMPLES` even steps over the life of a particle.
+    fn samples(&self) -> [T; CURVE_SAMPLES] {
+        std::array::from_fn(|index| self.sample(index as f32 / (CURVE_SAMPLES - 1) as f32))
+    }
+}
+
+/// Where particles are moved each update.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ParticleSimulation {
+    /// Particles are moved on the CPU, where they can be read and sorted.
+    #[default]
+    Cpu,
+    /// Particles are spawned on the CPU but moved by a compute shader, which is much faster
+    /// for large numbers of particles. The particles can't be read from the emitter or
+    /// sorted, so only additive and cutout particles are drawn correctly, and particles with
+    /// any other transparent blend mode are drawn as additive. Compute shaders aren't
+    /// available on every platform, such as WebGL.
+    Gpu,
+}
+
+/// A single particle of a `ParticleEmitter`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    /// The position of the centre of the particle.
+    pub position: Vec3,
+    /// How far the particle moves each second.
+    pub velocity: Vec3,
+    /// The rotation of the particle around the direction the camera is facing in radians.
+    pub rotation: f32,
+    /// How far the particle rotates each second.
+    pub angular_velocity: f32,
+    /// How long the particle has existed in seconds.
+    pub age: f32,
+    /// How long the particle exists for in seconds.
+    pub lifetime: f32,
+}
+
+impl Particle {
+    /// How far the particle is through its life, from 0 to 1.
+    pub fn life(&self) -> f32 {
+        (self.age / self.lifetime).clamp(0.0, 1.0)
+    }
+}
+
+/// Something that creates particles over time and moves them.
+pub struct ParticleEmitter {
+    /// The point particles are spawned around.
+    pub position: Vec3,
+    /// How far from `position` particles can be spawned.
+    pub spawn_radius: f32,
+    /// How many particles are spawned each second.
+    pub spawn_rate: f32,
+    /// If this is `false` no particles are spawned over time, but the particles that exist
+    /// keep moving until they die.
+    pub emitting: bool,
+    /// The most particles that can exist at once.
+    pub max_particles: usize,
+    /// The range of lifetimes that particles are given in seconds.
+    pub lifetime: Range<f32>,
+    /// The average velocity that particles are spawned with.
+    pub velocity: Vec3,
+    /// The most that each axis of the velocity of a particle can differ from `velocity`.
+    pub velocity_randomness: Vec3,
+    /// The range of angular velocities that particles are given in radians per second.
+    pub angular_velocity: Range<f32>,
+    /// The change in velocity of every particle each second, such as gravity.
+    pub acceleration: Vec3,
+    /// The color of particles over their life.
+    pub color: Curve<Vec4>,
+    /// The width and height of particles over their life.
+    pub size: Curve<f32>,
+    /// The texture particles are drawn with, or `None` to draw soft circles.
+    pub texture: Option<Texture>,
+    /// How particles are combined with what is behind them. Particles with a transparent
+    /// blend mode simulated on the CPU are sorted from back to front. See
+    /// `ParticleEmitter::draw_blend_mode` for particles simulated on the GPU.
+    pub blend_mode: BlendMode,
+    simulation: ParticleSimulation,
+    particles: Vec<Particle>,
+    /// Particles spawned since the last update of the GPU simulation.
+    spawned: Vec<Particle>,
+    /// The fraction of a particle that is waiting to be spawned.
+    spawn_remainder: f32,
+    /// The time since the emitter was made, in seconds.
+    time: f32,
+    /// The time each particle slot of the GPU simulation is free again.
+    deaths: Vec<f32>,
+    noise: Noise,
+    buffers: Option<ParticleBuffers>,
+}
+
+impl ParticleEmitter {
+    /// Create a `ParticleEmitter` at `position` that spawns 10 white particles each second,
+    /// which float upwards and fade out over a second.
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            spawn_radius: 0.0,
+            spawn_rate: 10.0,
+            emitting: true,
+            max_particles: 1000,
+            lifetime: 1.0..1.0,
+            velocity: Vec3::Y,
+            velocity_randomness: Vec3::ZERO,
+            angular_velocity: 0.0..0.0,
+            acceleration: Vec3::ZERO,
+            color: Curve::linear(Vec4::ONE, Vec4::new(1.0, 1.0, 1.0, 0.0)),
+            size: Curve::constant(0.1),
+            texture: None,
+            blend_mode: BlendMode::Alpha,
+            simulation: ParticleSimulation::Cpu,
+            particles: Vec::new(),
+            spawned: Vec::new(),
+            spawn_remainder: 0.0,
+            time: 0.0,
+            deaths: Vec::new(),
+            noise: Noise::new(),
+            buffers: None,
+        }
+    }
+
+    /// Modifies how far from the position particles are spawned, then returns the emitter.
+    pub fn with_spawn_radius(mut self, spawn_radius: f32) -> Self {
+        self.spawn_radius = spawn_radius;
+        self
+    }
+
+    /// Modifies how many particles are spawned each second, then returns the emitter.
+    pub fn with_spawn_rate(mut self, spawn_rate: f32) -> Self {
+        self.spawn_rate = spawn_rate;
+        self
+    }
+
+    /// Modifies the most particles that can exist at once, then returns the emitter.
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+
+    /// Modifies the range of lifetimes of particles, then returns the emitter.
+    pub fn with_lifetime(mut self, lifetime: Range<f32>) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// Modifies the velocity of particles and how much it varies, then returns the
+    /// emitter.
+    pub fn with_velocity(mut self, velocity: Vec3, randomness: Vec3) -> Self {
+        self.velocity = velocity;
+        self.velocity_randomness = randomness;
+        self
+    }
+
+    /// Modifies the range of angular velocities of particles, then returns the emitter.
+    pub fn with_angular_velocity(mut self, angular_velocity: Range<f32>) -> Self {
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    /// Modifies the acceleration of particles, then returns the emitter.
+    pub fn with_acceleration(mut self, acceleration: Vec3) -> Self {
+        self.acceleration = acceleration;
+        self
+    }
+
+    /// Modifies the color of particles over their life, then returns the emitter.
+    pub fn with_color(mut self, color: Curve<Vec4>) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Modifies the size of particles over their life, then returns the emitter.
+    pub fn with_size(mut self, size: Curve<f32>) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Modifies the texture of particles, then returns the emitter.
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Modifies the blend mode of particles, then returns the emitter.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Modifies where particles are moved, then returns the emitter. Any particles that
+    /// already exist are removed.
+    pub fn with_simulation(mut self, simulation: ParticleSimulation) -> Self {
+        self.set_simulation(simulation);
+        self
+    }
+
+    /// Where particles are moved.
+    pub fn simulation(&self) -> ParticleSimulation {
+        self.simulation
+    }
+
+    /// Changes where particles are moved, removing any particles that already exist.
+    pub fn set_simulation(&mut self, simulation: ParticleSimulation) {
+        self.simulation = simulation;
+        self.clear();
+    }
+
+    /// The blend mode particles are drawn with. This is `blend_mode`, except that particles
+    /// simulated on the GPU can't be sorted, so those with a blend mode that depends on the
+    /// order they are drawn in are drawn with `BlendMode::Additive` instead.
+    pub fn draw_blend_mode(&self) -> BlendMode {
+        match (self.simulation, self.blend_mode) {
+            (ParticleSimulation::Gpu, BlendMode::Alpha | BlendMode::Premultiplied) => {
+                BlendMode::Additive
+            }
+            (_, blend_mode) => blend_mode,
+        }
+    }
+
+    /// The particles that exist, if they are simulated on the CPU.
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// The number of particles that exist.
+    pub fn particle_count(&self) -> usize {
+        match self.simulation {
+            ParticleSimulation::Cpu => self.particles.len(),
+            ParticleSimulation::Gpu => self
+                .deaths
+                .iter()
+                .filter(|death| **death > self.time)
+                .count(),
+        }
+    }
+
+    /// Removes every particle.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.spawned.clear();
+        self.deaths.clear();
+        self.buffers = None;
+    }
+
+    /// Spawns `count` particles at once, as long as there is room for them.
+    pub fn burst(&mut self, count: usize) {
+        for _ in 0..count {
+            if self.particle_count() + self.spawned.len() >= self.max_particles {
+                break;
+            }
+            let particle = self.spawn();
+            match self.simulation {
+                ParticleSimulation::Cpu => self.particles.push(particle),
+                ParticleSimulation::Gpu => self.spawned.push(particle),
+            }
+        }
+    }
+
+    /// Moves the particles forward by `delta_time` seconds, removing particles that have
+    /// died and spawning new ones. Particles simulated on the GPU are only spawned.
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        if self.simulation == ParticleSimulation::Cpu {
+            for particle in &mut self.particles {
+                particle.age += delta_time;
+                particle.velocity += self.acceleration * delta_time;
+                particle.position += particle.velocity * delta_time;
+                particle.rotation += particle.angular_velocity * delta_time;
+            }
+            self.particles
+                .retain(|particle| particle.age < particle.lifetime);
+        }
+
+        if self.emitting {
+            self.spawn_remainder += self.spawn_rate * delta_time;
+            let count = self.spawn_remainder.floor();
+            self.spawn_remainder -= count;
+            self.burst(count as usize);
+        }
+    }
+
+    /// Makes a new particle with random values from the settings of the emitter.
+    fn spawn(&mut self) -> Particle {
+        let noise = &mut self.noise;
+        let mut random = |range: Range<f32>| {
+            let amount = noise.next() as f32 / u32::MAX as f32;
+            range.start + (range.end - range.start) * amount
+        };
+        let mut random_vec3 = |size: Vec3| {
+            Vec3::new(
+                random(-size.x..size.x),
+                random(-size.y..size.y),
+                random(-size.z..size.z),
+            )
+        };
+
+        let mut offset = random_vec3(Vec3::ONE);
+        while offset.length_squared() > 1.0 {
+            offset = random_vec3(Vec3::ONE);
+        }
+        Particle {
+            position: self.position + offset * self.spawn_radius,
+            velocity: self.velocity + random_vec3(self.velocity_randomness),
+            rotation: random(0.0..std::f32::consts::TAU),
+            angular_velocity: random(self.angular_velocity.clone()),
+            age: 0.0,
+            lifetime: random(self.lifetime.clone()).max(f32::EPSILON),
+        }
+    }
+
+    /// The transform and instance data of a particle on the CPU.
+    fn instance(&self, particle: &Particle) -> (Transform, InstanceData) {
+        let life = particle.life();
+        (
+            Transform {
+                translation: particle.position,
+                rotation: Quat::from_rotation_z(particle.rotation),
+                scale: Vec3::splat(self.size.sample(life)),
+            },
+            InstanceData::color(self.color.sample(life).into()),
+        )
+    }
+
+    /// Writes the particles to the buffers that are drawn, or runs a step of the GPU
+    /// simulation in `encoder`.
+    fn write(
+        &mut self,
+        data: &GameData,
+        eye: Vec3,
+        delta_time: f32,
+        simulation: Option<&ComputePipeline>,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        match self.simulation {
+            ParticleSimulation::Cpu => {
+                if self.blend_mode.is_transparent() {
+                    self.particles.sort_by(|a, b| {
+                        b.position
+                            .distance_squared(eye)
+                            .total_cmp(&a.position.distance_squared(eye))
+                    });
+                }
+                let (transforms, instance_data) = self
+                    .particles
+                    .iter()
+                    .map(|particle| self.instance(particle))
+                    .unzip();
+
+                match &mut self.buffers {
+                    Some(ParticleBuffers::Cpu(buffers)) => {
+                        buffers.transforms.values = transforms;
+                        buffers.transforms.mark_all();
+                        buffers.transforms.update(data);
+                        buffers.instance_data.values = instance_data;
+                        buffers.instance_data.mark_all();
+                        buffers.instance_data.update(data);
+                    }
+                    buffers => {
+                        *buffers = Some(ParticleBuffers::Cpu(CpuBuffers {
+                            transforms: Transform::instance_buffer(data, transforms),
+                            instance_data: InstanceBuffer::new_pod(data, instance_data),
+                        }))
+                    }
+                }
+            }
+            ParticleSimulation::Gpu => {
+                let simulation = simulation.expect("The simulation pipeline wasn't made");
+                let capacity = self.max_particles.max(1);
+                let buffers = match &mut self.buffers {
+                    Some(ParticleBuffers::Gpu(buffers)) if buffers.capacity == capacity => buffers,
+                    buffers => {
+                        self.deaths = vec![0.0; capacity];
+                        *buffers = Some(ParticleBuffers::Gpu(GpuBuffers::new(
+                            data, capacity, simulation,
+                        )));
+                        match buffers {
+                            Some(ParticleBuffers::Gpu(buffers)) => buffers,
+                            _ => unreachable!(),
+                        }
+                    }
+                };
+
+                let graphics = data.graphics.lock();
+                // New particles replace the oldest ones.
+                for particle in self.spawned.drain(..) {
+                    let slot = buffers.next;
+                    buffers.next = (buffers.next + 1) % capacity;
+                    self.deaths[slot] = self.time + particle.lifetime - particle.age;
+                    graphics.queue.write_buffer(
+                        &buffers.particles,
+                        (slot * size_of::<GpuParticle>()) as u64,
+                        bytemuck::cast_slice(&[GpuParticle::from(particle)]),
+                    );
+                }
+
+                let colors = self.color.samples().map(|color| color.to_array());
+                let sizes = self.size.samples();
+                let params = GpuParams {
+                    acceleration: self.acceleration.extend(delta_time).to_array(),
+                    colors,
+                    sizes: [
+                        [sizes[0], sizes[1], sizes[2], sizes[3]],
+                        [sizes[4], sizes[5], sizes[6], sizes[7]],
+                    ],
+                };
+                graphics
+                    .queue
+                    .write_buffer(&buffers.params, 0, bytemuck::cast_slice(&[params]));
+                drop(graphics);
+
+                let mut pass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                pass.set_pipeline(simulation);
+                pass.set_bind_group(0, &buffers.bind_group, &[]);
+                pass.dispatch_workgroups((capacity as u32).div_ceil(64), 1, 1);
+            }
+        }
+    }
+
+    /// The number of instances to draw and the buffers of their transforms and instance
+    /// data.
+    fn draw_buffers(&self) -> Option<(u32, &Buffer, &Buffer)> {
+        match self.buffers.as_ref()? {
+            ParticleBuffers::Cpu(buffers) => Some((
+                buffers.transforms.values.len() as u32,
+                &buffers.transforms.buffer,
+                &buffers.instance_data.buffer,
+            )),
+            ParticleBuffers::Gpu(buffers) => Some((
+                buffers.capacity as u32,
+                &buffers.transforms,
+                &buffers.instance_data,
+            )),
+        }
+    }
+}
+
+enum ParticleBuffers {
+    Cpu(CpuBuffers),
+    Gpu(GpuBuffers),
+}
+
+struct CpuBuffers {
+    transforms: InstanceBuffer<Transform>,
+    instance_data: InstanceBuffer<InstanceData>,
+}
+
+/// The buffers of an emitter simulated on the GPU. Every particle slot is drawn, and dead
+/// particles are scaled to nothing.
+struct GpuBuffers {
+    capacity: usize,
+    /// The slot the next particle is spawned in.
+    next: usize,
+    particles: Buffer,
+    transforms: Buffer,
+    instance_data: Buffer,
+    params: Buffer,
+    bind_group: BindGroup,
+}
+
+impl GpuBuffers {
+    fn new(data: &GameData, capacity: usize, simulation: &ComputePipeline) -> Self {
+        let graphics = data.graphics.lock();
+        let make_buffer = |stride: usize, usage: wgpu::BufferUsages| {
+            graphics.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: (capacity * stride) as u64,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let particles = make_buffer(
+            size_of::<GpuParticle>(),
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        );
+        let transforms = make_buffer(
+            size_of::<[[f32; 4]; 4]>(),
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+        );
+        let instance_data = make_buffer(
+            size_of::<InstanceData>(),
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+        );
+        let params = graphics.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: size_of::<GpuParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = graphics
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &simulation.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: particles.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: transforms.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: instance_data.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: params.as_entire_binding(),
+                    },
+                ],
+            });
+
+        Self {
+            capacity,
+            next: 0,
+            particles,
+            transforms,
+            instance_data,
+            params,
+            bind_group,
+        }
+    }
+}
+
+/// A particle as it is laid out for the GPU simulation.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuParticle {
+    position: [f32; 4],
+    velocity: [f32; 4],
+    rotation: [f32; 4],
+}
+
+impl From<Particle> for GpuParticle {
+    fn from(particle: Particle) -> Self {
+        Self {
+            position: particle.position.extend(particle.age).to_array(),
+            velocity: particle.velocity.extend(particle.lifetime).to_array(),
+            rotation: [particle.rotation, particle.angular_velocity, 0.0, 0.0],
+        }
+    }
+}
+
+/// The settings of an emitter as they are laid out for the GPU simulation.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuParams {
+    acceleration: [f32; 4],
+    colors: [[f32; 4]; CURVE_SAMPLES],
+    sizes: [[f32; 4]; CURVE_SAMPLES / 4],
+}
+
+/// A renderer that draws the particles of emitters as squares that always face the camera.
+/// It uses the same camera and projection as a `SimpleRenderer`, and can be drawn after one
+/// to have particles hidden behind models.
+pub struct ParticleRenderer {
+    pipelines: [RenderPipeline; 5],
+    simulation: Option<ComputePipeline>,
+    camera: Mat4,
+    projection: Mat4,
+    camera_buffer: Buffer,
+    projection_buffer: Buffer,
+    projection_bind_group: BindGroup,
+    quad: Buffer,
+    default_texture: Texture,
+    /// A view of the depth texture.
+    pub depth_texture_view: TextureView,
+    /// A sampler for nearest filters (magnified textures looked pixelated).
+    pub nearest_sampler: Sampler,
+    /// A sampler for linear filters (magnified textures looked blurry).
+    pub linear_sampler: Sampler,
+    /// Every emitter that will be updated and drawn.
+    pub emitters: Vec<ParticleEmitter>,
+}
+
+impl ParticleRenderer {
+    /// Create a `ParticleRenderer` with no emitters.
+    pub fn new(data: &GameData, projection: SimpleProjection) -> Self {
+        let projection = Mat4::from(projection);
+        let make_buffer = |contents: Mat4| {
+            data.graphics
+                .lock()
+                .device
+                .create_buffer_init(&BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(&[contents.to_cols_array_2d()]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                })
+        };
+        let camera_buffer = make_buffer(Mat4::IDENTITY);
+        let projection_buffer = make_buffer(projection);
+
+        let projection_bind_group =
+            data.graphics
+                .lock()
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: projection_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: camera_buffer.as_entire_binding(),
+                        },
+                    ],
+                    layout: &SimpleRenderer::mat4_bind_group_layout(data),
+                });
+
+        let corner = |x: f32, y: f32| TextureVertex {
+            pos: [x - 0.5, y - 0.5, 0.0],
+            tex_coords: [x, 1.0 - y],
+        };
+        let quad = data
+            .graphics
+            .lock()
+            .device
+            .create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&[
+                    corner(0.0, 0.0),
+                    corner(1.0, 0.0),
+                    corner(0.0, 1.0),
+                    corner(1.0, 0.0),
+                    corner(1.0, 1.0),
+                    corner(0.0, 1.0),
+                ]),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+        let linear_sampler = SimpleRenderer::linear_sampler(data);
+        Self {
+            pipelines: BlendMode::ALL.map(|blend_mode| Self::pipeline(data, blend_mode)),
+            simulation: None,
+            camera: Mat4::IDENTITY,
+            projection,
+            camera_buffer,
+            projection_buffer,
+            projection_bind_group,
+            quad,
+            default_texture: Texture::new(data, &soft_circle(32), &linear_sampler),
+            depth_texture_view: SimpleRenderer::depth_texture(data),
+            nearest_sampler: SimpleRenderer::nearest_sampler(data),
+            linear_sampler,
+            emitters: Vec::new(),
+        }
+    }
+
+    /// Adds an emitter and returns its index.
+    pub fn add_emitter(&mut self, emitter: ParticleEmitter) -> usize {
+        self.emitters.push(emitter);
+        self.emitters.len() - 1
+    }
+
+    /// Replaces the camera of the renderer and updates its buffer.
+    pub fn set_camera(&mut self, data: &GameData, camera: Mat4) {
+        self.camera = camera;
+        data.graphics.lock().queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[camera.to_cols_array_2d()]),
+        )
+    }
+
+    /// Replaces the projection of the renderer and updates its buffer.
+    pub fn set_projection(&mut self, data: &GameData, projection: SimpleProjection) {
+        self.projection = Mat4::from(projection);
+        data.graphics.lock().queue.write_buffer(
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(&[self.projection.to_cols_array_2d()]),
+        )
+    }
+
+    /// The position of the camera in the world.
+    pub fn camera_position(&self) -> Vec3 {
+        self.camera.inverse().w_axis.xyz()
+    }
+
+    /// Makes the particle pipeline for emitters with `blend_mode`.
+    pub fn pipeline(data: &GameData, blend_mode: BlendMode) -> RenderPipeline {
+        let shader = data
+            .shaders
+            .lock()
+            .module(data, "particle.wgsl", include_str!("particle.wgsl"), &[])
+            .unwrap();
+
+        let mat4_bind_group_layout = SimpleRenderer::mat4_bind_group_layout(data);
+        let texture_bind_group_layout = Texture::bind_group_layout(data);
+
+        let pipeline_layout =
+            data.graphics
+                .lock()
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&mat4_bind_group_layout, &texture_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let fragment_format = data.graphics.lock().config.format;
+
+        data.graphics
+            .lock()
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Particle Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "particle_vertex",
+                    buffers: &[
+                        TextureVertex::desc(),
+                        Transform::desc(),
+                        InstanceData::desc(),
+                    ],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: match blend_mode {
+                        BlendMode::AlphaCutout => "particle_cutout_fragment",
+                        _ => "particle_fragment",
+                    },
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: fragment_format,
+                        blend: blend_mode.blend_state(),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: !blend_mode.is_transparent(),
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+    }
+
+    /// Makes the compute pipeline that moves particles simulated on the GPU.
+    pub fn simulation_pipeline(data: &GameData) -> ComputePipeline {
+        let shader = data
+            .shaders
+            .lock()
+            .module(
+                data,
+                "particle_simulation.wgsl",
+                include_str!("particle_simulation.wgsl"),
+                &[],
+            )
+            .unwrap();
+
+        data.graphics
+            .lock()
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Particle Simulation Pipeline"),
+                layout: None,
+                module: &shader,
+                entry_point: "simulate",
+            })
+    }
+}
+
+/// A white circle that fades out towards its edge.
+fn soft_circle(size: u32) -> DynamicImage {
+    let centre = size as f32 / 2.0;
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(size, size, |x, y| {
+        let distance = ((x as f32 + 0.5 - centre).hypot(y as f32 + 0.5 - centre) / centre).min(1.0);
+        Rgba([255, 255, 255, ((1.0 - distance).powi(2) * 255.0) as u8])
+    }))
+}
+
+impl Renderer for ParticleRenderer {
+    fn render<'a, 'b: 'a>(&'b self, render_pass: &'a mut wgpu::RenderPass<'b>) {
+        render_pass.set_bind_group(0, &self.projection_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.quad.slice(..));
+        for emitter in &self.emitters {
+            let (count, transforms, instance_data) = match emitter.draw_buffers() {
+                Some(buffers) => buffers,
+                None => continue,
+            };
+            if count == 0 {
+                continue;
+            }
+            let texture = emitter.texture.as_ref().unwrap_or(&self.default_texture);
+            render_pass.set_pipeline(&self.pipelines[emitter.draw_blend_mode() as usize]);
+            render_pass.set_bind_group(1, &texture.diffuse, &[]);
+            render_pass.set_vertex_buffer(1, transforms.slice(..));
+            render_pass.set_vertex_buffer(2, instance_data.slice(..));
+            render_pass.draw(0..6, 0..count);
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let delta_time = data.delta_time.as_secs_f32();
+        let eye = self.camera_position();
+        if self.simulation.is_none()
+            && self
+                .emitters
+                .iter()
+                .any(|emitter| emitter.simulation == ParticleSimulation::Gpu)
+        {
+            self.simulation = Some(Self::simulation_pipeline(data));
+        }
+
+        let mut encoder = data
+            .graphics
+            .lock()
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        for emitter in &mut self.emitters {
+            emitter.update(delta_time);
+            emitter.write(
+                data,
+                eye,
+                delta_time,
+                self.simulation.as_ref(),
+                &mut encoder,
+            );
+        }
+        data.graphics
+            .lock()
+            .queue
+            .submit(std::iter::once(encoder.finish()));
+    }
+
+    fn make_render_pass<'a>(
+        &'a self,
+        view: &'a TextureView,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        })
+    }
+
+    fn resize(&mut self, data: &GameData) {
+        self.depth_texture_view = SimpleRenderer::depth_texture(data);
+    }
+}
+
+#[test]
+fn curve_test() {
+    let curve = Curve::new(vec![(1.0, 4.0), (0.0, 0.0), (0.5, 2.0)]);
+    assert_eq!(curve.sample(-1.0), 0.0);
+    assert_eq!(curve.sample(0.25), 1.0);
+    assert_eq!(curve.sample(0.75), 3.0);
+    assert_eq!(curve.sample(2.0), 4.0);
+    assert_eq!(Curve::constant(Vec4::ONE).sample(0.5), Vec4::ONE);
+    assert_eq!(curve.samples()[CURVE_SAMPLES - 1], 4.0);
+}
+
+#[test]
+fn particle_emitter_test() {
+    let mut emitter = ParticleEmitter::new(Vec3::ZERO)
+        .with_spawn_rate(4.0)
+        .with_lifetime(1.0..1.0)
+        .with_velocity(Vec3::X, Vec3::ZERO)
+        .with_acceleration(Vec3::new(0.0, -2.0, 0.0))
+        .with_max_particles(6);
+
+    // A particle is spawned every quarter of a second.
+    emitter.update(0.3);
+    assert_eq!(emitter.particle_count(), 1);
+    emitter.update(0.5);
+    assert_eq!(emitter.particle_count(), 3);
+    let oldest = emitter.particles()[0];
+    assert!((oldest.position - Vec3::new(0.5, -0.5, 0.0)).length() < 1e-5);
+    assert!((oldest.velocity - Vec3::new(1.0, -1.0, 0.0)).length() < 1e-5);
+
+    // The first particles die after a second, and no more than the maximum can exist.
+    emitter.burst(10);
+    assert_eq!(emitter.particle_count(), 6);
+    emitter.emitting = false;
+    emitter.update(0.6);
+    assert_eq!(emitter.particle_count(), 5);
+
+    // Particles simulated on the GPU can't be sorted, so they aren't blended by alpha.
+    assert_eq!(emitter.draw_blend_mode(), BlendMode::Alpha);
+    emitter.set_simulation(ParticleSimulation::Gpu);
+    assert_eq!(emitter.draw_blend_mode(), BlendMode::Additive);
+}
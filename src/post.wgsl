@@ -0,0 +1,38 @@
+// The start of every post-processing shader. Effects add their own uniforms in group 1.
+
+struct PostOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// The output of the previous pass.
+@group(0)@binding(0)
+var input_texture: texture_2d<f32>;
+@group(0)@binding(1)
+var input_sampler: sampler;
+// The image before any pass of the current effect.
+@group(0)@binding(2)
+var source_texture: texture_2d<f32>;
+
+@vertex
+fn post_vertex(@builtin(vertex_index) index: u32) -> PostOutput {
+    // A single triangle that covers the whole screen.
+    let uv = vec2<f32>(f32(index / 2u) * 2.0, f32(index % 2u) * 2.0);
+
+    var output: PostOutput;
+    output.pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
+
+fn sample_input(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(input_texture, input_sampler, uv, 0.0);
+}
+
+fn sample_source(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(source_texture, input_sampler, uv, 0.0);
+}
+
+fn input_size() -> vec2<f32> {
+    return vec2<f32>(textureDimensions(input_texture));
+}
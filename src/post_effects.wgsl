@@ -0,0 +1,134 @@
+// The built in post-processing effects. The meaning of `settings` depends on the effect.
+
+@group(1)@binding(0)
+var<uniform> settings: vec4<f32>;
+@group(1)@binding(1)
+var effect_texture: texture_2d<f32>;
+@group(1)@binding(2)
+var effect_sampler: sampler;
+
+@fragment
+fn copy(output: PostOutput) -> @location(0) vec4<f32> {
+    return sample_input(output.uv);
+}
+
+@fragment
+fn tonemap(output: PostOutput) -> @location(0) vec4<f32> {
+    let color = sample_input(output.uv);
+    let x = color.rgb * settings.x;
+    // An approximation of the ACES filmic curve.
+    let mapped = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+    return vec4<f32>(clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
+
+@fragment
+fn bloom_threshold(output: PostOutput) -> @location(0) vec4<f32> {
+    let color = sample_input(output.uv);
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - settings.x, 0.0) / max(brightness, 0.0001);
+    return vec4<f32>(color.rgb * contribution, 1.0);
+}
+
+fn blur(uv: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let step = direction * settings.z / input_size();
+    var weights = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+    var color = sample_input(uv) * weights[0];
+    for (var i = 1; i < 5; i = i + 1) {
+        let offset = step * f32(i);
+        color = color + (sample_input(uv + offset) + sample_input(uv - offset)) * weights[i];
+    }
+    return color;
+}
+
+@fragment
+fn bloom_blur_horizontal(output: PostOutput) -> @location(0) vec4<f32> {
+    return blur(output.uv, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn bloom_blur_vertical(output: PostOutput) -> @location(0) vec4<f32> {
+    return blur(output.uv, vec2<f32>(0.0, 1.0));
+}
+
+@fragment
+fn bloom_combine(output: PostOutput) -> @location(0) vec4<f32> {
+    let source = sample_source(output.uv);
+    return vec4<f32>(source.rgb + sample_input(output.uv).rgb * settings.y, source.a);
+}
+
+@fragment
+fn fxaa(output: PostOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / input_size();
+    let luma = vec3<f32>(0.299, 0.587, 0.114);
+    let centre = sample_input(output.uv);
+    let m = dot(centre.rgb, luma);
+    let nw = dot(sample_input(output.uv + vec2<f32>(-1.0, -1.0) * texel).rgb, luma);
+    let ne = dot(sample_input(output.uv + vec2<f32>(1.0, -1.0) * texel).rgb, luma);
+    let sw = dot(sample_input(output.uv + vec2<f32>(-1.0, 1.0) * texel).rgb, luma);
+    let se = dot(sample_input(output.uv + vec2<f32>(1.0, 1.0) * texel).rgb, luma);
+    let luma_min = min(m, min(min(nw, ne), min(sw, se)));
+    let luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+    // Blur along the edge, which runs across the steepest change in brightness.
+    var direction = vec2<f32>(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    let reduce = max((nw + ne + sw + se) * 0.03125, 0.0078125);
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-8.0), vec2<f32>(8.0)) * texel;
+
+    let near = 0.5 * (sample_input(output.uv - direction / 6.0).rgb
+        + sample_input(output.uv + direction / 6.0).rgb);
+    let far = near * 0.5 + 0.25 * (sample_input(output.uv - direction * 0.5).rgb
+        + sample_input(output.uv + direction * 0.5).rgb);
+    let far_luma = dot(far, luma);
+    let color = select(far, near, far_luma < luma_min || far_luma > luma_max);
+    return vec4<f32>(color, centre.a);
+}
+
+@fragment
+fn vignette(output: PostOutput) -> @location(0) vec4<f32> {
+    let color = sample_input(output.uv);
+    // The distance from the centre, which is 1 in the corners.
+    let distance = length(output.uv - 0.5) * 1.4142135;
+    let darkness = settings.x * smoothstep(settings.y, 1.0, distance);
+    return vec4<f32>(color.rgb * (1.0 - darkness), color.a);
+}
+
+@fragment
+fn color_grading(output: PostOutput) -> @location(0) vec4<f32> {
+    let color = sample_input(output.uv);
+    // The table is looked up with gamma corrected colors, like the image it was made from.
+    let index = pow(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(1.0 / 2.2));
+
+    // The table is a row of square slices, one for each amount of blue.
+    let size = f32(textureDimensions(effect_texture).y);
+    let blue = index.b * (size - 1.0);
+    let slice = floor(blue);
+    let next = min(slice + 1.0, size - 1.0);
+    let inner = (index.rg * (size - 1.0) + 0.5) / size;
+    let first = textureSampleLevel(effect_texture, effect_sampler, vec2<f32>((slice + inner.x) / size, inner.y), 0.0);
+    let second = textureSampleLevel(effect_texture, effect_sampler, vec2<f32>((next + inner.x) / size, inner.y), 0.0);
+    let graded = mix(first.rgb, second.rgb, blue - slice);
+    return vec4<f32>(mix(color.rgb, graded, settings.x), color.a);
+}
+
+@fragment
+fn crt(output: PostOutput) -> @location(0) vec4<f32> {
+    // The screen bulges outwards, more so towards the corners.
+    var uv = output.uv * 2.0 - 1.0;
+    uv = uv + uv * uv.yx * uv.yx * settings.x;
+    let inside = abs(uv.x) <= 1.0 && abs(uv.y) <= 1.0;
+    uv = uv * 0.5 + 0.5;
+
+    let color = sample_input(uv).rgb;
+    // Every other row of pixels is darker.
+    let row = sin(uv.y * input_size().y * 1.5707963);
+    let scanline = 1.0 - settings.y * row * row;
+    return vec4<f32>(select(vec3<f32>(0.0), color * scanline, inside), 1.0);
+}
+
+@fragment
+fn pixelate(output: PostOutput) -> @location(0) vec4<f32> {
+    let pixel = max(settings.x, 1.0) / input_size();
+    return sample_input((floor(output.uv / pixel) + 0.5) * pixel);
+}
@@ -0,0 +1,734 @@
+//! Effects that are applied to the whole screen after a renderer has drawn it, such as
+//! bloom, anti-aliasing and color grading.
+
+use std::{num::NonZeroU32, path::Path};
+
+use anyhow::{bail, Context, Result};
+use bytemuck::Pod;
+use glam::UVec2;
+use image::DynamicImage;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, Buffer, RenderPipeline, Sampler, TextureFormat, TextureView,
+};
+
+use crate::{
+    graphics::Renderer,
+    shaders::{self, ShaderFile},
+    targets::RenderTarget,
+    GameData,
+};
+
+/// The format of the textures that the renderer of a `PostProcessRenderer` draws to and
+/// that effects pass between each other, so that colors can be brighter than 1 until the
+/// last pass draws to the screen.
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// A post-processing effect made of one or more fullscreen passes, which share a set of
+/// uniforms and an optional image.
+///
+/// The shader of an effect includes `post.wgsl`, which gives it the `PostOutput` struct, the
+/// `sample_input` and `sample_source` functions and the `input_size` function, and it is
+/// preprocessed by the `ShaderLibrary`. The uniforms are bound to group 1 binding 0, and the
+/// image to bindings 1 and 2.
+pub struct Effect {
+    /// The name of the effect, to find it in a `PostProcessRenderer`.
+    pub name: String,
+    /// If this is `false` the effect is skipped.
+    pub enabled: bool,
+    /// The pipelines of each pass, which draw in `HDR_FORMAT`.
+    pipelines: Vec<RenderPipeline>,
+    /// The pipeline of the last pass for when it draws to the screen.
+    screen_pipeline: RenderPipeline,
+    entry_points: Vec<String>,
+    shader_file: Option<ShaderFile>,
+    uniforms: Vec<u8>,
+    uniforms_outdated: bool,
+    uniform_buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl Effect {
+    /// Create an `Effect` from a WGSL shader with a fragment entry point for each pass.
+    /// Each pass reads the output of the one before it, and the last pass writes the
+    /// output of the effect. Errors in the shader or its entry points are returned.
+    pub fn new<T: Pod>(
+        data: &GameData,
+        name: &str,
+        shader: &str,
+        entry_points: &[&str],
+        uniforms: T,
+    ) -> Result<Self> {
+        if entry_points.is_empty() {
+            bail!("An effect needs at least one pass");
+        }
+        let entry_points = entry_points
+            .iter()
+            .map(|entry_point| entry_point.to_string())
+            .collect::<Vec<_>>();
+        let (pipelines, screen_pipeline) = Self::compile(data, name, shader, &entry_points)?;
+
+        let uniforms = bytemuck::bytes_of(&uniforms).to_vec();
+        // Uniform buffers are padded to a multiple of 16 bytes by WGSL.
+        let mut contents = uniforms.clone();
+        contents.resize(contents.len().div_ceil(16).max(1) * 16, 0);
+        let uniform_buffer =
+            data.graphics
+                .lock()
+                .device
+                .create_buffer_init(&BufferInitDescriptor {
+                    label: None,
+                    contents: &contents,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+
+        Ok(Self {
+            name: name.to_string(),
+            enabled: true,
+            pipelines,
+            screen_pipeline,
+            entry_points,
+            shader_file: None,
+            bind_group: Self::make_bind_group(
+                data,
+                &uniform_buffer,
+                &DynamicImage::new_rgba8(1, 1),
+            ),
+            uniforms,
+            uniforms_outdated: false,
+            uniform_buffer,
+        })
+    }
+
+    /// Maps the colors of the screen to the range that can be shown, after multiplying
+    /// them by `exposure`. Its uniforms are `[exposure, 0, 0, 0]`.
+    pub fn tonemap(data: &GameData, exposure: f32) -> Self {
+        Self::built_in(data, "tonemap", &["tonemap"], [exposure, 0.0, 0.0, 0.0])
+    }
+
+    /// Makes the parts of the screen brighter than `threshold` glow, spreading `radius`
+    /// pixels per step of the blur. Its uniforms are `[threshold, intensity, radius, 0]`.
+    pub fn bloom(data: &GameData, threshold: f32, intensity: f32, radius: f32) -> Self {
+        Self::built_in(
+            data,
+            "bloom",
+            &[
+                "bloom_threshold",
+                "bloom_blur_horizontal",
+                "bloom_blur_vertical",
+                "bloom_combine",
+            ],
+            [threshold, intensity, radius, 0.0],
+        )
+    }
+
+    /// Smooths jagged edges with fast approximate anti-aliasing. It has no uniforms.
+    pub fn fxaa(data: &GameData) -> Self {
+        Self::built_in(data, "fxaa", &["fxaa"], [0.0; 4])
+    }
+
+    /// Darkens the corners of the screen by up to `strength`, starting `radius` of the way
+    /// from the centre to the corners. Its uniforms are `[strength, radius, 0, 0]`.
+    pub fn vignette(data: &GameData, strength: f32, radius: f32) -> Self {
+        Self::built_in(
+            data,
+            "vignette",
+            &["vignette"],
+            [strength, radius, 0.0, 0.0],
+        )
+    }
+
+    /// Replaces colors using a lookup table, blended with the original colors by
+    /// `intensity`. The table is a row of square slices, one for each amount of blue, so a
+    /// table with 16 steps is 256x16 pixels. Its uniforms are `[intensity, 0, 0, 0]`.
+    pub fn color_grading(data: &GameData, table: &DynamicImage, intensity: f32) -> Self {
+        Self::built_in(
+            data,
+            "color_grading",
+            &["color_grading"],
+            [intensity, 0.0, 0.0, 0.0],
+        )
+        .with_image(data, table)
+    }
+
+    /// Makes the screen look like an old monitor, curved by `curvature` with scanlines that
+    /// darken every other row by `scanlines`. Its uniforms are `[curvature, scanlines, 0, 0]`.
+    pub fn crt(data: &GameData, curvature: f32, scanlines: f32) -> Self {
+        Self::built_in(data, "crt", &["crt"], [curvature, scanlines, 0.0, 0.0])
+    }
+
+    /// Makes the screen blocky, with pixels `pixel_size` times larger. Its uniforms are
+    /// `[pixel_size, 0, 0, 0]`.
+    pub fn pixelate(data: &GameData, pixel_size: f32) -> Self {
+        Self::built_in(data, "pixelate", &["pixelate"], [pixel_size, 0.0, 0.0, 0.0])
+    }
+
+    fn built_in(data: &GameData, name: &str, entry_points: &[&str], settings: [f32; 4]) -> Self {
+        Self::new(
+            data,
+            name,
+            include_str!("post_effects.wgsl"),
+            entry_points,
+            settings,
+        )
+        .unwrap()
+    }
+
+    /// Modifies the image of the effect, then returns the effect. Like other textures, its
+    /// colors are sRGB and it is sampled with a linear filter.
+    pub fn with_image(mut self, data: &GameData, image: &DynamicImage) -> Self {
+        self.bind_group = Self::make_bind_group(data, &self.uniform_buffer, image);
+        self
+    }
+
+    /// Replaces the shader of the effect with the WGSL file at `path`, which needs the same
+    /// entry points, and reloads it whenever it changes. If the file is changed to have an
+    /// error it is printed, and the last pipelines without errors are kept.
+    pub fn with_shader_file<P: AsRef<Path>>(mut self, data: &GameData, path: P) -> Result<Self> {
+        let mut file = ShaderFile::new(&path);
+        let shader = file
+            .poll()
+            .with_context(|| format!("Can't read {}", path.as_ref().display()))?;
+        self.set_shader(data, &shader)?;
+        self.shader_file = Some(file);
+        Ok(self)
+    }
+
+    /// Replaces the pipelines with ones made from `shader` if it has no errors.
+    fn set_shader(&mut self, data: &GameData, shader: &str) -> Result<()> {
+        (self.pipelines, self.screen_pipeline) =
+            Self::compile(data, &self.name, shader, &self.entry_points)?;
+        Ok(())
+    }
+
+    /// Compiles `shader` as `name` and makes its pipelines, returning any errors.
+    fn compile(
+        data: &GameData,
+        name: &str,
+        shader: &str,
+        entry_points: &[String],
+    ) -> Result<(Vec<RenderPipeline>, RenderPipeline)> {
+        let source = format!("#include \"post.wgsl\"\n{shader}");
+        let module = data.shaders.lock().module(data, name, &source, &[])?;
+        shaders::validate(data, || Self::make_pipelines(data, &module, entry_points))
+    }
+
+    /// Makes the pipelines of every pass, and of the last pass drawing to the screen.
+    fn make_pipelines(
+        data: &GameData,
+        module: &wgpu::ShaderModule,
+        entry_points: &[String],
+    ) -> (Vec<RenderPipeline>, RenderPipeline) {
+        let screen_format = data.graphics.lock().config.format;
+        let pipelines = entry_points
+            .iter()
+            .map(|entry_point| Self::pipeline(data, module, entry_point, HDR_FORMAT))
+            .collect();
+        let screen_pipeline =
+            Self::pipeline(data, module, entry_points.last().unwrap(), screen_format);
+        (pipelines, screen_pipeline)
+    }
+
+    /// The uniforms of the effect. Panics if `T` isn't the size of the uniforms the effect
+    /// was made with.
+    pub fn uniforms<T: Pod>(&self) -> T {
+        bytemuck::pod_read_unaligned(&self.uniforms)
+    }
+
+    /// Replaces the uniforms of the effect, which are written before the next frame.
+    /// Panics if `T` isn't the size of the uniforms the effect was made with.
+    pub fn set_uniforms<T: Pod>(&mut self, uniforms: T) {
+        let uniforms = bytemuck::bytes_of(&uniforms);
+        assert_eq!(
+            uniforms.len(),
+            self.uniforms.len(),
+            "The uniforms of an effect can't change size"
+        );
+        self.uniforms.copy_from_slice(uniforms);
+        self.uniforms_outdated = true;
+    }
+
+    /// The number of passes the effect draws.
+    pub fn pass_count(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    fn make_bind_group(
+        data: &GameData,
+        uniform_buffer: &Buffer,
+        image: &DynamicImage,
+    ) -> BindGroup {
+        let image = image.to_rgba8();
+        let size = wgpu::Extent3d {
+            width: image.width(),
+            height: image.height(),
+            depth_or_array_layers: 1,
+        };
+
+        let graphics = data.graphics.lock();
+        let texture = graphics.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        graphics.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &image,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(4 * size.width),
+                rows_per_image: NonZeroU32::new(size.height),
+            },
+            size,
+        );
+        drop(graphics);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = clamp_sampler(data);
+        data.graphics
+            .lock()
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &Self::bind_group_layout(data),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            })
+    }
+
+    /// Makes the pipeline of a single pass of an effect that draws to a texture of `format`.
+    pub fn pipeline(
+        data: &GameData,
+        module: &wgpu::ShaderModule,
+        entry_point: &str,
+        format: TextureFormat,
+    ) -> RenderPipeline {
+        let input_bind_group_layout = Self::input_bind_group_layout(data);
+        let bind_group_layout = Self::bind_group_layout(data);
+
+        let pipeline_layout =
+            data.graphics
+                .lock()
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&input_bind_group_layout, &bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        data.graphics
+            .lock()
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Post-processing Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module,
+                    entry_point: "post_vertex",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+    }
+
+    /// The layout of group 0, the output of the previous pass and the image before the
+    /// effect.
+    pub fn input_bind_group_layout(data: &GameData) -> wgpu::BindGroupLayout {
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        data.graphics
+            .lock()
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    texture(0),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    texture(2),
+                ],
+            })
+    }
+
+    /// The layout of group 1, the uniforms and image of the effect.
+    pub fn bind_group_layout(data: &GameData) -> wgpu::BindGroupLayout {
+        data.graphics
+            .lock()
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            })
+    }
+}
+
+/// A linear sampler that doesn't wrap around the edges of the screen.
+fn clamp_sampler(data: &GameData) -> Sampler {
+    data.graphics
+        .lock()
+        .device
+        .create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        })
+}
+
+/// A pass of an effect, and the targets it reads from and writes to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PlannedPass {
+    effect: usize,
+    pass: usize,
+    input: usize,
+    source: usize,
+    /// The target the pass writes to, or `None` for the screen.
+    output: Option<usize>,
+}
+
+/// Plans the passes of every effect, given the index and pass count of each enabled
+/// effect. The renderer draws to target 0, and each pass after that writes to whichever
+/// target isn't being read from.
+fn plan(effects: &[(usize, usize)]) -> Vec<PlannedPass> {
+    let mut planned = Vec::new();
+    let mut current = 0;
+    for (index, (effect, pass_count)) in effects.iter().enumerate() {
+        let source = current;
+        let free = [(source + 1) % 3, (source + 2) % 3];
+        let mut input = source;
+        for pass in 0..*pass_count {
+            let last = index == effects.len() - 1 && pass == pass_count - 1;
+            let output = (!last).then_some(free[pass % 2]);
+            planned.push(PlannedPass {
+                effect: *effect,
+                pass,
+                input,
+                source,
+                output,
+            });
+            input = output.unwrap_or(input);
+        }
+        current = input;
+    }
+    planned
+}
+
+/// A renderer that draws another renderer to a texture, then applies a chain of effects to
+/// it on the way to the screen. Effects can be added, removed, reordered, enabled and
+/// configured at any time.
+///
+/// The renderer draws in `HDR_FORMAT` rather than the format of the screen, such as a
+/// `SimpleRenderer` with `RenderSettings::with_color_format(HDR_FORMAT)`.
+pub struct PostProcessRenderer<R> {
+    /// The renderer that draws the scene.
+    pub renderer: R,
+    /// The effects that are applied in order.
+    pub effects: Vec<Effect>,
+    /// Copies what the renderer drew to the screen when no effects are enabled.
+    copy: Effect,
+    targets: [RenderTarget; 3],
+    /// The bind groups of every pair of input and source targets.
+    input_bind_groups: Vec<BindGroup>,
+    sampler: Sampler,
+    /// The passes of the effects, planned by the last update.
+    planned: Vec<PlannedPass>,
+}
+
+impl<R: Renderer> PostProcessRenderer<R> {
+    /// Create a `PostProcessRenderer` around `renderer` with no effects.
+    pub fn new(data: &GameData, renderer: R) -> Self {
+        let sampler = clamp_sampler(data);
+        let targets = Self::make_targets(data, &sampler);
+        let mut renderer = Self {
+            renderer,
+            effects: Vec::new(),
+            copy: Effect::built_in(data, "copy", &["copy"], [0.0; 4]),
+            input_bind_groups: Self::make_input_bind_groups(data, &targets, &sampler),
+            targets,
+            sampler,
+            planned: Vec::new(),
+        };
+        renderer.plan();
+        renderer
+    }
+
+    /// Modifies the effects by adding `effect` to the end, then returns the renderer.
+    pub fn with_effect(mut self, effect: Effect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+
+    /// The first effect called `name`.
+    pub fn effect(&self, name: &str) -> Option<&Effect> {
+        self.effects.iter().find(|effect| effect.name == name)
+    }
+
+    /// The first effect called `name`, to be changed.
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut Effect> {
+        self.effects.iter_mut().find(|effect| effect.name == name)
+    }
+
+    /// Plans the passes of the enabled effects, or a copy to the screen if there are none.
+    /// The copy is the effect after the last one.
+    fn plan(&mut self) {
+        let mut effects = self
+            .effects
+            .iter()
+            .enumerate()
+            .filter(|(_, effect)| effect.enabled)
+            .map(|(index, effect)| (index, effect.pass_count()))
+            .collect::<Vec<_>>();
+        if effects.is_empty() {
+            effects.push((self.effects.len(), 1));
+        }
+        self.planned = plan(&effects);
+    }
+
+    fn make_targets(data: &GameData, sampler: &Sampler) -> [RenderTarget; 3] {
+        let graphics = data.graphics.lock();
+        let size = UVec2::new(graphics.config.width, graphics.config.height);
+        drop(graphics);
+        [0; 3].map(|_| RenderTarget::with_format(data, size, HDR_FORMAT, false, sampler))
+    }
+
+    fn make_input_bind_groups(
+        data: &GameData,
+        targets: &[RenderTarget; 3],
+        sampler: &Sampler,
+    ) -> Vec<BindGroup> {
+        let layout = Effect::input_bind_group_layout(data);
+        let graphics = data.graphics.lock();
+        (0..9)
+            .map(|index| {
+                let (input, source) = (&targets[index / 3], &targets[index % 3]);
+                graphics
+                    .device
+                    .create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: None,
+                        layout: &layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(&input.color_view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::Sampler(sampler),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: wgpu::BindingResource::TextureView(&source.color_view),
+                            },
+                        ],
+                    })
+            })
+            .collect()
+    }
+}
+
+impl<R: Renderer> Renderer for PostProcessRenderer<R> {
+    fn pass_count(&self) -> usize {
+        self.renderer.pass_count() + self.planned.len()
+    }
+
+    fn make_pass<'a>(
+        &'a self,
+        pass: usize,
+        view: &'a TextureView,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        let renderer_passes = self.renderer.pass_count();
+        if pass + 1 < renderer_passes {
+            return self.renderer.make_pass(pass, view, encoder);
+        }
+        if pass + 1 == renderer_passes {
+            return self
+                .renderer
+                .make_pass(pass, &self.targets[0].color_view, encoder);
+        }
+
+        let view = match self.planned[pass - renderer_passes].output {
+            Some(output) => &self.targets[output].color_view,
+            None => view,
+        };
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("post_process_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    fn render_pass<'a, 'b: 'a>(&'b self, pass: usize, render_pass: &'a mut wgpu::RenderPass<'b>) {
+        let renderer_passes = self.renderer.pass_count();
+        if pass < renderer_passes {
+            return self.renderer.render_pass(pass, render_pass);
+        }
+
+        let planned = self.planned[pass - renderer_passes];
+        let effect = self.effects.get(planned.effect).unwrap_or(&self.copy);
+        let pipeline = match planned.output {
+            Some(_) => &effect.pipelines[planned.pass],
+            None => &effect.screen_pipeline,
+        };
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(
+            0,
+            &self.input_bind_groups[planned.input * 3 + planned.source],
+            &[],
+        );
+        render_pass.set_bind_group(1, &effect.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn update(&mut self, data: &GameData) {
+        self.renderer.update(data);
+        for effect in &mut self.effects {
+            if let Some(shader) = effect.shader_file.as_mut().and_then(ShaderFile::poll) {
+                if let Err(err) = effect.set_shader(data, &shader) {
+                    let path = &effect.shader_file.as_ref().unwrap().path;
+                    eprintln!("{}: {err}", path.display());
+                }
+            }
+            if effect.uniforms_outdated {
+                data.graphics.lock().queue.write_buffer(
+                    &effect.uniform_buffer,
+                    0,
+                    &effect.uniforms,
+                );
+                effect.uniforms_outdated = false;
+            }
+        }
+        self.plan();
+    }
+
+    fn resize(&mut self, data: &GameData) {
+        self.renderer.resize(data);
+        self.targets = Self::make_targets(data, &self.sampler);
+        self.input_bind_groups = Self::make_input_bind_groups(data, &self.targets, &self.sampler);
+    }
+}
+
+#[test]
+fn plan_test() {
+    assert!(plan(&[]).is_empty());
+
+    // A single pass reads what the renderer drew and writes to the screen.
+    assert_eq!(
+        plan(&[(0, 1)]),
+        vec![PlannedPass {
+            effect: 0,
+            pass: 0,
+            input: 0,
+            source: 0,
+            output: None,
+        }]
+    );
+
+    // No pass writes to the target it reads, and every pass of an effect can read the
+    // image from before it.
+    let planned = plan(&[(2, 4), (3, 1), (5, 2)]);
+    assert_eq!(planned.len(), 7);
+    for pass in &planned {
+        assert_ne!(Some(pass.input), pass.output);
+        assert_ne!(Some(pass.source), pass.output);
+    }
+    for window in planned.windows(2) {
+        assert_eq!(window[0].output, Some(window[1].input));
+    }
+    assert_eq!(planned[3].effect, 2);
+    assert_eq!(planned[4].source, planned[3].output.unwrap());
+    assert_eq!(planned[6].output, None);
+}
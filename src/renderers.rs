@@ -5,19 +5,41 @@
 //! but only pieces may be used if needed.
 
 use std::{
-    collections::HashMap, f32::consts::TAU, fmt::Debug, hash::Hash, mem::size_of, num::NonZeroU32,
+    borrow::Cow,
+    collections::HashMap,
+    f32::consts::TAU,
+    fmt::Debug,
+    hash::Hash,
+    iter,
+    mem::size_of,
+    num::{NonZeroU32, NonZeroU8},
     path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
-use anyhow::Result;
-use glam::{Mat4, Quat, Vec3};
-use image::{DynamicImage, GenericImageView};
+use anyhow::{bail, Context, Result};
+use glam::{Mat4, Quat, UVec2, Vec3, Vec4Swizzles};
+use half::f16;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Pixel, Rgba, Rgba32FImage, RgbaImage};
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    BindGroup, Buffer, RenderPipeline, Sampler, TextureView,
+    BindGroup, Buffer, IndexFormat, RenderPipeline, Sampler, TextureFormat, TextureView,
 };
 
-use crate::{graphics::Renderer, GameData};
+use crate::{
+    bounds::{Aabb, BoundingSphere, Frustum},
+    compressed::CompressedImage,
+    graphics::Renderer,
+    instances::{
+        CustomInstances, InstanceBuffer, InstanceData, InstanceId, InstanceSlots, StreamingBuffer,
+    },
+    materials::Material,
+    shaders::{self, ShaderFile},
+    GameData,
+};
 
 /// An enum offering simpler projection description for renderers.
 pub enum SimpleProjection {
@@ -57,22 +79,119 @@ impl From<SimpleProjection> for Mat4 {
     }
 }
 
+/// How a `SimpleRenderer` draws its frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSettings {
+    /// The number of samples taken for each pixel, which is 1, 2, 4 or 8. Anything above
+    /// 1 smooths the edges of models, but the renderer must then make its own render pass
+    /// rather than drawing in one made by a `RenderGraph` or another renderer.
+    pub sample_count: u32,
+    /// The format of the depth texture.
+    pub depth_format: TextureFormat,
+    /// The format of the color texture that is drawn to, or `None` for the format of the
+    /// screen.
+    pub color_format: Option<TextureFormat>,
+    /// The color the screen is cleared to before anything is drawn.
+    pub clear_color: wgpu::Color,
+}
+
+impl RenderSettings {
+    /// Modifies the sample count, then returns itself.
+    pub fn with_sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Modifies the depth format, then returns itself.
+    pub fn with_depth_format(mut self, depth_format: TextureFormat) -> Self {
+        self.depth_format = depth_format;
+        self
+    }
+
+    /// Modifies the color format, then returns itself.
+    pub fn with_color_format(mut self, color_format: TextureFormat) -> Self {
+        self.color_format = Some(color_format);
+        self
+    }
+
+    /// Modifies the clear color, then returns itself.
+    pub fn with_clear_color(mut self, clear_color: wgpu::Color) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    /// The format of the color texture that is drawn to.
+    pub fn output_format(&self, data: &GameData) -> TextureFormat {
+        self.color_format
+            .unwrap_or_else(|| data.graphics.lock().config.format)
+    }
+
+    /// Whether renderers drawing with these settings and `other` can draw in the same pass,
+    /// which needs the same sample count and formats.
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.sample_count == other.sample_count
+            && self.depth_format == other.depth_format
+            && self.color_format == other.color_format
+    }
+
+    /// Checks that the depth format is a depth format, and that the device can multisample
+    /// both it and the color format with the sample count.
+    pub fn validate(&self, data: &GameData) -> Result<()> {
+        if self.depth_format.describe().sample_type != wgpu::TextureSampleType::Depth {
+            bail!("{:?} isn't a depth format", self.depth_format);
+        }
+
+        let color_format = self.output_format(data);
+        let graphics = data.graphics.lock();
+        for format in [color_format, self.depth_format] {
+            if !graphics.supports_sample_count(format, self.sample_count) {
+                bail!(
+                    "{} samples per pixel aren't supported for {format:?}",
+                    self.sample_count
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            sample_count: 1,
+            depth_format: TextureFormat::Depth32Float,
+            color_format: None,
+            clear_color: wgpu::Color::BLACK,
+        }
+    }
+}
+
 /// A simple renderer with pipelines for both color vertices and texture vertices. No
 /// lighting is performed.
 pub struct SimpleRenderer {
-    color_pipeline: RenderPipeline,
-    texture_pipeline: RenderPipeline,
+    color_pipelines: [RenderPipeline; 5],
+    texture_pipelines: [RenderPipeline; 5],
+    camera: Mat4,
+    projection: Mat4,
     camera_buffer: Buffer,
     projection_buffer: Buffer,
     projection_bind_group: BindGroup,
+    settings: RenderSettings,
     /// A view of the depth texture.
     pub depth_texture_view: TextureView,
+    /// A view of the texture that is drawn to and resolved to the screen when
+    /// multisampling.
+    pub multisampled_texture_view: Option<TextureView>,
     /// A sampler for nearest filters (magnified textures looked pixelated).
     pub nearest_sampler: Sampler,
     /// A sampler for linear filters (magnified textures looked blurry).
     pub linear_sampler: Sampler,
     /// A list of all `Model`s that will be rendered.
     pub models: Vec<Model>,
+    materials: Vec<Material>,
+    /// The source of the default pipelines if it isn't `simple.wgsl`.
+    shader_source: Option<String>,
+    shader_file: Option<ShaderFile>,
 }
 
 impl SimpleRenderer {
@@ -120,22 +239,33 @@ impl SimpleRenderer {
                     layout: &projection_bind_group_layout,
                 });
 
+        let settings = RenderSettings::default();
         Self {
-            color_pipeline: Self::color_pipeline(data),
-            texture_pipeline: Self::texture_pipeline(data),
+            color_pipelines: BlendMode::ALL
+                .map(|blend_mode| Self::color_pipeline_for(data, blend_mode, &settings)),
+            texture_pipelines: BlendMode::ALL
+                .map(|blend_mode| Self::texture_pipeline_for(data, blend_mode, &settings)),
+            camera: Mat4::IDENTITY,
+            projection,
             camera_buffer,
             projection_buffer,
             projection_bind_group,
+            settings,
             depth_texture_view: Self::depth_texture(data),
+            multisampled_texture_view: None,
             nearest_sampler: Self::nearest_sampler(data),
             linear_sampler: Self::linear_sampler(data),
             models: Vec::new(),
+            materials: Vec::new(),
+            shader_source: None,
+            shader_file: None,
         }
     }
 
     /// Replaces the camera of the renderer and updates its
     /// buffer
     pub fn set_camera(&mut self, data: &GameData, camera: Mat4) {
+        self.camera = camera;
         data.graphics.lock().queue.write_buffer(
             &self.camera_buffer,
             0,
@@ -146,153 +276,222 @@ impl SimpleRenderer {
     /// Replaces the projection of the renderer and updates its
     /// buffer
     pub fn set_projection(&mut self, data: &GameData, projection: SimpleProjection) {
+        self.projection = Mat4::from(projection);
         data.graphics.lock().queue.write_buffer(
             &self.projection_buffer,
             0,
-            bytemuck::cast_slice(&[Mat4::from(projection).to_cols_array_2d()]),
+            bytemuck::cast_slice(&[self.projection.to_cols_array_2d()]),
         )
     }
 
-    /// Makes the default color pipeline.
-    pub fn color_pipeline(data: &GameData) -> RenderPipeline {
-        let shader =
-            data.graphics
-                .lock()
-                .device
-                .create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: None,
-                    source: wgpu::ShaderSource::Wgsl(include_str!("simple.wgsl").into()),
-                });
+    /// The settings the renderer draws with.
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
 
-        let mat4_bind_group_layout = Self::mat4_bind_group_layout(data);
+    /// Replaces the settings of the renderer once they are validated, remaking its pipelines,
+    /// including those of its materials, and textures if the sample count or a format changed.
+    pub fn set_settings(&mut self, data: &GameData, settings: RenderSettings) -> Result<()> {
+        settings.validate(data)?;
+        let remake = settings.sample_count != self.settings.sample_count
+            || settings.depth_format != self.settings.depth_format
+            || settings.color_format != self.settings.color_format;
+
+        if remake {
+            let source = self
+                .shader_source
+                .as_deref()
+                .unwrap_or(include_str!("simple.wgsl"));
+            // Nothing is replaced until every pipeline is made, so an error leaves the
+            // renderer drawing with the old settings.
+            let (color_pipelines, texture_pipelines) =
+                Self::make_pipelines(data, &self.shader_name(), source, &settings)?;
+            let material_pipelines = self
+                .materials
+                .iter()
+                .map(|material| material.build_pipelines(data, &settings))
+                .collect::<Result<Vec<_>>>()?;
+
+            self.color_pipelines = color_pipelines;
+            self.texture_pipelines = texture_pipelines;
+            for (material, pipelines) in self.materials.iter_mut().zip(material_pipelines) {
+                material.pipelines = Some(pipelines);
+            }
+            self.settings = settings;
+            self.resize(data);
+        } else {
+            self.settings = settings;
+        }
+        Ok(())
+    }
 
-        let color_pipeline_layout =
-            data.graphics
-                .lock()
-                .device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: None,
-                    bind_group_layouts: &[&mat4_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
+    /// Modifies the settings of the renderer like `set_settings`, then returns itself.
+    pub fn with_settings(mut self, data: &GameData, settings: RenderSettings) -> Result<Self> {
+        self.set_settings(data, settings)?;
+        Ok(self)
+    }
 
-        let fragment_format = data.graphics.lock().config.format;
+    /// Makes the pipelines of `material` for the settings of the renderer, adds it and
+    /// returns its index, which models select it with. If the shader of the material has an
+    /// error it is returned instead.
+    pub fn add_material(&mut self, data: &GameData, mut material: Material) -> Result<usize> {
+        material.make_pipelines(data, &self.settings)?;
+        self.materials.push(material);
+        Ok(self.materials.len() - 1)
+    }
 
-        data.graphics
-            .lock()
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Tri Color Pipeline"),
-                layout: Some(&color_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "color_vertex",
-                    buffers: &[ColorVertex::desc(), Transform::desc()],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "color_fragment",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: fragment_format,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    unclipped_depth: false,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth32Float,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-            })
+    /// The first material with the name `name`.
+    pub fn material(&self, name: &str) -> Option<&Material> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+
+    /// The first material with the name `name`, which can be modified.
+    pub fn material_mut(&mut self, name: &str) -> Option<&mut Material> {
+        self.materials
+            .iter_mut()
+            .find(|material| material.name == name)
     }
 
-    /// Makes the default color pipeline.
+    /// The frustum of the current camera and projection.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.projection * self.camera)
+    }
+
+    /// The position of the camera in the world.
+    pub fn camera_position(&self) -> Vec3 {
+        self.camera.inverse().w_axis.xyz()
+    }
+
+    /// The number of instances across every model that were skipped by frustum culling in
+    /// the last update.
+    pub fn culled_count(&self) -> u32 {
+        self.models.iter().map(|model| model.culled_count).sum()
+    }
+
+    /// Makes the default color pipeline for opaque models with the default settings.
+    pub fn color_pipeline(data: &GameData) -> RenderPipeline {
+        Self::color_pipeline_for(data, BlendMode::Opaque, &RenderSettings::default())
+    }
+
+    /// Makes the default texture pipeline for opaque models with the default settings.
     pub fn texture_pipeline(data: &GameData) -> RenderPipeline {
-        let texture_bind_group_layout = Texture::bind_group_layout(data);
+        Self::texture_pipeline_for(data, BlendMode::Opaque, &RenderSettings::default())
+    }
 
-        let shader =
-            data.graphics
-                .lock()
-                .device
-                .create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: None,
-                    source: wgpu::ShaderSource::Wgsl(include_str!("simple.wgsl").into()),
-                });
+    /// Makes the default color pipeline for models with `blend_mode`, drawing with the
+    /// formats and sample count of `settings`.
+    pub fn color_pipeline_for(
+        data: &GameData,
+        blend_mode: BlendMode,
+        settings: &RenderSettings,
+    ) -> RenderPipeline {
+        Self::model_shader(&Self::shader(data), false, blend_mode)
+            .pipeline(data, blend_mode, settings)
+    }
 
-        let mat4_bind_group_layout = Self::mat4_bind_group_layout(data);
+    /// Makes the default texture pipeline for models with `blend_mode`, drawing with the
+    /// formats and sample count of `settings`.
+    pub fn texture_pipeline_for(
+        data: &GameData,
+        blend_mode: BlendMode,
+        settings: &RenderSettings,
+    ) -> RenderPipeline {
+        Self::model_shader(&Self::shader(data), true, blend_mode)
+            .pipeline(data, blend_mode, settings)
+    }
 
-        let texture_pipeline_layout =
-            data.graphics
-                .lock()
-                .device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: None,
-                    bind_group_layouts: &[&mat4_bind_group_layout, &texture_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
+    /// The entry points of `simple.wgsl`, or a shader like it, for models with `blend_mode`.
+    fn model_shader(
+        module: &wgpu::ShaderModule,
+        textured: bool,
+        blend_mode: BlendMode,
+    ) -> ModelShader<'_> {
+        let cutout = blend_mode == BlendMode::AlphaCutout;
+        let (label, vertex, fragment) = match (textured, cutout) {
+            (false, false) => ("Tri Color Pipeline", "color_vertex", "color_fragment"),
+            (false, true) => (
+                "Tri Color Pipeline",
+                "color_vertex",
+                "color_cutout_fragment",
+            ),
+            (true, false) => ("Tri Texture Pipeline", "texture_vertex", "texture_fragment"),
+            (true, true) => (
+                "Tri Texture Pipeline",
+                "texture_vertex",
+                "texture_cutout_fragment",
+            ),
+        };
+        ModelShader {
+            label,
+            module,
+            vertex_entry_point: vertex,
+            fragment_entry_point: fragment,
+            textured,
+            bind_group_layouts: &[],
+        }
+    }
+
+    /// Makes the default color and texture pipelines for `settings` from the WGSL in
+    /// `source`, which is compiled as `name`.
+    fn make_pipelines(
+        data: &GameData,
+        name: &str,
+        source: &str,
+        settings: &RenderSettings,
+    ) -> Result<([RenderPipeline; 5], [RenderPipeline; 5])> {
+        let module = data.shaders.lock().module(data, name, source, &[])?;
+        shaders::validate(data, || {
+            let pipelines = |textured| {
+                BlendMode::ALL.map(|blend_mode| {
+                    Self::model_shader(&module, textured, blend_mode)
+                        .pipeline(data, blend_mode, settings)
+                })
+            };
+            (pipelines(false), pipelines(true))
+        })
+    }
 
-        let fragment_format = data.graphics.lock().config.format;
+    /// Draws models with the default pipelines made from the WGSL file at `path` instead of
+    /// `simple.wgsl`, remaking them whenever the file changes. The shader needs the entry
+    /// points and bindings of `simple.wgsl`, including `color_cutout_fragment` and
+    /// `texture_cutout_fragment` for `BlendMode::AlphaCutout`, and is preprocessed the same
+    /// way. If the shader has an error it is returned, and when the file is changed to have
+    /// an error it is printed, but either way the last pipelines without errors are kept and
+    /// the file is still watched.
+    pub fn watch_shader<P: AsRef<Path>>(&mut self, data: &GameData, path: P) -> Result<()> {
+        let mut file = ShaderFile::new(&path);
+        let source = file
+            .poll()
+            .with_context(|| format!("Can't read {}", path.as_ref().display()))?;
+        self.shader_file = Some(file);
+        self.set_shader_source(data, source)
+    }
 
-        data.graphics
+    /// Replaces the default pipelines with ones made from `source` if it has no errors.
+    fn set_shader_source(&mut self, data: &GameData, source: String) -> Result<()> {
+        let (color_pipelines, texture_pipelines) =
+            Self::make_pipelines(data, &self.shader_name(), &source, &self.settings)?;
+        self.color_pipelines = color_pipelines;
+        self.texture_pipelines = texture_pipelines;
+        self.shader_source = Some(source);
+        Ok(())
+    }
+
+    /// The name the source of the default pipelines is compiled as, which is the path of the
+    /// watched shader if there is one.
+    fn shader_name(&self) -> String {
+        match &self.shader_file {
+            Some(file) => file.path.display().to_string(),
+            None => "simple.wgsl".to_string(),
+        }
+    }
+
+    /// Compiles `simple.wgsl`, or returns the module it was compiled to before.
+    fn shader(data: &GameData) -> Arc<wgpu::ShaderModule> {
+        data.shaders
             .lock()
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Tri Texture Pipeline"),
-                layout: Some(&texture_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "texture_vertex",
-                    buffers: &[TextureVertex::desc(), Transform::desc()],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "texture_fragment",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: fragment_format,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    unclipped_depth: false,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth32Float,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-            })
+            .file_module(data, "simple.wgsl", &[])
+            .unwrap()
     }
 
     /// Makes the default linear sampler.
@@ -325,6 +524,42 @@ impl SimpleRenderer {
             })
     }
 
+    /// Makes a linear sampler that also blends between mipmaps, and filters textures seen at
+    /// an angle from up to `anisotropy` samples. `anisotropy` must be 1, 2, 4, 8 or 16, and
+    /// is ignored by GPUs that don't support anisotropic filtering.
+    pub fn linear_mipmap_sampler(data: &GameData, anisotropy: u8) -> Sampler {
+        data.graphics
+            .lock()
+            .device
+            .create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::Repeat,
+                address_mode_v: wgpu::AddressMode::Repeat,
+                address_mode_w: wgpu::AddressMode::Repeat,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Linear,
+                anisotropy_clamp: NonZeroU8::new(anisotropy).filter(|_| anisotropy > 1),
+                ..Default::default()
+            })
+    }
+
+    /// Makes a nearest sampler that also picks the nearest mipmap, so magnified textures
+    /// still look pixelated but minified ones don't shimmer.
+    pub fn nearest_mipmap_sampler(data: &GameData) -> Sampler {
+        data.graphics
+            .lock()
+            .device
+            .create_sampler(&wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::Repeat,
+                address_mode_v: wgpu::AddressMode::Repeat,
+                address_mode_w: wgpu::AddressMode::Repeat,
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Nearest,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            })
+    }
+
     /// Makes the default 4x4 matrix bind group layout.
     pub fn mat4_bind_group_layout(data: &GameData) -> wgpu::BindGroupLayout {
         data.graphics
@@ -359,8 +594,18 @@ impl SimpleRenderer {
 
     /// Makes the default `TextureView` of a depth texture.
     pub fn depth_texture(data: &GameData) -> TextureView {
+        Self::settings_texture(data, &RenderSettings::default(), true)
+    }
+
+    /// Makes a screen sized texture matching `settings`, which is either the depth texture
+    /// or the multisampled color texture.
+    fn settings_texture(data: &GameData, settings: &RenderSettings, depth: bool) -> TextureView {
         let width = data.graphics.lock().config.width;
         let height = data.graphics.lock().config.height;
+        let format = match depth {
+            true => settings.depth_format,
+            false => settings.output_format(data),
+        };
 
         let size = wgpu::Extent3d {
             width,
@@ -376,9 +621,9 @@ impl SimpleRenderer {
                 label: None,
                 size,
                 mip_level_count: 1,
-                sample_count: 1,
+                sample_count: settings.sample_count,
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Depth32Float,
+                format,
                 usage: wgpu::TextureUsages::RENDER_ATTACHMENT
                     | wgpu::TextureUsages::TEXTURE_BINDING,
             });
@@ -386,44 +631,186 @@ impl SimpleRenderer {
     }
 }
 
+/// A shader that draws the models of a `SimpleRenderer`. Group 0 of the shader is the
+/// projection and camera, group 1 is the texture of textured models, and the groups in
+/// `bind_group_layouts` come after those.
+pub(crate) struct ModelShader<'a> {
+    pub label: &'a str,
+    pub module: &'a wgpu::ShaderModule,
+    pub vertex_entry_point: &'a str,
+    pub fragment_entry_point: &'a str,
+    pub textured: bool,
+    pub bind_group_layouts: &'a [&'a wgpu::BindGroupLayout],
+}
+
+impl ModelShader<'_> {
+    /// Makes the pipeline for models with `blend_mode`, drawing with the formats and sample
+    /// count of `settings`.
+    pub fn pipeline(
+        &self,
+        data: &GameData,
+        blend_mode: BlendMode,
+        settings: &RenderSettings,
+    ) -> RenderPipeline {
+        let mat4_bind_group_layout = SimpleRenderer::mat4_bind_group_layout(data);
+        let texture_bind_group_layout = Texture::bind_group_layout(data);
+
+        let mut bind_group_layouts = vec![&mat4_bind_group_layout];
+        if self.textured {
+            bind_group_layouts.push(&texture_bind_group_layout);
+        }
+        bind_group_layouts.extend(self.bind_group_layouts);
+
+        let pipeline_layout =
+            data.graphics
+                .lock()
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &bind_group_layouts,
+                    push_constant_ranges: &[],
+                });
+
+        let fragment_format = settings.output_format(data);
+        let vertex_desc = match self.textured {
+            true => TextureVertex::desc(),
+            false => ColorVertex::desc(),
+        };
+
+        data.graphics
+            .lock()
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(self.label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: self.module,
+                    entry_point: self.vertex_entry_point,
+                    buffers: &[vertex_desc, Transform::desc(), InstanceData::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: self.module,
+                    entry_point: self.fragment_entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: fragment_format,
+                        blend: blend_mode.blend_state(),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: settings.depth_format,
+                    depth_write_enabled: !blend_mode.is_transparent(),
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: settings.sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+    }
+}
+
 impl Renderer for SimpleRenderer {
+    fn pass_settings(&self) -> RenderSettings {
+        self.settings
+    }
+
     fn render<'a, 'b: 'a>(&'b self, render_pass: &'a mut wgpu::RenderPass<'b>) {
         render_pass.set_bind_group(0, &self.projection_bind_group, &[]);
-        for model in &self.models {
+
+        // Transparent models are drawn after everything else, from back to front, so that
+        // whatever is behind them has already been drawn.
+        let (mut transparent, opaque): (Vec<&Model>, Vec<&Model>) = self
+            .models
+            .iter()
+            .partition(|model| model.blend_mode.is_transparent());
+        transparent.sort_by(|a, b| b.distance.total_cmp(&a.distance));
+
+        for model in opaque.into_iter().chain(transparent) {
+            let pipeline = model.blend_mode as usize;
+            let material = model
+                .material
+                .and_then(|material| self.materials.get(material))
+                .filter(|material| {
+                    material.textured() == matches!(model.vertex_type, VertexType::TextureVertex(_))
+                });
+            let bound =
+                material.is_some_and(|material| material.bind(render_pass, model.blend_mode));
             match &model.vertex_type {
-                VertexType::ColorVertex => render_pass.set_pipeline(&self.color_pipeline),
+                VertexType::ColorVertex => {
+                    if !bound {
+                        render_pass.set_pipeline(&self.color_pipelines[pipeline]);
+                    }
+                }
                 VertexType::TextureVertex(texture) => {
-                    render_pass.set_pipeline(&self.texture_pipeline);
+                    if !bound {
+                        render_pass.set_pipeline(&self.texture_pipelines[pipeline]);
+                    }
                     render_pass.set_bind_group(1, &texture.diffuse, &[]);
                 }
             }
             render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
-            render_pass.set_index_buffer(model.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..model.index_count, 0, 0..model.transform_count);
+            render_pass.set_vertex_buffer(1, model.transform_buffer().slice(..));
+            render_pass.set_vertex_buffer(2, model.instance_data_buffer().slice(..));
+            match &model.index_buffer {
+                Some(index_buffer) => {
+                    render_pass.set_index_buffer(index_buffer.slice(..), model.index_format);
+                    render_pass.draw_indexed(0..model.index_count, 0, 0..model.transform_count);
+                }
+                None => render_pass.draw(0..model.vertex_count, 0..model.transform_count),
+            }
         }
     }
 
     fn update(&mut self, data: &GameData) {
+        let frustum = self.frustum();
+        let eye = self.camera_position();
         for model in &mut self.models {
-            if model.transforms_outdated {
-                model.update_transforms(data);
+            model.update_transforms(data);
+            model.cull(data, &frustum, eye);
+        }
+        if let Some(source) = self.shader_file.as_mut().and_then(ShaderFile::poll) {
+            if let Err(err) = self.set_shader_source(data, source) {
+                let path = &self.shader_file.as_ref().unwrap().path;
+                eprintln!("{}: {err}", path.display());
             }
         }
+        for material in &mut self.materials {
+            material.update(data, &self.settings);
+        }
     }
 
     fn make_render_pass<'a>(
         &'a self,
         view: &'a TextureView,
         encoder: &'a mut wgpu::CommandEncoder,
-    ) -> wgpu::RenderPass {
+    ) -> wgpu::RenderPass<'a> {
+        // When multisampling, the samples are drawn to their own texture and averaged into
+        // the view.
+        let (view, resolve_target) = match &self.multisampled_texture_view {
+            Some(multisampled) => (multisampled, Some(view)),
+            None => (view, None),
+        };
         encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("render_pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                 view,
-                resolve_target: None,
+                resolve_target,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    load: wgpu::LoadOp::Clear(self.settings.clear_color),
                     store: true,
                 },
             })],
@@ -439,7 +826,9 @@ impl Renderer for SimpleRenderer {
     }
 
     fn resize(&mut self, data: &GameData) {
-        self.depth_texture_view = Self::depth_texture(data);
+        self.depth_texture_view = Self::settings_texture(data, &self.settings, true);
+        self.multisampled_texture_view = (self.settings.sample_count > 1)
+            .then(|| Self::settings_texture(data, &self.settings, false));
     }
 }
 
@@ -459,6 +848,111 @@ impl VertexSlice<'_> {
             Self::TextureVertices(vertices, ..) => bytemuck::cast_slice(vertices),
         }
     }
+
+    /// The number of vertices in the slice.
+    pub fn len(&self) -> usize {
+        match *self {
+            Self::ColorVertices(vertices) => vertices.len(),
+            Self::TextureVertices(vertices, ..) => vertices.len(),
+        }
+    }
+
+    /// The positions of the vertices in the slice.
+    pub fn positions(&self) -> Vec<Vec3> {
+        match *self {
+            Self::ColorVertices(vertices) => vertices.iter().map(|v| v.pos.into()).collect(),
+            Self::TextureVertices(vertices, ..) => vertices.iter().map(|v| v.pos.into()).collect(),
+        }
+    }
+
+    /// Returns `true` if there are no vertices in the slice.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// How the colors of a model are combined with what has already been drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// The model is drawn over what is behind it and hides what is drawn behind it later.
+    /// Parts with an alpha under 1 are still mixed with what was drawn before them, as they
+    /// were before blend modes were added.
+    #[default]
+    Opaque,
+    /// The model is mixed with what is behind it by its alpha.
+    Alpha,
+    /// The color of the model, multiplied by its alpha, is added to what is behind it.
+    Additive,
+    /// Like `Alpha`, but the colors of the model have already been multiplied by its alpha.
+    Premultiplied,
+    /// The model is opaque, but parts of it with an alpha under 0.5 aren't drawn at all.
+    AlphaCutout,
+}
+
+impl BlendMode {
+    /// Every blend mode, in the order of their values.
+    pub const ALL: [Self; 5] = [
+        Self::Opaque,
+        Self::Alpha,
+        Self::Additive,
+        Self::Premultiplied,
+        Self::AlphaCutout,
+    ];
+
+    /// Returns `true` if the model can be seen through. Transparent models don't write to
+    /// the depth buffer and are drawn from back to front after all other models.
+    pub fn is_transparent(self) -> bool {
+        matches!(self, Self::Alpha | Self::Additive | Self::Premultiplied)
+    }
+
+    /// The shader defines that materials are compiled with for models with this blend mode,
+    /// which is `ALPHA_CUTOUT` for `BlendMode::AlphaCutout`.
+    pub fn defines(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::AlphaCutout => &[("ALPHA_CUTOUT", "")],
+            _ => &[],
+        }
+    }
+
+    /// The blend state used by pipelines for this blend mode.
+    pub fn blend_state(self) -> Option<wgpu::BlendState> {
+        match self {
+            Self::Opaque | Self::Alpha => Some(wgpu::BlendState::ALPHA_BLENDING),
+            Self::AlphaCutout => Some(wgpu::BlendState::REPLACE),
+            Self::Additive => Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            }),
+            Self::Premultiplied => Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+        }
+    }
+}
+
+#[test]
+fn render_settings_compatible_test() {
+    let settings = RenderSettings::default();
+    assert!(settings.is_compatible(&settings.with_clear_color(wgpu::Color::WHITE)));
+    assert!(!settings.is_compatible(&settings.with_sample_count(4)));
+    assert!(!settings.is_compatible(&settings.with_depth_format(TextureFormat::Depth24Plus)));
+    assert!(!settings.is_compatible(&settings.with_color_format(TextureFormat::Rgba16Float)));
+}
+
+#[test]
+fn blend_mode_test() {
+    // The pipelines of `SimpleRenderer` are indexed by the value of the blend mode.
+    for (index, blend_mode) in BlendMode::ALL.into_iter().enumerate() {
+        assert_eq!(blend_mode as usize, index);
+    }
+    assert!(!BlendMode::AlphaCutout.is_transparent());
+    assert!(BlendMode::Additive.is_transparent());
 }
 
 /// The type of vertex
@@ -484,84 +978,184 @@ pub struct Model {
     pub vertex_buffer: Buffer,
     /// The type of the vertices in `vertex_buffer`.
     pub vertex_type: VertexType,
-    /// The list of indices.
-    pub index_buffer: Buffer,
+    /// The number of vertices in `vertex_buffer`.
+    pub vertex_count: u32,
+    /// The list of indices. If this is `None` then the vertices are drawn in the
+    /// order they are in `vertex_buffer`.
+    pub index_buffer: Option<Buffer>,
+    /// The format of the indices in `index_buffer`.
+    pub index_format: IndexFormat,
     /// The number of indices in `index_buffer`.
     pub index_count: u32,
-    /// The list of instances of the model that will be visible. Every
+    /// The transforms of the instances of the model that will be visible. Every
     /// transform will be a new copy of the model without duplicating memory
     /// use.
-    pub transforms: Vec<Transform>,
-    /// When a transform is modified then this is set to `true`. Whenever the model is
-    /// next rendered it will rebuild the buffer and set this to `false`.
-    pub transforms_outdated: bool,
-    /// The handle to the transform buffer.
-    pub transform_buffer: Buffer,
-    /// The count of the transforms on the buffer. This has to be included because
-    /// `transforms.len()` might be different to the previous size and then the buffer would need
-    /// to be reallocated.
+    transforms: Vec<Transform>,
+    /// The matrix of every instance, which is what is written to the buffer. They are made
+    /// from `transforms`, except for the ones set by `Model::set_instance_matrix`.
+    matrices: InstanceBuffer<[[f32; 4]; 4]>,
+    /// The indices of the matrices that have to be made again from their transform, because
+    /// the transform was borrowed mutably.
+    outdated_matrices: Vec<usize>,
+    /// The color and texture region of every instance.
+    instance_data: InstanceBuffer<InstanceData>,
+    /// Extra values for every instance that can be read by custom pipelines.
+    custom_instance_data: Option<Box<dyn CustomInstances>>,
+    /// The handles of the instances.
+    instances: InstanceSlots,
+    /// The number of instances on the instance buffers. This has to be included because
+    /// the number of instances might be different to the previous size and then the
+    /// buffers would need to be reallocated. When instances are culled this is the number
+    /// of instances that are left.
     pub transform_count: u32,
+    /// The bounding box of the vertices before they are transformed.
+    pub aabb: Aabb,
+    /// The bounding sphere of the vertices before they are transformed.
+    pub bounding_sphere: BoundingSphere,
+    /// If this is `true` then `Model::cull` skips instances that are outside of the
+    /// camera's view.
+    pub frustum_culling: bool,
+    /// The number of instances that were skipped by the last call to `Model::cull`.
+    pub culled_count: u32,
+    /// How the model is combined with what is behind it.
+    blend_mode: BlendMode,
+    /// The index of the material in the `SimpleRenderer` that the model is drawn with, or
+    /// `None` for the default pipelines. Models whose vertices don't match the material are
+    /// also drawn with the default pipelines.
+    pub material: Option<usize>,
+    /// The distance from the camera to the farthest instance that will be drawn, if the
+    /// model is transparent.
+    distance: f32,
+    /// The instances left after culling, in the order they are drawn, if the model is culled
+    /// or transparent.
+    culled: Option<CulledInstances>,
+    /// The frustum and camera position the instances were last culled with, or `None` if the
+    /// instances have changed since then.
+    culled_view: Option<(Frustum, Vec3)>,
+}
+
+/// The buffers of the instances of a model that will be drawn.
+struct CulledInstances {
+    /// The indices of the instances in the buffers, in the order they are drawn.
+    indices: Vec<usize>,
+    transforms: StreamingBuffer,
+    instance_data: StreamingBuffer,
+    custom_instance_data: Option<StreamingBuffer>,
 }
 
 impl Model {
-    /// Make a model from the described features.
+    /// Make a model from the described features. The indices are stored as 16 bit
+    /// integers when there are few enough vertices for that to be possible, and as
+    /// 32 bit integers otherwise.
     pub fn new(
         data: &GameData,
         vertices: VertexSlice,
-        indices: &[u16],
+        indices: &[u32],
         transforms: Vec<Transform>,
     ) -> Self {
-        let vertex_buffer = data
+        let index_format = Self::index_format(vertices.len());
+        let contents = match index_format {
+            IndexFormat::Uint16 => {
+                bytemuck::cast_slice(&indices.iter().map(|x| *x as u16).collect::<Vec<u16>>())
+                    .to_vec()
+            }
+            IndexFormat::Uint32 => bytemuck::cast_slice(indices).to_vec(),
+        };
+        let index_buffer = data
             .graphics
             .lock()
             .device
             .create_buffer_init(&BufferInitDescriptor {
                 label: None,
-                contents: vertices.contents(),
-                usage: wgpu::BufferUsages::VERTEX,
+                contents: &contents,
+                usage: wgpu::BufferUsages::INDEX,
             });
-        let index_buffer = data
+
+        let mut model = Self::new_non_indexed(data, vertices, transforms);
+        model.index_buffer = Some(index_buffer);
+        model.index_format = index_format;
+        model.index_count = indices.len() as u32;
+        model
+    }
+
+    /// Make a model without an index buffer, every three vertices in order make up a
+    /// triangle.
+    pub fn new_non_indexed(
+        data: &GameData,
+        vertices: VertexSlice,
+        transforms: Vec<Transform>,
+    ) -> Self {
+        let vertex_buffer = data
             .graphics
             .lock()
             .device
             .create_buffer_init(&BufferInitDescriptor {
                 label: None,
-                contents: bytemuck::cast_slice(indices),
-                usage: wgpu::BufferUsages::INDEX,
+                contents: vertices.contents(),
+                usage: wgpu::BufferUsages::VERTEX,
             });
-        let transform_buffer =
-            data.graphics
-                .lock()
-                .device
-                .create_buffer_init(&BufferInitDescriptor {
-                    label: None,
-                    contents: bytemuck::cast_slice(
-                        &transforms
-                            .iter()
-                            .map(Transform::matrix)
-                            .collect::<Vec<[[f32; 4]; 4]>>(),
-                    ),
-                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                });
+        let instance_count = transforms.len();
+        let positions = vertices.positions();
+
+        Self {
+            vertex_buffer,
+            vertex_count: vertices.len() as u32,
+            vertex_type: vertices.into(),
+            index_buffer: None,
+            index_format: IndexFormat::Uint16,
+            index_count: 0,
+            transform_count: instance_count as u32,
+            matrices: InstanceBuffer::new_pod(
+                data,
+                transforms.iter().map(Transform::matrix).collect(),
+            ),
+            transforms,
+            outdated_matrices: Vec::new(),
+            instance_data: InstanceBuffer::new_pod(
+                data,
+                vec![InstanceData::default(); instance_count],
+            ),
+            custom_instance_data: None,
+            instances: InstanceSlots::with_len(instance_count),
+            aabb: Aabb::from_points(positions.iter().copied()),
+            bounding_sphere: BoundingSphere::from_points(positions.iter().copied()),
+            frustum_culling: true,
+            culled_count: 0,
+            blend_mode: BlendMode::Opaque,
+            material: None,
+            distance: 0.0,
+            culled: None,
+            culled_view: None,
+        }
+    }
+
+    /// Load a model from an obj file.
+    pub fn from_obj<P: AsRef<Path> + Debug>(
+        data: &GameData,
+        path: P,
+        sampler: &Sampler,
+        transforms: Vec<Transform>,
+    ) -> Result<Vec<Self>> {
+        Self::load_obj(data, path, sampler, transforms, false)
+    }
 
-        Self {
-            vertex_buffer,
-            vertex_type: vertices.into(),
-            index_buffer,
-            index_count: indices.len() as u32,
-            transform_count: transforms.len() as u32,
-            transforms,
-            transforms_outdated: false,
-            transform_buffer,
-        }
+    /// Load a model from an obj file, generating mipmaps for its textures like
+    /// `Texture::new_mipmapped`.
+    pub fn from_obj_mipmapped<P: AsRef<Path> + Debug>(
+        data: &GameData,
+        path: P,
+        sampler: &Sampler,
+        transforms: Vec<Transform>,
+    ) -> Result<Vec<Self>> {
+        Self::load_obj(data, path, sampler, transforms, true)
     }
 
-    /// Load a model from an obj file.
-    pub fn from_obj<P: AsRef<Path> + Debug>(
+    fn load_obj<P: AsRef<Path> + Debug>(
         data: &GameData,
         path: P,
         sampler: &Sampler,
         transforms: Vec<Transform>,
+        mipmapped: bool,
     ) -> Result<Vec<Self>> {
         let (models, materials) = tobj::load_obj(
             &path,
@@ -575,19 +1169,14 @@ impl Model {
         let to_ret = models
             .into_iter()
             .map(|model| {
-                let indices = model
-                    .mesh
-                    .indices
-                    .into_iter()
-                    .map(|x| x as u16)
-                    .collect::<Vec<u16>>();
+                let indices = model.mesh.indices;
 
                 match model.mesh.material_id {
                     Some(..) => {
                         let positions = model.mesh.positions.chunks(3);
                         let tex_coords = model.mesh.texcoords.chunks(2);
 
-                        let vertices = iter_tools::zip(positions, tex_coords)
+                        let vertices = std::iter::zip(positions, tex_coords)
                             .map(|(pos, tex_coords)| TextureVertex {
                                 pos: [pos[0], pos[1], pos[2]],
                                 tex_coords: [tex_coords[0], -tex_coords[1]],
@@ -595,8 +1184,11 @@ impl Model {
                             .collect::<Vec<TextureVertex>>();
 
                         let texture_path = &materials.as_ref().unwrap()[0].diffuse_texture;
-                        let texture =
-                            Texture::new(data, &image::open(&texture_path).unwrap(), sampler);
+                        let image = image::open(texture_path).unwrap();
+                        let texture = match mipmapped {
+                            true => Texture::new_mipmapped(data, &image, sampler),
+                            false => Texture::new(data, &image, sampler),
+                        };
 
                         Self::new(
                             data,
@@ -737,84 +1329,428 @@ impl Model {
         )
     }
 
-    /// Put values of the transforms from the `Vec` on the struct to the
-    /// actual buffer for rendering.
+    /// Returns the smallest index format that can refer to every one of `vertex_count`
+    /// vertices. The largest index of each format is never used, as some backends treat it
+    /// as a primitive restart.
+    pub fn index_format(vertex_count: usize) -> IndexFormat {
+        if vertex_count <= u16::MAX as usize {
+            IndexFormat::Uint16
+        } else {
+            IndexFormat::Uint32
+        }
+    }
+
+    /// Put values of the transforms and instance data on the struct to the
+    /// actual buffers for rendering. Only the values that have changed are written,
+    /// unless the buffers have to grow. Instances that are culled or sorted are only
+    /// written by `Model::cull`, which only writes the visible ones.
     pub fn update_transforms(&mut self, data: &GameData) {
-        if self.transforms.len() as u32 != self.transform_count {
-            self.transform_buffer =
-                data.graphics
-                    .lock()
-                    .device
-                    .create_buffer_init(&BufferInitDescriptor {
-                        label: None,
-                        contents: bytemuck::cast_slice(
-                            &self
-                                .transforms
-                                .iter()
-                                .map(Transform::matrix)
-                                .collect::<Vec<[[f32; 4]; 4]>>(),
-                        ),
-                        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                    });
+        self.remake_matrices();
+        if self.culls_instances() {
+            let mut changed = self.matrices.defer();
+            changed |= self.instance_data.defer();
+            if let Some(custom) = &mut self.custom_instance_data {
+                changed |= custom.defer();
+            }
+            if changed {
+                self.outdate_culling();
+            }
+            return;
+        }
+
+        let mut changed = self.matrices.update(data);
+        changed |= self.instance_data.update(data);
+        if let Some(custom) = &mut self.custom_instance_data {
+            changed |= custom.update(data);
+        }
+        if changed {
+            self.outdate_culling();
+        }
+        if self.culled.is_none() {
             self.transform_count = self.transforms.len() as u32;
-        } else {
-            data.graphics.lock().queue.write_buffer(
-                &self.transform_buffer,
-                0,
-                bytemuck::cast_slice(
-                    &self
-                        .transforms
-                        .iter()
-                        .map(Transform::matrix)
-                        .collect::<Vec<[[f32; 4]; 4]>>(),
-                ),
-            );
         }
+    }
+
+    /// Writes only the instances with bounds inside `frustum` to the buffers that are drawn,
+    /// so that instances which can't be seen are skipped. Culling is skipped if
+    /// `frustum_culling` is `false`. The instances of transparent models are also sorted from
+    /// back to front by their distance from `eye`, the position of the camera. It should be
+    /// called after `Model::update_transforms`.
+    ///
+    /// Every instance is tested again whenever the camera moves, but the buffers are only
+    /// rewritten when the visible instances or their order change. When they do, every
+    /// visible instance is written, not just the ones that changed.
+    pub fn cull(&mut self, data: &GameData, frustum: &Frustum, eye: Vec3) {
+        let transparent = self.blend_mode.is_transparent();
+        if !self.culls_instances() {
+            self.culled = None;
+            self.culled_view = None;
+            self.culled_count = 0;
+            self.transform_count = self.transforms.len() as u32;
+            return;
+        }
+        if self.culled_view == Some((*frustum, eye)) {
+            return;
+        }
+        self.culled_view = Some((*frustum, eye));
+
+        let mut visible = self
+            .matrices
+            .values
+            .iter()
+            .enumerate()
+            .map(|(index, matrix)| (index, Mat4::from_cols_array_2d(matrix)))
+            .filter(|(_, matrix)| {
+                !self.frustum_culling
+                    || frustum.contains_sphere(&self.bounding_sphere.transformed(*matrix))
+                        && frustum.contains_aabb(&self.aabb.transformed(*matrix))
+            })
+            .map(|(index, matrix)| {
+                let centre = matrix.transform_point3(self.bounding_sphere.centre);
+                (index, centre.distance(eye))
+            })
+            .collect::<Vec<(usize, f32)>>();
+
+        self.transform_count = visible.len() as u32;
+        self.culled_count = (self.transforms.len() - visible.len()) as u32;
+        if transparent {
+            visible.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            self.distance = visible.first().map_or(0.0, |(_, distance)| *distance);
+        }
+
+        let visible = visible
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+        if let Some(culled) = &self.culled {
+            if culled.indices == visible {
+                return;
+            }
+        }
+        let transforms = self.matrices.gather(&visible);
+        let instance_data = self.instance_data.gather(&visible);
+        let custom_instance_data = self
+            .custom_instance_data
+            .as_ref()
+            .map(|custom| custom.gather(&visible));
+        match &mut self.culled {
+            Some(culled) => {
+                culled.indices = visible;
+                culled.transforms.write(data, &transforms);
+                culled.instance_data.write(data, &instance_data);
+                match (&mut culled.custom_instance_data, custom_instance_data) {
+                    (Some(buffer), Some(contents)) => buffer.write(data, &contents),
+                    (buffer, contents) => {
+                        *buffer = contents.map(|contents| StreamingBuffer::new(data, &contents))
+                    }
+                }
+            }
+            None => {
+                self.culled = Some(CulledInstances {
+                    indices: visible,
+                    transforms: StreamingBuffer::new(data, &transforms),
+                    instance_data: StreamingBuffer::new(data, &instance_data),
+                    custom_instance_data: custom_instance_data
+                        .map(|contents| StreamingBuffer::new(data, &contents)),
+                })
+            }
+        }
+    }
+
+    /// Whether `Model::cull` writes the instances to draw, instead of every instance being
+    /// drawn from the buffers written by `Model::update_transforms`.
+    fn culls_instances(&self) -> bool {
+        self.frustum_culling || self.blend_mode.is_transparent()
+    }
+
+    /// Makes the next call to `Model::cull` test and write every instance again.
+    fn outdate_culling(&mut self) {
+        self.culled_view = None;
+        if let Some(culled) = &mut self.culled {
+            culled.indices.clear();
+        }
+    }
+
+    /// The transforms of every instance of the model. The order of these changes when
+    /// instances are removed.
+    pub fn transforms(&self) -> &[Transform] {
+        &self.transforms
+    }
+
+    /// The buffer with the transforms of the instances to draw, laid out as described by
+    /// `Transform::desc`.
+    pub fn transform_buffer(&self) -> &Buffer {
+        match &self.culled {
+            Some(culled) => &culled.transforms.buffer,
+            None => &self.matrices.buffer,
+        }
+    }
+
+    /// The buffer with the data of the instances to draw, laid out as described by
+    /// `InstanceData::desc`.
+    pub fn instance_data_buffer(&self) -> &Buffer {
+        match &self.culled {
+            Some(culled) => &culled.instance_data.buffer,
+            None => &self.instance_data.buffer,
+        }
+    }
+
+    /// Adds an instance of the model and returns a handle to it.
+    pub fn add_instance(&mut self, transform: Transform) -> InstanceId {
+        self.add_instance_with_data(transform, InstanceData::default())
+    }
+
+    /// Adds an instance of the model with a color and texture region and returns a handle
+    /// to it.
+    pub fn add_instance_with_data(
+        &mut self,
+        transform: Transform,
+        instance_data: InstanceData,
+    ) -> InstanceId {
+        self.matrices.push(transform.matrix());
+        self.transforms.push(transform);
+        self.instance_data.push(instance_data);
+        if let Some(custom) = &mut self.custom_instance_data {
+            custom.push_default();
+        }
+        self.instances.insert()
+    }
+
+    /// Removes an instance of the model and returns its transform, or `None` if it has
+    /// already been removed. The last instance is moved into the place of the removed
+    /// one so that the instance buffers stay compact.
+    pub fn remove_instance(&mut self, id: InstanceId) -> Option<Transform> {
+        self.remake_matrices();
+        let index = self.instances.swap_remove(id)?;
+        self.matrices.swap_remove(index);
+        self.instance_data.swap_remove(index);
+        if let Some(custom) = &mut self.custom_instance_data {
+            custom.swap_remove(index);
+        }
+        Some(self.transforms.swap_remove(index))
+    }
+
+    /// Gets the transform of an instance, or `None` if it has been removed.
+    pub fn instance(&self, id: InstanceId) -> Option<&Transform> {
+        self.transforms.get(self.instances.get(id)?)
+    }
+
+    /// Gets the transform of an instance and marks it as outdated, or returns `None` if it
+    /// has been removed.
+    pub fn instance_mut(&mut self, id: InstanceId) -> Option<&mut Transform> {
+        let index = self.instances.get(id)?;
+        self.outdated_matrices.push(index);
+        self.transforms.get_mut(index)
+    }
+
+    /// Sets the transform of an instance and marks it as outdated. Returns `false` if the
+    /// instance has been removed.
+    pub fn set_instance(&mut self, id: InstanceId, transform: Transform) -> bool {
+        self.instance_mut(id).map(|old| *old = transform).is_some()
+    }
+
+    /// Sets the matrix an instance is drawn with and marks it as outdated, which can place
+    /// it in ways a `Transform` can't, such as stretched along a rotated axis. Its transform
+    /// becomes the closest one to `matrix`. Returns `false` if the instance has been removed.
+    pub fn set_instance_matrix(&mut self, id: InstanceId, matrix: Mat4) -> bool {
+        let index = match self.instances.get(id) {
+            Some(index) => index,
+            None => return false,
+        };
+        self.remake_matrices();
+        self.transforms[index] = matrix.into();
+        *self.matrices.get_mut(index).unwrap() = matrix.to_cols_array_2d();
+        true
+    }
+
+    /// Makes the matrices of the transforms that were borrowed mutably again.
+    fn remake_matrices(&mut self) {
+        for index in self.outdated_matrices.drain(..) {
+            if let Some(transform) = self.transforms.get(index) {
+                *self.matrices.get_mut(index).unwrap() = transform.matrix();
+            }
+        }
+    }
+
+    /// Gets the color and texture region of an instance, or `None` if it has been removed.
+    pub fn instance_data(&self, id: InstanceId) -> Option<&InstanceData> {
+        self.instance_data.values.get(self.instances.get(id)?)
+    }
+
+    /// Gets the color and texture region of an instance and marks it as outdated, or returns
+    /// `None` if it has been removed.
+    pub fn instance_data_mut(&mut self, id: InstanceId) -> Option<&mut InstanceData> {
+        self.instance_data.get_mut(self.instances.get(id)?)
+    }
+
+    /// Sets the color and texture region of an instance and marks it as outdated. Returns
+    /// `false` if the instance has been removed.
+    pub fn set_instance_data(&mut self, id: InstanceId, instance_data: InstanceData) -> bool {
+        self.instance_data_mut(id)
+            .map(|old| *old = instance_data)
+            .is_some()
+    }
+
+    /// Calls the function `modify` on the data of every instance and marks it as outdated.
+    /// The data is in the same order as `Model::transforms`.
+    pub fn modify_instance_data<F: FnOnce(&mut [InstanceData])>(&mut self, modify: F) {
+        modify(&mut self.instance_data.values);
+        self.instance_data.mark_all();
+    }
+
+    /// Gives every instance a custom value of type `T`, starting with `T::default()`, which
+    /// replaces any custom values the model already had. The values are written to
+    /// `Model::custom_instance_buffer` so that custom pipelines can read them.
+    pub fn set_custom_instance_data<T: bytemuck::Pod + Default + Send + Sync>(
+        &mut self,
+        data: &GameData,
+    ) {
+        self.custom_instance_data = Some(Box::new(InstanceBuffer::new_pod(
+            data,
+            vec![T::default(); self.transforms.len()],
+        )));
+        self.outdate_culling();
+    }
+
+    /// Gives every instance a custom value of type `T`, then returns the model.
+    pub fn with_custom_instance_data<T: bytemuck::Pod + Default + Send + Sync>(
+        mut self,
+        data: &GameData,
+    ) -> Self {
+        self.set_custom_instance_data::<T>(data);
+        self
+    }
+
+    /// Gets the custom value of an instance, or `None` if it has been removed or the model
+    /// has no custom values of type `T`.
+    pub fn custom_instance_data<T: bytemuck::Pod + Default + Send + Sync>(
+        &self,
+        id: InstanceId,
+    ) -> Option<&T> {
+        let custom = self
+            .custom_instance_data
+            .as_ref()?
+            .downcast_ref::<InstanceBuffer<T>>()?;
+        custom.values.get(self.instances.get(id)?)
+    }
+
+    /// Gets the custom value of an instance and marks it as outdated, or returns `None` if
+    /// it has been removed or the model has no custom values of type `T`.
+    pub fn custom_instance_data_mut<T: bytemuck::Pod + Default + Send + Sync>(
+        &mut self,
+        id: InstanceId,
+    ) -> Option<&mut T> {
+        let index = self.instances.get(id)?;
+        self.custom_instance_data
+            .as_mut()?
+            .downcast_mut::<InstanceBuffer<T>>()?
+            .get_mut(index)
+    }
+
+    /// The buffer with the custom values of the instances to draw, if there are any.
+    pub fn custom_instance_buffer(&self) -> Option<&Buffer> {
+        match &self.culled {
+            Some(culled) => culled
+                .custom_instance_data
+                .as_ref()
+                .map(|custom| &custom.buffer),
+            None => self
+                .custom_instance_data
+                .as_ref()
+                .map(|custom| custom.buffer()),
+        }
+    }
 
-        self.transforms_outdated = false;
+    /// The handles of every instance of the model, in the same order as
+    /// `Model::transforms`.
+    pub fn instances(&self) -> impl Iterator<Item = InstanceId> + '_ {
+        self.instances.ids()
     }
 
-    /// Modified the value of the transform and marks it as outdated.
-    pub fn set_transform(&mut self, index: usize, transform: Transform) {
-        *self.transforms.get_mut(index).unwrap() = transform;
-        self.transforms_outdated = true;
+    /// Modified the value of the transform and marks it as outdated, returning the old
+    /// transform, or `None` if there is no instance at `index`. `index` is the current index
+    /// of the instance in `Model::transforms`, which can change when instances are removed.
+    pub fn set_transform(&mut self, index: usize, transform: Transform) -> Option<Transform> {
+        let old = self.transforms.get_mut(index)?;
+        *self.matrices.get_mut(index).unwrap() = transform.matrix();
+        Some(std::mem::replace(old, transform))
     }
 
     /// Modified the value of the transform, marks it as outdated, then returns the model.
+    /// Nothing is changed if there is no instance at `index`.
     pub fn with_transform(mut self, index: usize, transform: Transform) -> Self {
-        *self.transforms.get_mut(index).unwrap() = transform;
-        self.transforms_outdated = true;
+        self.set_transform(index, transform);
         self
     }
 
-    /// Modified the value of the transforms and marks it as outdated.
+    /// Replaces every instance with the transforms in `transforms`, resetting their
+    /// instance data. Handles to the old instances become invalid, and the new handles can
+    /// be found with `Model::instances`.
     pub fn set_transforms(&mut self, transforms: Vec<Transform>) {
+        let len = transforms.len();
+        self.instances.reset(len);
+        self.instance_data.reset(len);
+        if let Some(custom) = &mut self.custom_instance_data {
+            custom.reset(len);
+        }
+        self.matrices.values = transforms.iter().map(Transform::matrix).collect();
+        self.matrices.mark_all();
         self.transforms = transforms;
-        self.transforms_outdated = true;
+        self.outdated_matrices.clear();
     }
 
-    /// Modified the value of the transforms, marks it as outdated, then returns the model.
+    /// Replaces every instance, marks them as outdated, then returns the model.
     pub fn with_transforms(mut self, transforms: Vec<Transform>) -> Self {
-        self.transforms = transforms;
-        self.transforms_outdated = true;
+        self.set_transforms(transforms);
         self
     }
 
     /// Calls the function `modify` on the list of transforms and marks it as outdated.
     pub fn modify_transforms<F: FnOnce(&mut [Transform])>(&mut self, modify: F) {
         modify(&mut self.transforms);
-        self.transforms_outdated = true;
+        self.matrices.values = self.transforms.iter().map(Transform::matrix).collect();
+        self.matrices.mark_all();
+        self.outdated_matrices.clear();
     }
 
     /// Calls the function `modify` on the list of transforms, marks it as outdated, then returns
     /// the model.
     pub fn with_modify_transforms<F: FnOnce(&mut [Transform])>(mut self, modify: F) -> Self {
-        modify(&mut self.transforms);
-        self.transforms_outdated = true;
+        self.modify_transforms(modify);
+        self
+    }
+
+    /// How the model is combined with what is behind it.
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Changes how the model is combined with what is behind it.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+        self.outdate_culling();
+    }
+
+    /// Changes how the model is combined with what is behind it, then returns the model.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.set_blend_mode(blend_mode);
+        self
+    }
+
+    /// Changes the material the model is drawn with, then returns the model.
+    pub fn with_material(mut self, material: usize) -> Self {
+        self.material = Some(material);
         self
     }
 }
 
+#[test]
+fn index_format_test() {
+    assert_eq!(Model::index_format(0), IndexFormat::Uint16);
+    assert_eq!(Model::index_format(65_535), IndexFormat::Uint16);
+    assert_eq!(Model::index_format(65_536), IndexFormat::Uint32);
+}
+
 #[derive(Clone, Copy, Debug)]
 /// A set of modifications to a model's vertices.
 pub struct Transform {
@@ -874,8 +1810,23 @@ impl Transform {
 
     /// Construct matrices from transform values.
     pub fn matrix(&self) -> [[f32; 4]; 4] {
-        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
-            .to_cols_array_2d()
+        Mat4::from(*self).to_cols_array_2d()
+    }
+
+    /// Makes a buffer of transforms that are written as their matrices.
+    pub(crate) fn instance_buffer(
+        data: &GameData,
+        transforms: Vec<Transform>,
+    ) -> InstanceBuffer<Transform> {
+        InstanceBuffer::new(data, transforms, size_of::<[[f32; 4]; 4]>(), |transforms| {
+            bytemuck::cast_slice(
+                &transforms
+                    .iter()
+                    .map(Transform::matrix)
+                    .collect::<Vec<[[f32; 4]; 4]>>(),
+            )
+            .to_vec()
+        })
     }
 
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
@@ -919,6 +1870,16 @@ impl From<Mat4> for Transform {
     }
 }
 
+impl From<Transform> for Mat4 {
+    fn from(transform: Transform) -> Self {
+        Mat4::from_scale_rotation_translation(
+            transform.scale,
+            transform.rotation,
+            transform.translation,
+        )
+    }
+}
+
 impl From<Transform> for Vec<Transform> {
     fn from(transform: Transform) -> Self {
         vec![transform]
@@ -964,18 +1925,221 @@ impl ColorVertex {
     }
 }
 
+/// How an image is stored in a `Texture`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureSettings {
+    /// Whether the colors of images are sRGB, which is true for most images except HDR
+    /// ones, which are always linear. Images that hold data rather than colors, like normal
+    /// maps, must be linear instead.
+    pub srgb: bool,
+    /// Whether a full chain of mipmaps is generated.
+    pub mipmapped: bool,
+}
+
+impl TextureSettings {
+    /// Modifies whether images are sRGB, then returns itself.
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Modifies whether mipmaps are generated, then returns itself.
+    pub fn with_mipmapped(mut self, mipmapped: bool) -> Self {
+        self.mipmapped = mipmapped;
+        self
+    }
+
+    /// The format `image` is stored in. Images with more than 8 bits per channel, such as
+    /// 16 bit and HDR images, are stored as linear 16 bit floats, and every other image is
+    /// stored as 8 bit RGBA. sRGB images with more than 8 bits are decoded to linear color
+    /// when they are stored.
+    pub fn format(&self, image: &DynamicImage) -> TextureFormat {
+        let color = image.color();
+        if color.bytes_per_pixel() > color.channel_count() {
+            TextureFormat::Rgba16Float
+        } else if self.srgb {
+            TextureFormat::Rgba8UnormSrgb
+        } else {
+            TextureFormat::Rgba8Unorm
+        }
+    }
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            mipmapped: false,
+        }
+    }
+}
+
+#[test]
+fn texture_settings_test() {
+    let settings = TextureSettings::default();
+    let rgb = DynamicImage::new_rgb8(1, 1);
+    assert_eq!(settings.format(&rgb), TextureFormat::Rgba8UnormSrgb);
+    assert_eq!(
+        settings
+            .with_srgb(false)
+            .format(&DynamicImage::new_luma8(1, 1)),
+        TextureFormat::Rgba8Unorm
+    );
+    assert_eq!(
+        settings.format(&DynamicImage::new_rgb16(1, 1)),
+        TextureFormat::Rgba16Float
+    );
+    assert_eq!(
+        settings.format(&DynamicImage::new_rgba32f(1, 1)),
+        TextureFormat::Rgba16Float
+    );
+}
+
+/// A texture and sampler that can be drawn by a pipeline. Cloning a texture is cheap, and
+/// the clone draws the same texture.
+#[derive(Clone)]
 pub struct Texture {
-    pub diffuse: BindGroup,
+    pub diffuse: Arc<BindGroup>,
+    /// The size of the texture in pixels.
+    pub size: UVec2,
 }
 
 impl Texture {
-    /// Creates a texture from specified image information.
+    /// Creates a texture from specified image information, converting it to a format the
+    /// GPU can use with the default `TextureSettings`.
     pub fn new(data: &GameData, image: &DynamicImage, sampler: &Sampler) -> Texture {
+        Self::from_image(data, image, sampler, TextureSettings::default())
+    }
+
+    /// Creates a texture from specified image information with a full chain of mipmaps,
+    /// which are downsampled from the image on the CPU. Use a sampler with a mipmap filter,
+    /// like `SimpleRenderer::linear_mipmap_sampler`, so that minified textures don't
+    /// shimmer.
+    pub fn new_mipmapped(data: &GameData, image: &DynamicImage, sampler: &Sampler) -> Texture {
+        let settings = TextureSettings::default().with_mipmapped(true);
+        Self::from_image(data, image, sampler, settings)
+    }
+
+    /// Creates a texture from specified image information, stored in the format given by
+    /// `settings`.
+    pub fn from_image(
+        data: &GameData,
+        image: &DynamicImage,
+        sampler: &Sampler,
+        settings: TextureSettings,
+    ) -> Texture {
         let (width, height) = image.dimensions();
+        let size = UVec2::new(width, height);
+        let mip_level_count = match settings.mipmapped {
+            true => mip_level_count(size),
+            false => 1,
+        };
+
+        let format = settings.format(image);
+        let levels: Vec<Cow<[u8]>> = match format {
+            TextureFormat::Rgba16Float => {
+                let image = linear_rgba32f(image, settings.srgb);
+                let mipmaps = mipmaps(&image, mip_level_count);
+                iter::once(&image)
+                    .chain(&mipmaps)
+                    .map(|level| {
+                        // HDR values too bright for a 16 bit float are clamped instead of
+                        // becoming infinite.
+                        Cow::Owned(
+                            level
+                                .iter()
+                                .flat_map(|&value| {
+                                    let max = f16::MAX.to_f32();
+                                    f16::from_f32(value.clamp(-max, max)).to_le_bytes()
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect()
+            }
+            _ => {
+                // RGBA images are uploaded as they are, without being copied.
+                let image = match image {
+                    DynamicImage::ImageRgba8(image) => Cow::Borrowed(image),
+                    _ => Cow::Owned(image.to_rgba8()),
+                };
+                let mipmaps = match format {
+                    TextureFormat::Rgba8UnormSrgb => srgb_mipmaps(&image, mip_level_count),
+                    _ => mipmaps(&image, mip_level_count)
+                        .into_iter()
+                        .map(ImageBuffer::into_raw)
+                        .collect(),
+                };
+                let image = match image {
+                    Cow::Borrowed(image) => Cow::Borrowed(image.as_raw().as_slice()),
+                    Cow::Owned(image) => Cow::Owned(image.into_raw()),
+                };
+                iter::once(image)
+                    .chain(mipmaps.into_iter().map(Cow::Owned))
+                    .collect()
+            }
+        };
+
+        Self::from_levels(data, format, size, &levels, sampler)
+    }
+
+    /// Creates a texture from an image that is already compressed for the GPU. Fails if the
+    /// GPU doesn't support the format of the image, or if it has more mipmaps than its size
+    /// allows.
+    pub fn from_compressed(
+        data: &GameData,
+        image: &CompressedImage,
+        sampler: &Sampler,
+    ) -> Result<Texture> {
+        let info = image.format.describe();
+        if !data
+            .graphics
+            .lock()
+            .device
+            .features()
+            .contains(info.required_features)
+        {
+            bail!("{:?} textures aren't supported by this GPU", image.format);
+        }
+        let (block_width, block_height) = info.block_dimensions;
+        if !image.size.x.is_multiple_of(block_width as u32)
+            || !image.size.y.is_multiple_of(block_height as u32)
+        {
+            bail!(
+                "The size of {:?} textures must be a multiple of {block_width}x{block_height}",
+                image.format
+            );
+        }
+        if image.levels.is_empty() || image.levels.len() as u32 > mip_level_count(image.size) {
+            bail!(
+                "A {}x{} texture can't have {} mipmaps",
+                image.size.x,
+                image.size.y,
+                image.levels.len()
+            );
+        }
+
+        Ok(Self::from_levels(
+            data,
+            image.format,
+            image.size,
+            &image.levels,
+            sampler,
+        ))
+    }
 
+    /// Creates a texture in `format` from the data of each of its mipmaps, starting with
+    /// the full size one.
+    fn from_levels(
+        data: &GameData,
+        format: TextureFormat,
+        size: UVec2,
+        levels: &[impl AsRef<[u8]>],
+        sampler: &Sampler,
+    ) -> Texture {
         let size = wgpu::Extent3d {
-            width,
-            height,
+            width: size.x,
+            height: size.y,
             depth_or_array_layers: 1,
         };
 
@@ -986,29 +2150,56 @@ impl Texture {
                 .create_texture(&wgpu::TextureDescriptor {
                     label: None,
                     size,
-                    mip_level_count: 1,
+                    mip_level_count: levels.len() as u32,
                     sample_count: 1,
                     dimension: wgpu::TextureDimension::D2,
-                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                    format,
                     usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
                 });
 
-        data.graphics.lock().queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &diffuse_texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            image.as_rgba8().unwrap(),
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: NonZeroU32::new(4 * width),
-                rows_per_image: NonZeroU32::new(height),
-            },
-            size,
-        );
+        let info = format.describe();
+        let (block_width, block_height) = info.block_dimensions;
+        for (mip_level, level) in levels.iter().enumerate() {
+            // Compressed mipmaps smaller than a block still take up a whole block.
+            let level_size = size
+                .mip_level_size(mip_level as u32, false)
+                .physical_size(format);
+
+            data.graphics.lock().queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &diffuse_texture,
+                    mip_level: mip_level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                level.as_ref(),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(
+                        level_size.width / block_width as u32 * info.block_size as u32,
+                    ),
+                    rows_per_image: NonZeroU32::new(level_size.height / block_height as u32),
+                },
+                level_size,
+            );
+        }
+
+        Self::from_view(
+            data,
+            &diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            UVec2::new(size.width, size.height),
+            sampler,
+        )
+    }
 
+    /// Creates a texture from a view of a texture that is already on the GPU, such as one
+    /// that will be written to later.
+    pub fn from_view(
+        data: &GameData,
+        view: &TextureView,
+        size: UVec2,
+        sampler: &Sampler,
+    ) -> Texture {
         let bind_group_layout = Texture::bind_group_layout(data);
 
         let diffuse = data
@@ -1021,9 +2212,7 @@ impl Texture {
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
-                        resource: wgpu::BindingResource::TextureView(
-                            &diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                        ),
+                        resource: wgpu::BindingResource::TextureView(view),
                     },
                     wgpu::BindGroupEntry {
                         binding: 1,
@@ -1032,7 +2221,10 @@ impl Texture {
                 ],
             });
 
-        Texture { diffuse }
+        Texture {
+            diffuse: Arc::new(diffuse),
+            size,
+        }
     }
 
     /// Gets an image from `cache`, inserting it if it has not already been loaded.
@@ -1077,6 +2269,129 @@ impl Texture {
     }
 }
 
+/// The number of mipmaps in a full chain for a texture of `size`, halving the size each
+/// time until it is 1x1.
+pub fn mip_level_count(size: UVec2) -> u32 {
+    u32::BITS - size.max_element().max(1).leading_zeros()
+}
+
+/// The `count - 1` mipmaps of `image`, each half the size of the one before.
+fn mipmaps<P: Pixel + 'static>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    count: u32,
+) -> Vec<ImageBuffer<P, Vec<P::Subpixel>>> {
+    let (width, height) = image.dimensions();
+    let mut levels: Vec<ImageBuffer<P, Vec<P::Subpixel>>> = Vec::new();
+    for level in 1..count {
+        // Each mipmap is downsampled from the one before it, which is quicker than going
+        // from the full image and looks the same.
+        let (width, height) = ((width >> level).max(1), (height >> level).max(1));
+        let filter = image::imageops::FilterType::Triangle;
+        let next = match levels.last() {
+            Some(last) => image::imageops::resize(last, width, height, filter),
+            None => image::imageops::resize(image, width, height, filter),
+        };
+        levels.push(next);
+    }
+    levels
+}
+
+/// The `count - 1` mipmaps of an sRGB image as RGBA bytes. They are downsampled in linear
+/// color, since averaging sRGB values makes each mipmap darker than the last.
+fn srgb_mipmaps(image: &RgbaImage, count: u32) -> Vec<Vec<u8>> {
+    let linear = Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0.map(|value| value as f32 / 255.0);
+        Rgba([srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a])
+    });
+    mipmaps(&linear, count)
+        .into_iter()
+        .map(|level| {
+            level
+                .pixels()
+                .flat_map(|&Rgba([r, g, b, a])| {
+                    [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a]
+                        .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// `image` as linear 32 bit floats. Unless it is already stored as floats, which are always
+/// linear, it is decoded from sRGB if `srgb` is set.
+fn linear_rgba32f(image: &DynamicImage, srgb: bool) -> Rgba32FImage {
+    let mut linear = image.to_rgba32f();
+    let float = matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    );
+    if srgb && !float {
+        for Rgba([r, g, b, _]) in linear.pixels_mut() {
+            for value in [r, g, b] {
+                *value = srgb_to_linear(*value);
+            }
+        }
+    }
+    linear
+}
+
+#[test]
+fn linear_rgba32f_test() {
+    let pixel = Rgba([u16::MAX / 2, 0, u16::MAX, u16::MAX / 2]);
+    let image = DynamicImage::ImageRgba16(ImageBuffer::from_pixel(1, 1, pixel));
+
+    let Rgba([r, g, b, a]) = *linear_rgba32f(&image, true).get_pixel(0, 0);
+    assert!((r - 0.214).abs() < 0.001, "{r}");
+    assert_eq!([g, b], [0.0, 1.0]);
+    assert!((a - 0.5).abs() < 0.001, "{a}");
+    let Rgba([r, ..]) = *linear_rgba32f(&image, false).get_pixel(0, 0);
+    assert!((r - 0.5).abs() < 0.001, "{r}");
+
+    let hdr = DynamicImage::ImageRgba32F(Rgba32FImage::from_pixel(1, 1, Rgba([0.5; 4])));
+    assert_eq!(*linear_rgba32f(&hdr, true).get_pixel(0, 0), Rgba([0.5; 4]));
+}
+
+/// Decodes an sRGB color channel from 0 to 1 into linear color.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear color channel from 0 to 1 as sRGB.
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[test]
+fn srgb_mipmaps_test() {
+    // Black and white average to half as much light, which is much brighter than the sRGB
+    // value halfway between them.
+    let image = RgbaImage::from_fn(2, 2, |x, _| match x {
+        0 => Rgba([0, 0, 0, 255]),
+        _ => Rgba([255, 255, 255, 255]),
+    });
+    let mipmaps = srgb_mipmaps(&image, 2);
+    assert_eq!(mipmaps.len(), 1);
+    let [r, g, b, a] = [mipmaps[0][0], mipmaps[0][1], mipmaps[0][2], mipmaps[0][3]];
+    assert!((186..=190).contains(&r), "{r}");
+    assert_eq!([r, r, a], [g, b, 255]);
+}
+
+#[test]
+fn mip_level_count_test() {
+    assert_eq!(mip_level_count(UVec2::new(1, 1)), 1);
+    assert_eq!(mip_level_count(UVec2::new(256, 256)), 9);
+    assert_eq!(mip_level_count(UVec2::new(300, 20)), 9);
+    assert_eq!(mip_level_count(UVec2::new(0, 0)), 1);
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 /// A vertex used for textured models
@@ -1106,25 +2421,97 @@ impl TextureVertex {
     }
 }
 
+/// The renderer and its pass that draws the pass numbered `pass` of a list of renderers,
+/// or `None` if it is the last pass that every renderer draws into.
+fn offscreen_pass(renderers: &[Box<dyn Renderer>], mut pass: usize) -> Option<(usize, usize)> {
+    for (index, renderer) in renderers.iter().enumerate() {
+        let offscreen = renderer.pass_count().saturating_sub(1);
+        if pass < offscreen {
+            return Some((index, pass));
+        }
+        pass -= offscreen;
+    }
+    None
+}
+
+/// Set once a renderer in a `Vec` is found to be incompatible with the first one, so that it
+/// is only printed once rather than every frame.
+static INCOMPATIBLE_PRINTED: AtomicBool = AtomicBool::new(false);
+
+/// Every renderer draws into the last pass, which is made by the first renderer. Before
+/// that, the other passes of each renderer are drawn in order. Renderers that need their own
+/// attachments or clear colors can be drawn with a `RenderGraph` instead. Renderers without
+/// any passes are skipped.
+///
+/// Every renderer needs settings compatible with the first one. Only `SimpleRenderer` can
+/// change its settings, so multisampling or another format can't be used with other
+/// renderers. Renderers that aren't compatible are skipped in the last pass, and the first
+/// one found is printed.
 impl Renderer for Vec<Box<dyn Renderer>> {
     fn make_render_pass<'a>(
         &'a self,
         view: &'a TextureView,
         encoder: &'a mut wgpu::CommandEncoder,
-    ) -> wgpu::RenderPass {
-        self[0].make_render_pass(view, encoder)
+    ) -> wgpu::RenderPass<'a> {
+        self[0].make_pass(self[0].pass_count().saturating_sub(1), view, encoder)
     }
 
     fn render<'a, 'b: 'a>(&'b self, render_pass: &'a mut wgpu::RenderPass<'b>) {
+        let settings = self[0].pass_settings();
         for renderer in self {
-            renderer.render(render_pass);
+            if !settings.is_compatible(&renderer.pass_settings()) {
+                continue;
+            }
+            if let Some(last) = renderer.pass_count().checked_sub(1) {
+                renderer.render_pass(last, render_pass);
+            }
+        }
+    }
+
+    fn pass_count(&self) -> usize {
+        self.iter()
+            .map(|renderer| renderer.pass_count().saturating_sub(1))
+            .sum::<usize>()
+            + 1
+    }
+
+    fn make_pass<'a>(
+        &'a self,
+        pass: usize,
+        view: &'a TextureView,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        match offscreen_pass(self, pass) {
+            Some((index, pass)) => self[index].make_pass(pass, view, encoder),
+            None => self.make_render_pass(view, encoder),
+        }
+    }
+
+    fn render_pass<'a, 'b: 'a>(&'b self, pass: usize, render_pass: &'a mut wgpu::RenderPass<'b>) {
+        match offscreen_pass(self, pass) {
+            Some((index, pass)) => self[index].render_pass(pass, render_pass),
+            None => self.render(render_pass),
         }
     }
 
     fn update(&mut self, data: &GameData) {
-        for renderer in self {
+        for renderer in self.iter_mut() {
             renderer.update(data);
         }
+
+        let settings = self[0].pass_settings();
+        for (index, renderer) in self.iter().enumerate().skip(1) {
+            let other = renderer.pass_settings();
+            if !settings.is_compatible(&other)
+                && !INCOMPATIBLE_PRINTED.swap(true, Ordering::Relaxed)
+            {
+                eprintln!(
+                    "Renderer {index} draws with {other:?}, which can't be drawn in the pass of \
+                     renderer 0 with {settings:?}, so it is skipped. Renderers in a Vec need \
+                     the same sample count and formats."
+                );
+            }
+        }
     }
 
     fn resize(&mut self, data: &GameData) {
@@ -1133,3 +2520,34 @@ impl Renderer for Vec<Box<dyn Renderer>> {
         }
     }
 }
+
+#[test]
+fn offscreen_pass_test() {
+    use crate::graphics::EmptyRenderer;
+
+    struct ThreePasses;
+    impl Renderer for ThreePasses {
+        fn pass_count(&self) -> usize {
+            3
+        }
+    }
+    struct NoPasses;
+    impl Renderer for NoPasses {
+        fn pass_count(&self) -> usize {
+            0
+        }
+    }
+
+    // The first two passes of each `ThreePasses` are drawn before the shared last pass.
+    let renderers: Vec<Box<dyn Renderer>> = vec![
+        Box::new(EmptyRenderer),
+        Box::new(ThreePasses),
+        Box::new(NoPasses),
+        Box::new(ThreePasses),
+    ];
+    assert_eq!(renderers.pass_count(), 5);
+    assert_eq!(offscreen_pass(&renderers, 0), Some((1, 0)));
+    assert_eq!(offscreen_pass(&renderers, 1), Some((1, 1)));
+    assert_eq!(offscreen_pass(&renderers, 3), Some((3, 1)));
+    assert_eq!(offscreen_pass(&renderers, 4), None);
+}
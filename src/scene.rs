@@ -0,0 +1,338 @@
+//! A scene graph for placing model instances relative to each other.
+
+use glam::Mat4;
+
+use crate::{
+    instances::InstanceId,
+    renderers::{Model, Transform},
+};
+
+/// A handle to a node in a `Scene`. Handles to removed nodes are never reused, so using
+/// one after its node is removed does nothing instead of affecting another node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u32,
+}
+
+/// A reference to a single instance of a model, by the index of the model in a list of
+/// models such as `SimpleRenderer::models`, and the handle of the instance in that model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ModelInstance {
+    /// The index of the model.
+    pub model: usize,
+    /// The handle of the instance.
+    pub instance: InstanceId,
+}
+
+/// A single node in a `Scene`.
+pub struct Node {
+    transform: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    instance: Option<ModelInstance>,
+    world: Mat4,
+    outdated: bool,
+}
+
+impl Node {
+    /// The transform of the node relative to its parent.
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// The parent of the node, or `None` if it is at the root of the scene.
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    /// The children of the node.
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    /// The model instance that this node moves, if there is one.
+    pub fn instance(&self) -> Option<ModelInstance> {
+        self.instance
+    }
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// A hierarchy of nodes, each with a transform relative to its parent. Whenever
+/// `Scene::update` is called the transforms of the model instances attached to nodes are
+/// set to the world transform of those nodes.
+#[derive(Default)]
+pub struct Scene {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    roots: Vec<NodeId>,
+}
+
+impl Scene {
+    /// Create an empty `Scene`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node to the root of the scene.
+    pub fn add(&mut self, transform: Transform) -> NodeId {
+        let id = self.insert(Node {
+            transform,
+            parent: None,
+            children: Vec::new(),
+            instance: None,
+            world: Mat4::IDENTITY,
+            outdated: true,
+        });
+        self.roots.push(id);
+        id
+    }
+
+    /// Adds a node as a child of `parent`. Returns `None` if `parent` doesn't exist.
+    pub fn add_child(&mut self, parent: NodeId, transform: Transform) -> Option<NodeId> {
+        self.node(parent)?;
+        let id = self.add(transform);
+        self.set_parent(id, Some(parent));
+        Some(id)
+    }
+
+    /// Removes a node and all of its descendants. Returns `false` if the node doesn't
+    /// exist.
+    pub fn remove(&mut self, id: NodeId) -> bool {
+        if !self.detach(id) {
+            return false;
+        }
+
+        let mut to_remove = vec![id];
+        while let Some(id) = to_remove.pop() {
+            let slot = &mut self.slots[id.index];
+            if let Some(node) = slot.node.take() {
+                to_remove.extend(node.children);
+                slot.generation += 1;
+                self.free.push(id.index);
+            }
+        }
+        true
+    }
+
+    /// Gets a node, or `None` if it doesn't exist.
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.slots
+            .get(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_ref())
+    }
+
+    /// The nodes at the root of the scene.
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// Gets the transform of a node relative to its parent and marks it as outdated.
+    pub fn transform_mut(&mut self, id: NodeId) -> Option<&mut Transform> {
+        let node = self.node_mut(id)?;
+        node.outdated = true;
+        Some(&mut node.transform)
+    }
+
+    /// Sets the transform of a node relative to its parent. Returns `false` if the node
+    /// doesn't exist.
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) -> bool {
+        self.transform_mut(id).map(|old| *old = transform).is_some()
+    }
+
+    /// Attaches a model instance to a node so that it is moved with the node. Returns
+    /// `false` if the node doesn't exist.
+    pub fn set_instance(&mut self, id: NodeId, instance: Option<ModelInstance>) -> bool {
+        self.node_mut(id)
+            .map(|node| {
+                node.instance = instance;
+                node.outdated = true;
+            })
+            .is_some()
+    }
+
+    /// Moves a node to be a child of `parent`, or to the root of the scene if `parent` is
+    /// `None`. Returns `false` and does nothing if either node doesn't exist or if
+    /// `parent` is the node itself or one of its descendants.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if let Some(parent) = parent {
+            let mut ancestor = Some(parent);
+            while let Some(current) = ancestor {
+                if current == id {
+                    return false;
+                }
+                ancestor = match self.node(current) {
+                    Some(node) => node.parent,
+                    None => return false,
+                };
+            }
+        }
+        if !self.detach(id) {
+            return false;
+        }
+
+        match parent {
+            Some(parent) => self.node_mut(parent).unwrap().children.push(id),
+            None => self.roots.push(id),
+        }
+        let node = self.node_mut(id).unwrap();
+        node.parent = parent;
+        node.outdated = true;
+        true
+    }
+
+    /// Calculates the transform of a node relative to the world. Unlike the transforms
+    /// given to models this is always up to date, even before `Scene::update` is called.
+    pub fn world_matrix(&self, id: NodeId) -> Option<Mat4> {
+        let node = self.node(id)?;
+        let local = Mat4::from(node.transform);
+        Some(match node.parent {
+            Some(parent) => self.world_matrix(parent)? * local,
+            None => local,
+        })
+    }
+
+    /// Recalculates the world transforms of every node that has been modified, or has had
+    /// an ancestor modified, and writes their matrices to the attached model instances in
+    /// `models`.
+    /// Nodes attached to a model that isn't in `models` are skipped.
+    pub fn update(&mut self, models: &mut [Model]) {
+        self.propagate(|instance, world| {
+            if let Some(model) = models.get_mut(instance.model) {
+                model.set_instance_matrix(instance.instance, world);
+            }
+        });
+    }
+
+    fn propagate<F: FnMut(ModelInstance, Mat4)>(&mut self, mut write: F) {
+        let mut stack = self
+            .roots
+            .iter()
+            .map(|root| (*root, Mat4::IDENTITY, false))
+            .collect::<Vec<_>>();
+
+        while let Some((id, parent_world, parent_changed)) = stack.pop() {
+            let node = self.node_mut(id).unwrap();
+            let changed = parent_changed || node.outdated;
+            if changed {
+                node.world = parent_world * Mat4::from(node.transform);
+                node.outdated = false;
+                if let Some(instance) = node.instance {
+                    write(instance, node.world);
+                }
+            }
+
+            let world = node.world;
+            stack.extend(node.children.iter().map(|child| (*child, world, changed)));
+        }
+    }
+
+    fn insert(&mut self, node: Node) -> NodeId {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.node = Some(node);
+                NodeId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+                NodeId {
+                    index: self.slots.len() - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    /// Removes a node from the children of its parent or from the roots.
+    fn detach(&mut self, id: NodeId) -> bool {
+        let parent = match self.node(id) {
+            Some(node) => node.parent,
+            None => return false,
+        };
+        let siblings = match parent {
+            Some(parent) => &mut self.node_mut(parent).unwrap().children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|sibling| *sibling != id);
+        true
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.slots
+            .get_mut(id.index)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.node.as_mut())
+    }
+}
+
+#[test]
+fn scene_propagate_test() {
+    use glam::Vec3;
+
+    let mut scene = Scene::new();
+    let parent = scene.add(Transform::translation((1.0, 0.0, 0.0)));
+    let child = scene
+        .add_child(parent, Transform::translation((0.0, 2.0, 0.0)))
+        .unwrap();
+    let instance = ModelInstance {
+        model: 0,
+        instance: InstanceId {
+            slot: 3,
+            generation: 0,
+        },
+    };
+    scene.set_instance(child, Some(instance));
+
+    let mut written = Vec::new();
+    scene.propagate(|instance, world| written.push((instance, world)));
+    assert_eq!(
+        written,
+        [(instance, Mat4::from_translation(Vec3::new(1.0, 2.0, 0.0)))]
+    );
+
+    // Nothing is written if nothing changed.
+    written.clear();
+    scene.propagate(|instance, world| written.push((instance, world)));
+    assert!(written.is_empty());
+
+    // Moving the parent moves the child.
+    scene.transform_mut(parent).unwrap().translation.x = 5.0;
+    scene.propagate(|instance, world| written.push((instance, world)));
+    assert_eq!(
+        written,
+        [(instance, Mat4::from_translation(Vec3::new(5.0, 2.0, 0.0)))]
+    );
+}
+
+#[test]
+fn scene_hierarchy_test() {
+    let mut scene = Scene::new();
+    let a = scene.add(Transform::default());
+    let b = scene.add_child(a, Transform::default()).unwrap();
+    let c = scene.add_child(b, Transform::default()).unwrap();
+
+    assert!(!scene.set_parent(a, Some(c)));
+    assert!(scene.set_parent(c, None));
+    assert_eq!(scene.roots(), [a, c]);
+
+    assert!(scene.remove(a));
+    assert!(scene.node(b).is_none());
+    assert!(scene.node(c).is_some());
+
+    // The slot of a removed node is reused without the old handle becoming valid again.
+    let d = scene.add(Transform::default());
+    assert!(scene.node(a).is_none());
+    assert!(scene.node(d).is_some());
+}
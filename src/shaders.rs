@@ -0,0 +1,341 @@
+//! Preprocessing and compiling WGSL shaders without panicking, and watching shader files on
+//! disk so that they can be reloaded while the game runs.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context, Result};
+use wgpu::ShaderModule;
+
+use crate::GameData;
+
+/// A WGSL file on disk that is checked for changes.
+pub struct ShaderFile {
+    /// The path of the file.
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ShaderFile {
+    /// Watch the file at `path`. The first call to `ShaderFile::poll` reads it.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            modified: None,
+        }
+    }
+
+    /// Reads the file if it has been modified since it was last read. A file that can't be
+    /// read is skipped until it can, since editors often replace a file rather than writing
+    /// to it.
+    pub fn poll(&mut self) -> Option<String> {
+        let modified = fs::metadata(&self.path).ok()?.modified().ok()?;
+        if self.modified == Some(modified) {
+            return None;
+        }
+        let source = fs::read_to_string(&self.path).ok()?;
+        self.modified = Some(modified);
+        Some(source)
+    }
+}
+
+/// Runs `make`, returning the first validation error of any wgpu object it creates instead
+/// of panicking. Errors in shaders include the line they are on.
+pub fn validate<T, F: FnOnce() -> T>(data: &GameData, make: F) -> Result<T> {
+    data.graphics
+        .lock()
+        .device
+        .push_error_scope(wgpu::ErrorFilter::Validation);
+    let made = make();
+
+    let error = data.graphics.lock().device.pop_error_scope();
+    match pollster::block_on(error) {
+        Some(error) => bail!("{error}"),
+        None => Ok(made),
+    }
+}
+
+/// Shaders that other shaders can include by name, and every module compiled so far.
+///
+/// Shaders are preprocessed before they are compiled, which handles these directives, each on
+/// a line of its own:
+///
+/// - `#include "name"` inserts the shader added as `name`. Each shader is only inserted the
+///   first time it is included.
+/// - `#define NAME value` replaces every later use of `NAME` with `value`. A define without a
+///   value is only used by `#ifdef`.
+/// - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif` keep or remove the lines between
+///   them depending on whether `NAME` is defined.
+///
+/// Defines can also be given when compiling, to make variants of a shader. Each variant is
+/// kept by its name and defines, and is only compiled again when its source changes. The
+/// built in shaders `common.wgsl`, `simple.wgsl` and `post.wgsl` can be included by any
+/// shader.
+pub struct ShaderLibrary {
+    files: HashMap<String, String>,
+    /// The preprocessed source and compiled module of each variant.
+    modules: HashMap<ModuleKey, (String, Arc<ShaderModule>)>,
+}
+
+/// The name and defines a module was compiled with.
+type ModuleKey = (String, Vec<(String, String)>);
+
+impl ShaderLibrary {
+    /// Create a `ShaderLibrary` with only the built in shaders.
+    pub fn new() -> Self {
+        let mut library = Self {
+            files: HashMap::new(),
+            modules: HashMap::new(),
+        };
+        library.add_file("common.wgsl", include_str!("common.wgsl"));
+        library.add_file("simple.wgsl", include_str!("simple.wgsl"));
+        library.add_file("post.wgsl", include_str!("post.wgsl"));
+        library
+    }
+
+    /// Adds a shader that can be included as `name`, replacing any shader with that name.
+    pub fn add_file(&mut self, name: &str, source: &str) {
+        self.files.insert(name.to_string(), source.to_string());
+    }
+
+    /// The shader added as `name`.
+    pub fn file(&self, name: &str) -> Option<&str> {
+        self.files.get(name).map(String::as_str)
+    }
+
+    /// Resolves the directives in `source`, starting with the defines in `defines`.
+    pub fn preprocess(&self, source: &str, defines: &[(&str, &str)]) -> Result<String> {
+        let mut defines = defines
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let mut output = String::new();
+        self.preprocess_into(source, &mut defines, &mut HashSet::new(), &mut output)?;
+        Ok(output)
+    }
+
+    fn preprocess_into(
+        &self,
+        source: &str,
+        defines: &mut HashMap<String, String>,
+        included: &mut HashSet<String>,
+        output: &mut String,
+    ) -> Result<()> {
+        // Whether each `#ifdef` around the line keeps its lines, and whether it has had an
+        // `#else`.
+        let mut branches: Vec<(bool, bool)> = Vec::new();
+        for (number, line) in source.lines().enumerate() {
+            let number = number + 1;
+            let active = branches.iter().all(|(keep, _)| *keep);
+            let directive = match line.trim().strip_prefix('#') {
+                Some(directive) => directive,
+                None => {
+                    if active {
+                        output.push_str(&substitute(line, defines));
+                        output.push('\n');
+                    }
+                    continue;
+                }
+            };
+            let (name, argument) = match directive.split_once(char::is_whitespace) {
+                Some((name, argument)) => (name, argument.trim()),
+                None => (directive, ""),
+            };
+
+            match name {
+                "ifdef" | "ifndef" => {
+                    branches.push((defines.contains_key(argument) == (name == "ifdef"), false))
+                }
+                "else" => match branches.last_mut() {
+                    Some((keep, had_else @ false)) => {
+                        *keep = !*keep;
+                        *had_else = true;
+                    }
+                    _ => bail!("Line {number}: #else without an #ifdef"),
+                },
+                "endif" => {
+                    if branches.pop().is_none() {
+                        bail!("Line {number}: #endif without an #ifdef");
+                    }
+                }
+                _ if !active => {}
+                "include" => {
+                    let file = argument
+                        .strip_prefix('"')
+                        .and_then(|file| file.strip_suffix('"'))
+                        .with_context(|| format!("Line {number}: #include needs a quoted name"))?;
+                    if included.insert(file.to_string()) {
+                        let source = self.files.get(file).with_context(|| {
+                            format!("Line {number}: there is no shader named {file} to include")
+                        })?;
+                        self.preprocess_into(source, defines, included, output)
+                            .with_context(|| format!("In {file}"))?;
+                    }
+                }
+                "define" => {
+                    let (define, value) = match argument.split_once(char::is_whitespace) {
+                        Some((define, value)) => (define, value.trim()),
+                        None => (argument, ""),
+                    };
+                    if define.is_empty() {
+                        bail!("Line {number}: #define needs a name");
+                    }
+                    defines.insert(define.to_string(), value.to_string());
+                }
+                _ => bail!("Line {number}: unknown directive #{name}"),
+            }
+        }
+
+        if !branches.is_empty() {
+            bail!("#ifdef without an #endif");
+        }
+        Ok(())
+    }
+
+    /// Preprocesses `source` with `defines` and compiles it as `name`, or returns the module
+    /// it was compiled to before. A module with the same name and defines but a different
+    /// source, such as a reloaded shader, replaces the old one. Errors in the shader are
+    /// returned instead of panicking, and leave the old module in place.
+    pub fn module(
+        &mut self,
+        data: &GameData,
+        name: &str,
+        source: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<Arc<ShaderModule>> {
+        let source = self.preprocess(source, defines)?;
+        let key = (
+            name.to_string(),
+            defines
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        );
+        if let Some((old_source, module)) = self.modules.get(&key) {
+            if *old_source == source {
+                return Ok(module.clone());
+            }
+        }
+
+        let module = Arc::new(validate(data, || {
+            data.graphics
+                .lock()
+                .device
+                .create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some(name),
+                    source: wgpu::ShaderSource::Wgsl(source.as_str().into()),
+                })
+        })?);
+        self.modules.insert(key, (source, module.clone()));
+        Ok(module)
+    }
+
+    /// Compiles the shader added as `name` like `ShaderLibrary::module`.
+    pub fn file_module(
+        &mut self,
+        data: &GameData,
+        name: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<Arc<ShaderModule>> {
+        let source = self
+            .files
+            .get(name)
+            .with_context(|| format!("There is no shader named {name}"))?
+            .clone();
+        self.module(data, name, &source, defines)
+    }
+}
+
+impl Default for ShaderLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Replaces every identifier in `line` that is defined with a value.
+fn substitute(line: &str, defines: &HashMap<String, String>) -> String {
+    let is_start = |c: char| c.is_alphabetic() || c == '_';
+    let is_part = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find(is_start) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c: char| !is_part(c)).unwrap_or(rest.len());
+        let word = &rest[..end];
+        match defines.get(word) {
+            Some(value) if !value.is_empty() => output.push_str(value),
+            _ => output.push_str(word),
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[test]
+fn shader_file_test() {
+    let path = std::env::temp_dir().join(format!("rhachis_shader_{}.wgsl", std::process::id()));
+    let mut file = ShaderFile::new(&path);
+    assert_eq!(file.poll(), None);
+
+    fs::write(&path, "// first").unwrap();
+    assert_eq!(file.poll().as_deref(), Some("// first"));
+    assert_eq!(file.poll(), None);
+
+    // Only a change to the modification time makes the file be read again.
+    fs::write(&path, "// second").unwrap();
+    let later = SystemTime::now() + std::time::Duration::from_secs(10);
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    assert_eq!(file.poll().as_deref(), Some("// second"));
+    assert_eq!(file.poll(), None);
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(file.poll(), None);
+}
+
+#[test]
+fn preprocess_test() {
+    let mut library = ShaderLibrary::new();
+    library.add_file("a.wgsl", "#include \"b.wgsl\"\nlet a = B;");
+    library.add_file("b.wgsl", "#define B 2.0\nlet b = 1.0;");
+
+    // Shaders are only included once, and defines carry on after the include.
+    let source = "#include \"a.wgsl\"\n#include \"b.wgsl\"\nlet c = B + B_2;";
+    assert_eq!(
+        library.preprocess(source, &[]).unwrap(),
+        "let b = 1.0;\nlet a = 2.0;\nlet c = 2.0 + B_2;\n",
+    );
+
+    let source = "#ifdef FAST\nfast\n#ifndef EXTRA\nno extra\n#endif\n#else\nslow\n#endif\nSIZE";
+    assert_eq!(
+        library.preprocess(source, &[("SIZE", "4")]).unwrap(),
+        "slow\n4\n"
+    );
+    assert_eq!(
+        library
+            .preprocess(source, &[("FAST", ""), ("EXTRA", "")])
+            .unwrap(),
+        "fast\nSIZE\n"
+    );
+
+    assert!(library.preprocess("#ifdef A", &[]).is_err());
+    assert!(library.preprocess("#endif", &[]).is_err());
+    assert!(library.preprocess("#include \"c.wgsl\"", &[]).is_err());
+    assert!(library.preprocess("#pragma once", &[]).is_err());
+    // Directives in removed lines aren't checked, except for the ones that end the branch.
+    assert!(library
+        .preprocess("#ifdef A\n#include \"c.wgsl\"\n#endif", &[])
+        .is_ok());
+}
@@ -1,9 +1,7 @@
-struct Transform {
-    @location(2) data0: vec4<f32>,
-    @location(3) data1: vec4<f32>,
-    @location(4) data2: vec4<f32>,
-    @location(5) data3: vec4<f32>,
-}
+// The default shader for models. The cutout fragments discard any pixel that is less than
+// half opaque.
+
+#include "common.wgsl"
 
 struct ColorInput {
     @location(0) pos: vec3<f32>,
@@ -23,45 +21,40 @@ struct TextureInput {
 struct TextureOutput {
     @builtin(position) pos: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
 }
 
-@group(0)@binding(0)
-var<uniform> projection: mat4x4<f32>;
-@group(0)@binding(1)
-var<uniform> camera: mat4x4<f32>;
-
 @vertex
-fn color_vertex(input: ColorInput, transform: Transform) -> ColorOutput {
-    let transform_matrix = mat4x4<f32>(
-        transform.data0,
-        transform.data1,
-        transform.data2,
-        transform.data3,
-    );
-
+fn color_vertex(input: ColorInput, transform: Transform, instance: InstanceData) -> ColorOutput {
     var output: ColorOutput;
-    output.pos = projection * camera * transform_matrix * vec4<f32>(input.pos, 1.0);
-    output.color = input.color;
+    output.pos = projection * camera * transform_matrix(transform) * vec4<f32>(input.pos, 1.0);
+    output.color = input.color * instance.color;
     return output;
 }
 
+fn cutout(color: vec4<f32>) -> vec4<f32> {
+    if (color.a < 0.5) {
+        discard;
+    }
+    return color;
+}
+
 @fragment
 fn color_fragment(output: ColorOutput) -> @location(0) vec4<f32> {
     return output.color;
 }
 
-@vertex
-fn texture_vertex(input: TextureInput, transform: Transform) -> TextureOutput {
-    let transform_matrix = mat4x4<f32>(
-        transform.data0,
-        transform.data1,
-        transform.data2,
-        transform.data3,
-    );
+@fragment
+fn color_cutout_fragment(output: ColorOutput) -> @location(0) vec4<f32> {
+    return cutout(output.color);
+}
 
+@vertex
+fn texture_vertex(input: TextureInput, transform: Transform, instance: InstanceData) -> TextureOutput {
     var output: TextureOutput;
-    output.pos = projection * camera * transform_matrix * vec4<f32>(input.pos, 1.0);
-    output.tex_coords = input.tex_coords;
+    output.pos = projection * camera * transform_matrix(transform) * vec4<f32>(input.pos, 1.0);
+    output.tex_coords = instance.uv_rect.xy + input.tex_coords * instance.uv_rect.zw;
+    output.color = instance.color;
     return output;
 }
 
@@ -70,7 +63,16 @@ var texture: texture_2d<f32>;
 @group(1)@binding(1)
 var texture_sampler: sampler;
 
+fn texture_color(output: TextureOutput) -> vec4<f32> {
+    return textureSample(texture, texture_sampler, output.tex_coords) * output.color;
+}
+
 @fragment
 fn texture_fragment(output: TextureOutput) -> @location(0) vec4<f32> {
-    return textureSample(texture, texture_sampler, output.tex_coords);
+    return texture_color(output);
+}
+
+@fragment
+fn texture_cutout_fragment(output: TextureOutput) -> @location(0) vec4<f32> {
+    return cutout(texture_color(output));
 }
@@ -0,0 +1,476 @@
+//! Cubemap textures and a renderer that draws one around the camera as the sky.
+
+use std::{num::NonZeroU32, path::Path, sync::Arc};
+
+use anyhow::{bail, Result};
+use glam::{Mat3, Mat4, UVec2};
+use image::{imageops, DynamicImage, RgbaImage};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, Buffer, RenderPipeline, Sampler, TextureView,
+};
+
+use crate::{
+    graphics::Renderer,
+    renderers::{SimpleProjection, SimpleRenderer},
+    GameData,
+};
+
+/// How the six faces of a cubemap are arranged in a single image. The faces are named by
+/// where they are from the default camera, which faces towards negative z.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CubemapLayout {
+    /// Four faces in a row, left, front, right and back, with the top face above the front
+    /// face and the bottom face below it.
+    HorizontalCross,
+    /// Three faces in a row, left, front and right, with the top face above the front face
+    /// and the bottom and back faces below it. The back face is upside down.
+    VerticalCross,
+    /// The faces side by side, in the same order as `Cubemap::new`.
+    HorizontalStrip,
+    /// The faces from top to bottom, in the same order as `Cubemap::new`.
+    VerticalStrip,
+}
+
+impl CubemapLayout {
+    /// Works out the layout of an image from its size, if it is the size of any layout.
+    pub fn detect(size: UVec2) -> Option<Self> {
+        [
+            Self::HorizontalCross,
+            Self::VerticalCross,
+            Self::HorizontalStrip,
+            Self::VerticalStrip,
+        ]
+        .into_iter()
+        .find(|layout| layout.face_size(size).is_some())
+    }
+
+    /// The size of each face in an image of this layout that is `size` pixels, if the image
+    /// can be split into square faces.
+    fn face_size(self, size: UVec2) -> Option<u32> {
+        let grid = self.grid();
+        let face_size = size.x / grid.x;
+        (face_size > 0 && size == grid * face_size).then_some(face_size)
+    }
+
+    /// The number of faces that fit across and down the layout.
+    fn grid(self) -> UVec2 {
+        match self {
+            Self::HorizontalCross => UVec2::new(4, 3),
+            Self::VerticalCross => UVec2::new(3, 4),
+            Self::HorizontalStrip => UVec2::new(6, 1),
+            Self::VerticalStrip => UVec2::new(1, 6),
+        }
+    }
+
+    /// Where each face is in the grid of the layout, in the same order as `Cubemap::new`.
+    fn positions(self) -> [UVec2; 6] {
+        let cross = |back| {
+            [
+                UVec2::new(2, 1),
+                UVec2::new(0, 1),
+                UVec2::new(1, 0),
+                UVec2::new(1, 2),
+                UVec2::new(1, 1),
+                back,
+            ]
+        };
+        match self {
+            Self::HorizontalCross => cross(UVec2::new(3, 1)),
+            Self::VerticalCross => cross(UVec2::new(1, 3)),
+            Self::HorizontalStrip => [0, 1, 2, 3, 4, 5].map(|x| UVec2::new(x, 0)),
+            Self::VerticalStrip => [0, 1, 2, 3, 4, 5].map(|y| UVec2::new(0, y)),
+        }
+    }
+
+    /// Cuts the six faces out of `image`, in the same order as `Cubemap::new`.
+    pub fn split(self, image: &DynamicImage) -> Result<[RgbaImage; 6]> {
+        let (width, height) = (image.width(), image.height());
+        let size = match self.face_size(UVec2::new(width, height)) {
+            Some(size) => size,
+            None => {
+                bail!("A {width}x{height} image can't be split into a {self:?} of square faces")
+            }
+        };
+        let image = image.to_rgba8();
+        let mut faces = self.positions().map(|position| {
+            let corner = position * size;
+            imageops::crop_imm(&image, corner.x, corner.y, size, size).to_image()
+        });
+        if self == Self::VerticalCross {
+            faces[5] = imageops::rotate180(&faces[5]);
+        }
+        Ok(faces)
+    }
+}
+
+/// A texture made of six square faces that can be sampled in any direction.
+pub struct Cubemap {
+    pub diffuse: Arc<BindGroup>,
+    /// The width and height of each face in pixels.
+    pub size: u32,
+}
+
+impl Cubemap {
+    /// Creates a cubemap from six square images of the same size. In order, they are the
+    /// right, left, top, bottom, front and back faces, where the front face is the one the
+    /// default camera faces.
+    pub fn new(data: &GameData, faces: [&DynamicImage; 6], sampler: &Sampler) -> Result<Self> {
+        let size = faces[0].width();
+        if faces
+            .iter()
+            .any(|face| face.width() != size || face.height() != size)
+        {
+            bail!("Every face of a cubemap must be square and the same size");
+        }
+        if size == 0 {
+            bail!("The faces of a cubemap can't be empty");
+        }
+
+        let pixels = faces
+            .iter()
+            .flat_map(|face| face.to_rgba8().into_raw())
+            .collect::<Vec<u8>>();
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        };
+
+        let graphics = data.graphics.lock();
+        let texture = graphics.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        graphics.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(4 * size),
+                rows_per_image: NonZeroU32::new(size),
+            },
+            extent,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        drop(graphics);
+
+        let diffuse = data
+            .graphics
+            .lock()
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &Self::bind_group_layout(data),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                ],
+            });
+
+        Ok(Self {
+            diffuse: Arc::new(diffuse),
+            size,
+        })
+    }
+
+    /// Creates a cubemap from a single image of its faces, working out the layout from the
+    /// size of the image.
+    pub fn from_image(data: &GameData, image: &DynamicImage, sampler: &Sampler) -> Result<Self> {
+        let layout = match CubemapLayout::detect(UVec2::new(image.width(), image.height())) {
+            Some(layout) => layout,
+            None => bail!(
+                "A {}x{} image isn't the size of a cubemap layout",
+                image.width(),
+                image.height()
+            ),
+        };
+        let faces = layout.split(image)?.map(DynamicImage::ImageRgba8);
+        Self::new(data, faces.each_ref(), sampler)
+    }
+
+    /// Loads a cubemap from an image file of its faces.
+    pub fn open<P: AsRef<Path>>(data: &GameData, path: P, sampler: &Sampler) -> Result<Self> {
+        Self::from_image(data, &image::open(path)?, sampler)
+    }
+
+    pub fn bind_group_layout(data: &GameData) -> wgpu::BindGroupLayout {
+        let graphics = data.graphics.lock();
+        graphics
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            })
+    }
+}
+
+/// A renderer that draws a cubemap infinitely far away in every direction. Only the
+/// rotation of the camera is used, so the sky never gets closer.
+///
+/// When it is drawn in the same pass as a `SimpleRenderer`, it only covers the parts of the
+/// screen that nothing else has been drawn to.
+pub struct Skybox {
+    pipeline: RenderPipeline,
+    camera_buffer: Buffer,
+    projection_buffer: Buffer,
+    projection_bind_group: BindGroup,
+    /// The cubemap drawn as the sky.
+    pub cubemap: Cubemap,
+    /// A view of the depth texture.
+    pub depth_texture_view: TextureView,
+}
+
+impl Skybox {
+    /// Create a `Skybox` that draws `cubemap`.
+    pub fn new(data: &GameData, cubemap: Cubemap, projection: SimpleProjection) -> Self {
+        let make_buffer = |contents: Mat4| {
+            data.graphics
+                .lock()
+                .device
+                .create_buffer_init(&BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(&[contents.to_cols_array_2d()]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                })
+        };
+        let camera_buffer = make_buffer(Mat4::IDENTITY);
+        let projection_buffer = make_buffer(Mat4::from(projection).inverse());
+
+        let projection_bind_group =
+            data.graphics
+                .lock()
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: projection_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: camera_buffer.as_entire_binding(),
+                        },
+                    ],
+                    layout: &SimpleRenderer::mat4_bind_group_layout(data),
+                });
+
+        Self {
+            pipeline: Self::pipeline(data),
+            camera_buffer,
+            projection_buffer,
+            projection_bind_group,
+            cubemap,
+            depth_texture_view: SimpleRenderer::depth_texture(data),
+        }
+    }
+
+    /// Replaces the camera of the skybox. Only the rotation of `camera` is used.
+    pub fn set_camera(&mut self, data: &GameData, camera: Mat4) {
+        let rotation = Mat4::from_mat3(Mat3::from_mat4(camera));
+        data.graphics.lock().queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[rotation.to_cols_array_2d()]),
+        )
+    }
+
+    /// Replaces the projection of the skybox.
+    pub fn set_projection(&mut self, data: &GameData, projection: SimpleProjection) {
+        let inverse = Mat4::from(projection).inverse();
+        data.graphics.lock().queue.write_buffer(
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(&[inverse.to_cols_array_2d()]),
+        )
+    }
+
+    /// Makes the skybox pipeline. It tests against the depth buffer without writing to it,
+    /// so it can share a pass with a `SimpleRenderer`.
+    pub fn pipeline(data: &GameData) -> RenderPipeline {
+        let shader = data
+            .shaders
+            .lock()
+            .module(data, "skybox.wgsl", include_str!("skybox.wgsl"), &[])
+            .unwrap();
+
+        let mat4_bind_group_layout = SimpleRenderer::mat4_bind_group_layout(data);
+        let cubemap_bind_group_layout = Cubemap::bind_group_layout(data);
+
+        let pipeline_layout =
+            data.graphics
+                .lock()
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&mat4_bind_group_layout, &cubemap_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let fragment_format = data.graphics.lock().config.format;
+
+        data.graphics
+            .lock()
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Skybox Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "skybox_vertex",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "skybox_fragment",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: fragment_format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+    }
+}
+
+impl Renderer for Skybox {
+    fn render<'a, 'b: 'a>(&'b self, render_pass: &'a mut wgpu::RenderPass<'b>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.projection_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.cubemap.diffuse, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn make_render_pass<'a>(
+        &'a self,
+        view: &'a TextureView,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        })
+    }
+
+    fn resize(&mut self, data: &GameData) {
+        self.depth_texture_view = SimpleRenderer::depth_texture(data);
+    }
+}
+
+#[test]
+fn cubemap_layout_test() {
+    assert_eq!(
+        CubemapLayout::detect(UVec2::new(3, 4)),
+        Some(CubemapLayout::VerticalCross)
+    );
+    assert_eq!(
+        CubemapLayout::detect(UVec2::new(256, 192)),
+        Some(CubemapLayout::HorizontalCross)
+    );
+    assert_eq!(
+        CubemapLayout::detect(UVec2::new(32, 192)),
+        Some(CubemapLayout::VerticalStrip)
+    );
+    assert_eq!(CubemapLayout::detect(UVec2::new(64, 64)), None);
+
+    // Each face of a vertical cross is filled with its index, and the back face has a
+    // marker in its top left corner, which ends up in its bottom right corner.
+    let mut image = RgbaImage::new(6, 8);
+    for (index, position) in CubemapLayout::VerticalCross.positions().iter().enumerate() {
+        for y in 0..2 {
+            for x in 0..2 {
+                image.put_pixel(
+                    position.x * 2 + x,
+                    position.y * 2 + y,
+                    [index as u8; 4].into(),
+                );
+            }
+        }
+    }
+    image.put_pixel(2, 6, [255; 4].into());
+    let faces = CubemapLayout::VerticalCross
+        .split(&DynamicImage::ImageRgba8(image))
+        .unwrap();
+    for (index, face) in faces.iter().enumerate() {
+        assert_eq!(face.dimensions(), (2, 2));
+        assert_eq!(face.get_pixel(0, 0).0, [index as u8; 4]);
+    }
+    assert_eq!(faces[5].get_pixel(1, 1).0, [255; 4]);
+    assert!(CubemapLayout::HorizontalCross
+        .split(&DynamicImage::new_rgba8(6, 8))
+        .is_err());
+}
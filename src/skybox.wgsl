@@ -0,0 +1,38 @@
+#include "common.wgsl"
+
+// The skybox binds the inverse of the projection as `projection`, so that positions on the
+// screen can be turned back into directions, and the camera without its translation.
+
+struct SkyboxOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) direction: vec3<f32>,
+}
+
+@vertex
+fn skybox_vertex(@builtin(vertex_index) index: u32) -> SkyboxOutput {
+    // A single triangle that covers the whole screen.
+    let pos = vec2<f32>(f32(index / 2u) * 4.0 - 1.0, f32(index % 2u) * 4.0 - 1.0);
+
+    let near = projection * vec4<f32>(pos, 0.0, 1.0);
+    let far = projection * vec4<f32>(pos, 0.5, 1.0);
+    let view_direction = far.xyz / far.w - near.xyz / near.w;
+
+    var output: SkyboxOutput;
+    // The skybox is as far away as possible, so it is behind everything else.
+    output.pos = vec4<f32>(pos, 1.0, 1.0);
+    // The camera only rotates, so its transpose is its inverse.
+    output.direction = (transpose(camera) * vec4<f32>(view_direction, 0.0)).xyz;
+    return output;
+}
+
+@group(1)@binding(0)
+var cubemap: texture_cube<f32>;
+@group(1)@binding(1)
+var cubemap_sampler: sampler;
+
+@fragment
+fn skybox_fragment(output: SkyboxOutput) -> @location(0) vec4<f32> {
+    // Cubemaps are left handed, so the front face is flipped to be in front of the camera.
+    let direction = vec3<f32>(output.direction.xy, -output.direction.z);
+    return textureSample(cubemap, cubemap_sampler, direction);
+}
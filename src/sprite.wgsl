@@ -0,0 +1,50 @@
+#include "common.wgsl"
+
+struct SpriteInstance {
+    @location(0) position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) origin: vec2<f32>,
+    @location(3) rotation: f32,
+    @location(4) uv_rect: vec4<f32>,
+    @location(5) color: vec4<f32>,
+}
+
+struct SpriteOutput {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn sprite_vertex(@builtin(vertex_index) index: u32, sprite: SpriteInstance) -> SpriteOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[index];
+
+    let local = (corner - sprite.origin) * sprite.size;
+    let c = cos(sprite.rotation);
+    let s = sin(sprite.rotation);
+    let rotated = vec2<f32>(local.x * c - local.y * s, local.x * s + local.y * c);
+
+    var output: SpriteOutput;
+    output.pos = projection * camera * vec4<f32>(sprite.position + rotated, 0.0, 1.0);
+    output.tex_coords = sprite.uv_rect.xy + corner * sprite.uv_rect.zw;
+    output.color = sprite.color;
+    return output;
+}
+
+@group(1)@binding(0)
+var texture: texture_2d<f32>;
+@group(1)@binding(1)
+var texture_sampler: sampler;
+
+@fragment
+fn sprite_fragment(output: SpriteOutput) -> @location(0) vec4<f32> {
+    return textureSample(texture, texture_sampler, output.tex_coords) * output.color;
+}
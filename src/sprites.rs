@@ -0,0 +1,484 @@
+//! A renderer for drawing large numbers of 2D sprites with very few draw calls.
+
+use std::{mem::size_of, ops::Range};
+
+use glam::{Mat4, Vec2, Vec3};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, Buffer, RenderPipeline, Sampler, TextureView,
+};
+
+use crate::{
+    graphics::Renderer,
+    instances::StreamingBuffer,
+    math::Rect,
+    renderers::{SimpleRenderer, Texture},
+    GameData,
+};
+
+/// A single textured rectangle drawn by a `SpriteRenderer`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    /// The index of the texture in `SpriteRenderer::textures`.
+    pub texture: usize,
+    /// The position of the origin of the sprite in pixels.
+    pub position: Vec2,
+    /// The rotation of the sprite around its origin in radians. Positive values rotate
+    /// clockwise on the screen.
+    pub rotation: f32,
+    /// The amount the size of the source rectangle is multiplied by.
+    pub scale: Vec2,
+    /// The point that the sprite is placed and rotated around, where `(0.0, 0.0)` is the top
+    /// left corner and `(1.0, 1.0)` is the bottom right corner.
+    pub origin: Vec2,
+    /// The color the texture is multiplied by.
+    pub color: [f32; 4],
+    /// The part of the texture to draw in pixels, or `None` to draw all of it.
+    pub source: Option<Rect>,
+    /// Sprites with a higher layer are drawn in front of sprites with a lower layer. Sprites
+    /// on the same layer and with the same texture are drawn in the order they are in
+    /// `SpriteRenderer::sprites`.
+    pub layer: i32,
+    /// If the texture is mirrored horizontally.
+    pub flip_x: bool,
+    /// If the texture is mirrored vertically.
+    pub flip_y: bool,
+}
+
+impl Sprite {
+    /// Create a `Sprite` that draws all of a texture at its normal size, with its top left
+    /// corner at `position`.
+    pub fn new(texture: usize, position: Vec2) -> Self {
+        Self {
+            texture,
+            position,
+            rotation: 0.0,
+            scale: Vec2::ONE,
+            origin: Vec2::ZERO,
+            color: [1.0, 1.0, 1.0, 1.0],
+            source: None,
+            layer: 0,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+
+    /// Modifies the rotation of the sprite, then returns it.
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Modifies the scale of the sprite, then returns it.
+    pub fn with_scale(mut self, scale: Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Modifies the origin of the sprite, then returns it.
+    pub fn with_origin(mut self, origin: Vec2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Modifies the color of the sprite, then returns it.
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Modifies the source rectangle of the sprite, then returns it.
+    pub fn with_source(mut self, source: Rect) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Modifies the layer of the sprite, then returns it.
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Modifies whether the sprite is mirrored, then returns it.
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Converts the sprite to the values read by the shader, for a texture of
+    /// `texture_size` pixels.
+    pub(crate) fn instance(&self, texture_size: Vec2) -> SpriteInstance {
+        let source = self.source.unwrap_or(Rect {
+            position: Vec2::ZERO,
+            size: texture_size,
+        });
+        let mut uv_position = source.position / texture_size;
+        let mut uv_size = source.size / texture_size;
+        if self.flip_x {
+            uv_position.x += uv_size.x;
+            uv_size.x = -uv_size.x;
+        }
+        if self.flip_y {
+            uv_position.y += uv_size.y;
+            uv_size.y = -uv_size.y;
+        }
+
+        SpriteInstance {
+            position: self.position.into(),
+            size: (source.size * self.scale).into(),
+            origin: self.origin.into(),
+            rotation: self.rotation,
+            uv_rect: [uv_position.x, uv_position.y, uv_size.x, uv_size.y],
+            color: self.color,
+        }
+    }
+}
+
+/// The view of a `SpriteRenderer`, measured in pixels with the y axis pointing down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpriteCamera {
+    /// The position in the world that is shown at the centre of the window.
+    pub position: Vec2,
+    /// How many pixels on the window a single pixel in the world covers.
+    pub zoom: f32,
+}
+
+impl Default for SpriteCamera {
+    /// A camera looking at the origin without any zoom.
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl SpriteCamera {
+    /// The matrix that moves the world so that the camera is looking at it, with `position`
+    /// at the centre of a window of size `window_size`.
+    pub fn matrix(&self, window_size: Vec2) -> Mat4 {
+        Mat4::from_translation((window_size / 2.0).extend(0.0))
+            * Mat4::from_scale(Vec3::new(self.zoom, self.zoom, 1.0))
+            * Mat4::from_translation((-self.position).extend(0.0))
+    }
+}
+
+/// The values of a single sprite as they are laid out in the instance buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct SpriteInstance {
+    position: [f32; 2],
+    size: [f32; 2],
+    origin: [f32; 2],
+    rotation: f32,
+    uv_rect: [f32; 4],
+    color: [f32; 4],
+}
+
+impl SpriteInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x2,
+        3 => Float32,
+        4 => Float32x4,
+        5 => Float32x4,
+    ];
+
+    pub(crate) fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Self>() as u64,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// The projection and camera uniforms of a renderer that uses the sprite pipeline.
+pub(crate) struct SpriteView {
+    projection_buffer: Buffer,
+    camera_buffer: Buffer,
+    pub(crate) bind_group: BindGroup,
+}
+
+impl SpriteView {
+    pub(crate) fn new(data: &GameData) -> Self {
+        let bind_group_layout = SimpleRenderer::mat4_bind_group_layout(data);
+        let make_buffer = |contents: Mat4| {
+            data.graphics
+                .lock()
+                .device
+                .create_buffer_init(&BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(&[contents.to_cols_array_2d()]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                })
+        };
+        let projection_buffer = make_buffer(Self::projection(data));
+        let camera_buffer = make_buffer(Mat4::IDENTITY);
+
+        let bind_group =
+            data.graphics
+                .lock()
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: None,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: projection_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: camera_buffer.as_entire_binding(),
+                        },
+                    ],
+                    layout: &bind_group_layout,
+                });
+
+        Self {
+            projection_buffer,
+            camera_buffer,
+            bind_group,
+        }
+    }
+
+    /// Writes the matrix of `camera` for the current window size, or the identity matrix if
+    /// there is no camera.
+    pub(crate) fn update(&self, data: &GameData, camera: Option<SpriteCamera>) {
+        let camera = match camera {
+            Some(camera) => camera.matrix(data.get_window_size().as_vec2()),
+            None => Mat4::IDENTITY,
+        };
+        data.graphics.lock().queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[camera.to_cols_array_2d()]),
+        );
+    }
+
+    /// Writes the projection for the current window size.
+    pub(crate) fn resize(&self, data: &GameData) {
+        data.graphics.lock().queue.write_buffer(
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(&[Self::projection(data).to_cols_array_2d()]),
+        );
+    }
+
+    /// The projection that maps pixels to the window, with the origin in the top left
+    /// corner.
+    fn projection(data: &GameData) -> Mat4 {
+        let size = data.get_window_size().as_vec2();
+        Mat4::orthographic_rh(0.0, size.x, size.y, 0.0, -1.0, 1.0)
+    }
+}
+
+/// A renderer that draws 2D sprites in pixel coordinates. Every update the sprites are
+/// sorted by layer and then texture, and each run of sprites sharing a texture is drawn
+/// with one draw call.
+pub struct SpriteRenderer {
+    pipeline: RenderPipeline,
+    view: SpriteView,
+    instances: StreamingBuffer,
+    batches: Vec<(usize, Range<u32>)>,
+    /// The camera that the sprites are viewed through. If this is `None` then the top left
+    /// corner of the window is at the origin.
+    pub camera: Option<SpriteCamera>,
+    /// A sampler for nearest filters (magnified textures looked pixelated).
+    pub nearest_sampler: Sampler,
+    /// A sampler for linear filters (magnified textures looked blurry).
+    pub linear_sampler: Sampler,
+    /// The textures that sprites can use, referred to by their index.
+    pub textures: Vec<Texture>,
+    /// Every sprite that will be drawn.
+    pub sprites: Vec<Sprite>,
+}
+
+impl SpriteRenderer {
+    /// Create a `SpriteRenderer` with no textures or sprites.
+    pub fn new(data: &GameData) -> Self {
+        Self {
+            pipeline: Self::pipeline(data),
+            view: SpriteView::new(data),
+            instances: StreamingBuffer::new(data, &[]),
+            batches: Vec::new(),
+            camera: None,
+            nearest_sampler: SimpleRenderer::nearest_sampler(data),
+            linear_sampler: SimpleRenderer::linear_sampler(data),
+            textures: Vec::new(),
+            sprites: Vec::new(),
+        }
+    }
+
+    /// Adds a texture for sprites to use and returns its index.
+    pub fn add_texture(&mut self, texture: Texture) -> usize {
+        self.textures.push(texture);
+        self.textures.len() - 1
+    }
+
+    /// Makes the default sprite pipeline.
+    pub fn pipeline(data: &GameData) -> RenderPipeline {
+        let shader = data
+            .shaders
+            .lock()
+            .module(data, "sprite.wgsl", include_str!("sprite.wgsl"), &[])
+            .unwrap();
+
+        let mat4_bind_group_layout = SimpleRenderer::mat4_bind_group_layout(data);
+        let texture_bind_group_layout = Texture::bind_group_layout(data);
+
+        let pipeline_layout =
+            data.graphics
+                .lock()
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&mat4_bind_group_layout, &texture_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let fragment_format = data.graphics.lock().config.format;
+
+        data.graphics
+            .lock()
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Sprite Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "sprite_vertex",
+                    buffers: &[SpriteInstance::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "sprite_fragment",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: fragment_format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+    }
+}
+
+/// Sorts the indices of `sprites` by layer and then by texture, keeping the order of sprites
+/// that are equal in both, then groups sprites with the same texture that are next to each
+/// other into a batch.
+pub(crate) fn batch(sprites: &[Sprite]) -> (Vec<usize>, Vec<(usize, Range<u32>)>) {
+    let mut order = (0..sprites.len()).collect::<Vec<usize>>();
+    order.sort_by_key(|index| (sprites[*index].layer, sprites[*index].texture));
+
+    let mut batches: Vec<(usize, Range<u32>)> = Vec::new();
+    for (position, index) in order.iter().enumerate() {
+        let texture = sprites[*index].texture;
+        match batches.last_mut() {
+            Some((last, range)) if *last == texture => range.end += 1,
+            _ => batches.push((texture, position as u32..position as u32 + 1)),
+        }
+    }
+
+    (order, batches)
+}
+
+impl Renderer for SpriteRenderer {
+    fn render<'a, 'b: 'a>(&'b self, render_pass: &'a mut wgpu::RenderPass<'b>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.view.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instances.buffer.slice(..));
+        for (texture, range) in &self.batches {
+            if let Some(texture) = self.textures.get(*texture) {
+                render_pass.set_bind_group(1, &texture.diffuse, &[]);
+                render_pass.draw(0..6, range.clone());
+            }
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let sprites = self
+            .sprites
+            .iter()
+            .filter(|sprite| sprite.texture < self.textures.len())
+            .copied()
+            .collect::<Vec<Sprite>>();
+        let (order, batches) = batch(&sprites);
+        let instances = order
+            .into_iter()
+            .map(|index| {
+                let sprite = &sprites[index];
+                sprite.instance(self.textures[sprite.texture].size.as_vec2())
+            })
+            .collect::<Vec<SpriteInstance>>();
+        self.instances.write(data, bytemuck::cast_slice(&instances));
+        self.batches = batches;
+
+        self.view.update(data, self.camera);
+    }
+
+    fn make_render_pass<'a>(
+        &'a self,
+        view: &'a TextureView,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    fn resize(&mut self, data: &GameData) {
+        self.view.resize(data);
+    }
+}
+
+#[test]
+fn sprite_batch_test() {
+    let sprites = [
+        Sprite::new(1, Vec2::ZERO).with_layer(1),
+        Sprite::new(0, Vec2::ZERO),
+        Sprite::new(1, Vec2::ZERO),
+        Sprite::new(0, Vec2::ZERO).with_layer(1),
+        Sprite::new(0, Vec2::ZERO),
+    ];
+    let (order, batches) = batch(&sprites);
+    assert_eq!(order, [1, 4, 2, 3, 0]);
+    assert_eq!(batches, [(0, 0..2), (1, 2..3), (0, 3..4), (1, 4..5)]);
+}
+
+#[test]
+fn sprite_instance_test() {
+    let sprite = Sprite::new(0, Vec2::new(10.0, 20.0))
+        .with_source(Rect::new(16.0, 0.0, 16.0, 32.0))
+        .with_scale(Vec2::new(2.0, 1.0))
+        .with_flip(true, false);
+    let instance = sprite.instance(Vec2::new(64.0, 32.0));
+    assert_eq!(instance.size, [32.0, 32.0]);
+    assert_eq!(instance.uv_rect, [0.5, 0.0, -0.25, 1.0]);
+}
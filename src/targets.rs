@@ -0,0 +1,109 @@
+//! Textures that can be rendered into instead of the screen and sampled afterwards, for
+//! things like minimaps, mirrors and post-processing.
+
+use glam::UVec2;
+use wgpu::{Color, CommandEncoder, RenderPass, Sampler, TextureFormat, TextureView};
+
+use crate::{renderers::Texture, GameData};
+
+/// An offscreen color texture, with an optional depth texture, that passes can draw to.
+pub struct RenderTarget {
+    /// The size of the target in pixels.
+    pub size: UVec2,
+    /// The format of the color texture. By default this is the format of the screen, so
+    /// every pipeline that draws to the screen can draw to the target.
+    pub format: TextureFormat,
+    /// A view of the color texture to draw to.
+    pub color_view: TextureView,
+    /// The color texture, to be drawn like any other texture.
+    pub texture: Texture,
+    /// A view of the `Depth32Float` depth texture, if the target has one. It can also be
+    /// bound to a shader as a `texture_depth_2d`.
+    pub depth_view: Option<TextureView>,
+}
+
+impl RenderTarget {
+    /// Create a `RenderTarget` with the format of the screen and a depth texture.
+    pub fn new(data: &GameData, size: UVec2, sampler: &Sampler) -> Self {
+        let format = data.graphics.lock().config.format;
+        Self::with_format(data, size, format, true, sampler)
+    }
+
+    /// Create a `RenderTarget` with a color texture of `format`, and a depth texture if
+    /// `depth` is true.
+    pub fn with_format(
+        data: &GameData,
+        size: UVec2,
+        format: TextureFormat,
+        depth: bool,
+        sampler: &Sampler,
+    ) -> Self {
+        let size = size.max(UVec2::ONE);
+        let extent = wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        };
+        let make_texture = |format| {
+            data.graphics
+                .lock()
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: None,
+                    size: extent,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+
+        let color_view = make_texture(format);
+        Self {
+            size,
+            format,
+            texture: Texture::from_view(data, &color_view, size, sampler),
+            color_view,
+            depth_view: depth.then(|| make_texture(TextureFormat::Depth32Float)),
+        }
+    }
+
+    /// Replaces the textures of the target with ones of a new size, such as when the
+    /// window is resized. Anything that draws `texture` must be given the new one.
+    pub fn resize(&mut self, data: &GameData, size: UVec2, sampler: &Sampler) {
+        *self = Self::with_format(data, size, self.format, self.depth_view.is_some(), sampler);
+    }
+
+    /// Begins a pass that draws to the target, clearing it to `clear` if it is given, or
+    /// keeping what was drawn before if it isn't. The depth texture is always cleared.
+    pub fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut CommandEncoder,
+        clear: Option<Color>,
+    ) -> RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("render_target_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: clear.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: self.depth_view.as_ref().map(|view| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }
+            }),
+        })
+    }
+}
@@ -0,0 +1,189 @@
+//! Generation of terrain meshes and heightmaps from height functions, such as ones made
+//! with `rand::perlin_2d`.
+
+use glam::{IVec2, UVec2, Vec2, Vec3};
+use image::{GrayImage, Luma};
+
+use crate::mesh::Mesh;
+
+/// A description of terrain that is split into square chunks, which can each be generated
+/// on their own and at different levels of detail.
+///
+/// ## Example:
+/// ```
+/// use rhachis::{math::smootherstep, rand::{perlin_2d, Noise}, terrain::Terrain};
+///
+/// let noise = Noise::new();
+/// let terrain = Terrain::new(|pos| perlin_2d(&noise, pos / 20.0, smootherstep) * 10.0)
+///     .with_chunk_cells(16);
+/// let mesh = terrain.chunk_mesh((0, 0).into(), 1);
+/// assert_eq!(mesh.vertices.len(), 9 * 9);
+/// ```
+pub struct Terrain<F> {
+    /// The function that decides the height of the terrain at a position on the XZ plane.
+    pub height: F,
+    /// The number of cells along each side of a chunk at the highest level of detail.
+    pub chunk_cells: u32,
+    /// The width of a cell at the highest level of detail.
+    pub cell_size: f32,
+    /// How far below the surface the skirts around each chunk go. Skirts hide the gaps
+    /// between neighbouring chunks with different levels of detail. If this is not
+    /// positive then no skirts are made.
+    pub skirt_depth: f32,
+}
+
+impl<F: Fn(Vec2) -> f32> Terrain<F> {
+    /// Create a `Terrain` with 32 cells per chunk, cells one unit wide and no skirts.
+    pub fn new(height: F) -> Self {
+        Self {
+            height,
+            chunk_cells: 32,
+            cell_size: 1.0,
+            skirt_depth: 0.0,
+        }
+    }
+
+    /// Sets the number of cells along each side of a chunk, then returns the terrain.
+    pub fn with_chunk_cells(mut self, chunk_cells: u32) -> Self {
+        self.chunk_cells = chunk_cells.max(1);
+        self
+    }
+
+    /// Sets the width of a cell, then returns the terrain.
+    pub fn with_cell_size(mut self, cell_size: f32) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+
+    /// Sets the depth of the skirts around each chunk, then returns the terrain.
+    pub fn with_skirt_depth(mut self, skirt_depth: f32) -> Self {
+        self.skirt_depth = skirt_depth;
+        self
+    }
+
+    /// The width of a chunk in world units.
+    pub fn chunk_extent(&self) -> f32 {
+        self.chunk_cells as f32 * self.cell_size
+    }
+
+    /// The lowest level of detail available, where a chunk is a single cell.
+    pub fn max_lod(&self) -> u32 {
+        self.chunk_cells.max(1).ilog2()
+    }
+
+    /// The normal of the terrain surface at `pos` on the XZ plane.
+    pub fn normal(&self, pos: Vec2) -> Vec3 {
+        let offset = self.cell_size;
+        let dx = (self.height)(pos + Vec2::X * offset) - (self.height)(pos - Vec2::X * offset);
+        let dz = (self.height)(pos + Vec2::Y * offset) - (self.height)(pos - Vec2::Y * offset);
+        Vec3::new(-dx, offset * 2.0, -dz).normalize()
+    }
+
+    /// Makes the mesh of a single chunk. The chunk at `(0, 0)` starts at the origin and
+    /// extends in the positive X and Z directions. Every increase in `lod` halves the number
+    /// of cells along each side. Texture coordinates go from 0 to 1 across each chunk.
+    pub fn chunk_mesh(&self, chunk: IVec2, lod: u32) -> Mesh {
+        let cells = (self.chunk_cells >> lod.min(self.max_lod())).max(1);
+        let origin = chunk.as_vec2() * self.chunk_extent();
+        let spacing = self.chunk_extent() / cells as f32;
+
+        let point = |column: u32, row: u32| origin + Vec2::new(column as f32, row as f32) * spacing;
+        let vertex = |pos: Vec2, depth: f32| {
+            (
+                Vec3::new(pos.x, (self.height)(pos) - depth, pos.y),
+                self.normal(pos),
+            )
+        };
+
+        let mut mesh = Mesh::grid(cells, cells, |column, row| vertex(point(column, row), 0.0));
+
+        if self.skirt_depth > 0.0 {
+            // Each skirt goes from left to right as seen from outside of the chunk.
+            for edge in 0..4 {
+                mesh.append(Mesh::grid(cells, 1, |i, row| {
+                    let (column, grid_row) = match edge {
+                        0 => (cells - i, 0),
+                        1 => (i, cells),
+                        2 => (0, i),
+                        _ => (cells, cells - i),
+                    };
+                    vertex(point(column, grid_row), self.skirt_depth * row as f32)
+                }));
+            }
+        }
+
+        for vertex in &mut mesh.vertices {
+            vertex.tex_coords =
+                (Vec2::new(vertex.pos.x, vertex.pos.z) - origin) / self.chunk_extent();
+        }
+
+        mesh
+    }
+
+    /// Makes a single mesh out of `chunks.x * chunks.y` chunks at the highest level of
+    /// detail, starting from the chunk at `(0, 0)`.
+    pub fn mesh(&self, chunks: UVec2) -> Mesh {
+        let mut mesh = Mesh::default();
+        for x in 0..chunks.x {
+            for y in 0..chunks.y {
+                mesh.append(self.chunk_mesh(UVec2::new(x, y).as_ivec2(), 0));
+            }
+        }
+        mesh
+    }
+
+    /// Picks a level of detail for `chunk` that increases by one for every `lod_distance`
+    /// between the centre of the chunk and `viewer` on the XZ plane.
+    pub fn lod(&self, chunk: IVec2, viewer: Vec2, lod_distance: f32) -> u32 {
+        let centre = (chunk.as_vec2() + 0.5) * self.chunk_extent();
+        ((centre.distance(viewer) / lod_distance) as u32).min(self.max_lod())
+    }
+
+    /// Makes a greyscale image of the terrain's height, with a pixel for every cell
+    /// starting at `origin`. Heights of `min` and below are black, and heights of `max` and
+    /// above are white.
+    pub fn heightmap(&self, origin: Vec2, size: UVec2, min: f32, max: f32) -> GrayImage {
+        GrayImage::from_fn(size.x, size.y, |x, y| {
+            let pos = origin + Vec2::new(x as f32, y as f32) * self.cell_size;
+            let value = ((self.height)(pos) - min) / (max - min);
+            Luma([(value.clamp(0.0, 1.0) * 255.0).round() as u8])
+        })
+    }
+}
+
+#[test]
+fn terrain_seam_test() {
+    let terrain = Terrain::new(|pos: Vec2| (pos.x * 0.3).sin() + (pos.y * 0.2).cos())
+        .with_chunk_cells(8)
+        .with_cell_size(0.5);
+    let left = terrain.chunk_mesh(IVec2::new(0, 0), 0);
+    let right = terrain.chunk_mesh(IVec2::new(1, 0), 0);
+
+    for row in 0..=8 {
+        let left_edge = left.vertices[row * 9 + 8];
+        let right_edge = right.vertices[row * 9];
+        assert_eq!(left_edge.pos, right_edge.pos);
+        assert_eq!(left_edge.normal, right_edge.normal);
+    }
+}
+
+#[test]
+fn terrain_lod_test() {
+    let terrain = Terrain::new(|_| 0.0).with_chunk_cells(8);
+    assert_eq!(terrain.max_lod(), 3);
+    assert_eq!(terrain.chunk_mesh(IVec2::ZERO, 0).indices.len(), 8 * 8 * 6);
+    assert_eq!(terrain.chunk_mesh(IVec2::ZERO, 2).indices.len(), 2 * 2 * 6);
+    assert_eq!(terrain.chunk_mesh(IVec2::ZERO, 10).indices.len(), 6);
+    assert_eq!(terrain.lod(IVec2::ZERO, Vec2::splat(4.0), 10.0), 0);
+    assert_eq!(terrain.lod(IVec2::ZERO, Vec2::new(4.0, 1000.0), 10.0), 3);
+
+    let skirted = terrain.with_skirt_depth(1.0).chunk_mesh(IVec2::ZERO, 0);
+    assert_eq!(skirted.indices.len(), (8 * 8 + 8 * 4) * 6);
+}
+
+#[test]
+fn heightmap_test() {
+    let terrain = Terrain::new(|pos: Vec2| pos.x);
+    let heightmap = terrain.heightmap(Vec2::ZERO, UVec2::new(3, 1), 0.0, 2.0);
+    assert_eq!(heightmap.as_raw(), &[0, 128, 255]);
+}
@@ -0,0 +1,821 @@
+//! Laying out and drawing text with TrueType, OpenType and BMFont bitmap fonts.
+
+use std::{collections::HashMap, fmt::Debug, path::Path, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use glam::{Mat4, Vec2, Vec3};
+use image::{DynamicImage, Rgba, RgbaImage};
+use wgpu::{Sampler, TextureView};
+
+use crate::{
+    atlas::Atlas,
+    graphics::Renderer,
+    instances::InstanceData,
+    math::Rect,
+    renderers::{BlendMode, Model, SimpleRenderer, Texture, Transform},
+    sprites::{Sprite, SpriteCamera, SpriteRenderer},
+    GameData,
+};
+
+/// How each line of text is placed horizontally.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Align {
+    /// Lines start at the left edge.
+    #[default]
+    Left,
+    /// Lines are centred.
+    Centre,
+    /// Lines end at the right edge.
+    Right,
+}
+
+/// Options for how text is broken into lines and placed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextLayout {
+    /// The width that lines are wrapped at. Lines are only broken in the middle of a word if
+    /// the word is wider than this. If this is `None` lines are only broken at newlines.
+    pub max_width: Option<f32>,
+    /// How each line is placed. If there is a maximum width the lines are aligned within
+    /// it, otherwise they are aligned to the position of the text.
+    pub align: Align,
+    /// The amount the height of each line is multiplied by.
+    pub line_spacing: f32,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            align: Align::Left,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+/// A single character of laid out text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphPlacement {
+    /// The index of the texture in `Font::pages` that the glyph is on.
+    pub page: usize,
+    /// The area of the page covered by the glyph in pixels.
+    pub source: Rect,
+    /// The position of the top left corner of the glyph, relative to the top left corner of
+    /// the text.
+    pub position: Vec2,
+    /// The size the glyph is drawn at.
+    pub size: Vec2,
+}
+
+/// A character in a BMFont.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BitmapChar {
+    /// The area of the page covered by the character in pixels.
+    pub rect: Rect,
+    /// The offset from the pen position at the top of the line to the top left corner of
+    /// the character.
+    pub offset: Vec2,
+    /// How far the pen moves after the character.
+    pub advance: f32,
+    /// The index of the page the character is on.
+    pub page: usize,
+}
+
+/// The contents of a BMFont description in the text format.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BitmapFont {
+    /// The size the font was made at.
+    pub size: f32,
+    /// The distance between the tops of each line.
+    pub line_height: f32,
+    /// The distance from the top of a line to the baseline.
+    pub base: f32,
+    /// The file names of the page images.
+    pub pages: Vec<String>,
+    /// Every character in the font.
+    pub chars: HashMap<char, BitmapChar>,
+    /// Adjustments to the distance between pairs of characters.
+    pub kernings: HashMap<(char, char), f32>,
+}
+
+impl FromStr for BitmapFont {
+    type Err = anyhow::Error;
+
+    /// Parses a BMFont description in the text format, as saved in `.fnt` files.
+    fn from_str(description: &str) -> Result<Self> {
+        let mut font = Self::default();
+        for line in description.lines() {
+            let mut tokens = tokenize(line);
+            let tag = match tokens.next() {
+                Some((tag, _)) => tag,
+                None => continue,
+            };
+            let values = tokens.collect::<HashMap<&str, &str>>();
+            let number = |key: &str| -> Result<f32> {
+                values
+                    .get(key)
+                    .with_context(|| format!("{tag} is missing {key}"))?
+                    .parse::<f32>()
+                    .with_context(|| format!("{tag} has an invalid {key}"))
+            };
+            let char_id = |key: &str| -> Result<char> {
+                char::from_u32(number(key)? as u32)
+                    .with_context(|| format!("{tag} has an invalid {key}"))
+            };
+
+            match tag {
+                "info" => font.size = number("size")?.abs(),
+                "common" => {
+                    font.line_height = number("lineHeight")?;
+                    font.base = number("base")?;
+                }
+                "page" => {
+                    let id = number("id")? as usize;
+                    let file = values.get("file").context("page is missing file")?;
+                    if font.pages.len() <= id {
+                        font.pages.resize(id + 1, String::new());
+                    }
+                    font.pages[id] = file.to_string();
+                }
+                "char" => {
+                    font.chars.insert(
+                        char_id("id")?,
+                        BitmapChar {
+                            rect: Rect::new(
+                                number("x")?,
+                                number("y")?,
+                                number("width")?,
+                                number("height")?,
+                            ),
+                            offset: Vec2::new(number("xoffset")?, number("yoffset")?),
+                            advance: number("xadvance")?,
+                            page: number("page").unwrap_or(0.0) as usize,
+                        },
+                    );
+                }
+                "kerning" => {
+                    font.kernings
+                        .insert((char_id("first")?, char_id("second")?), number("amount")?);
+                }
+                _ => {}
+            }
+        }
+
+        if font.size == 0.0 {
+            bail!("The font has no size");
+        }
+        Ok(font)
+    }
+}
+
+/// Splits a line of a BMFont description into its tag followed by its keys and values.
+/// Values may be quoted to include spaces.
+fn tokenize(line: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = line.trim_start();
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let key_end = rest.find(['=', ' ']).unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = &rest[key_end..];
+
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let (start, end) = match after.strip_prefix('"') {
+                Some(quoted) => (quoted, quoted.find('"').unwrap_or(quoted.len())),
+                None => (after, after.find(' ').unwrap_or(after.len())),
+            };
+            value = &start[..end];
+            rest = &start[(end + 1).min(start.len())..];
+        }
+        rest = rest.trim_start();
+        Some((key, value))
+    })
+}
+
+/// A glyph that has been placed on a page.
+#[derive(Clone, Copy, Debug)]
+struct Glyph {
+    page: usize,
+    rect: Rect,
+    /// The offset from the pen position on the baseline to the top left corner of the glyph.
+    offset: Vec2,
+}
+
+enum FontKind {
+    TrueType {
+        font: Box<fontdue::Font>,
+        atlas: Atlas,
+        glyphs: HashMap<(char, u32), Option<Glyph>>,
+    },
+    Bitmap {
+        font: BitmapFont,
+        pages: Vec<Texture>,
+    },
+}
+
+/// A font that text can be laid out and drawn with.
+///
+/// TrueType and OpenType fonts have each character drawn onto an atlas the first time it is
+/// used at a size. Bitmap fonts are drawn from their own pages and scaled to the size they are
+/// used at.
+pub struct Font {
+    kind: FontKind,
+}
+
+impl Font {
+    /// Loads a TrueType or OpenType font from the contents of a font file. Glyphs are drawn
+    /// with `sampler`.
+    pub fn from_bytes(bytes: &[u8], sampler: Sampler) -> Result<Self> {
+        let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+            .map_err(|err| anyhow!(err))?;
+        Ok(Self {
+            kind: FontKind::TrueType {
+                font: Box::new(font),
+                atlas: Atlas::new(sampler),
+                glyphs: HashMap::new(),
+            },
+        })
+    }
+
+    /// Loads a TrueType or OpenType font from a file. Glyphs are drawn with `sampler`.
+    pub fn load<P: AsRef<Path> + Debug>(path: P, sampler: Sampler) -> Result<Self> {
+        let bytes = std::fs::read(&path).with_context(|| format!("Could not read {path:?}"))?;
+        Self::from_bytes(&bytes, sampler)
+    }
+
+    /// Makes a font from a BMFont and the images of its pages, in the same order as
+    /// `BitmapFont::pages`.
+    pub fn from_bitmap(
+        data: &GameData,
+        font: BitmapFont,
+        pages: &[DynamicImage],
+        sampler: &Sampler,
+    ) -> Self {
+        Self {
+            kind: FontKind::Bitmap {
+                font,
+                pages: pages
+                    .iter()
+                    .map(|page| Texture::new(data, &DynamicImage::from(page.to_rgba8()), sampler))
+                    .collect(),
+            },
+        }
+    }
+
+    /// Loads a BMFont from a `.fnt` file in the text format, with its pages in the same
+    /// directory.
+    pub fn load_bitmap<P: AsRef<Path> + Debug>(
+        data: &GameData,
+        path: P,
+        sampler: &Sampler,
+    ) -> Result<Self> {
+        let description =
+            std::fs::read_to_string(&path).with_context(|| format!("Could not read {path:?}"))?;
+        let font = description.parse::<BitmapFont>()?;
+        let directory = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+        let pages = font
+            .pages
+            .iter()
+            .map(|page| {
+                image::open(directory.join(page))
+                    .with_context(|| format!("Could not load font page {page}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_bitmap(data, font, &pages, sampler))
+    }
+
+    /// Lays out `text` the same way as `Font::layout`, then makes a model for each page its
+    /// glyphs are on, so that it can be drawn in a 3D world by a `SimpleRenderer`. Each
+    /// glyph is an instance of a quad, which is a unit across for every pixel with y
+    /// pointing up, and the top left corner of the text is placed by `transform`.
+    pub fn models(
+        &mut self,
+        data: &GameData,
+        text: &str,
+        size: f32,
+        layout: &TextLayout,
+        color: [f32; 4],
+        transform: Transform,
+    ) -> Vec<Model> {
+        let glyphs = self.layout(data, text, size, layout);
+        let transform = Mat4::from(transform);
+        self.pages()
+            .iter()
+            .enumerate()
+            .filter(|(page, _)| glyphs.iter().any(|glyph| glyph.page == *page))
+            .map(|(page, texture)| {
+                let mut model = Model::quad_texture(data, texture.clone(), Vec::new())
+                    .with_blend_mode(BlendMode::Alpha);
+                for glyph in glyphs.iter().filter(|glyph| glyph.page == page) {
+                    let (matrix, instance_data) =
+                        glyph_instance(glyph, texture.size.as_vec2(), color);
+                    let id = model.add_instance_with_data(Transform::default(), instance_data);
+                    model.set_instance_matrix(id, transform * matrix);
+                }
+                model
+            })
+            .collect()
+    }
+
+    /// The textures that the glyphs of the font are on. More pages may be added to
+    /// TrueType fonts as text is laid out.
+    pub fn pages(&self) -> &[Texture] {
+        match &self.kind {
+            FontKind::TrueType { atlas, .. } => atlas.pages(),
+            FontKind::Bitmap { pages, .. } => pages,
+        }
+    }
+
+    /// The distance from the top of a line to the baseline, and the distance between the
+    /// tops of each line, for text of `size` pixels.
+    pub fn line_metrics(&self, size: f32) -> (f32, f32) {
+        match &self.kind {
+            FontKind::TrueType { font, .. } => match font.horizontal_line_metrics(size) {
+                Some(metrics) => (metrics.ascent, metrics.new_line_size),
+                None => (size, size * 1.2),
+            },
+            FontKind::Bitmap { font, .. } => {
+                let scale = size / font.size;
+                (font.base * scale, font.line_height * scale)
+            }
+        }
+    }
+
+    /// Places every character of `text` at `size` pixels. The characters of TrueType fonts
+    /// that haven't been used at this size before are drawn onto the atlas.
+    pub fn layout(
+        &mut self,
+        data: &GameData,
+        text: &str,
+        size: f32,
+        layout: &TextLayout,
+    ) -> Vec<GlyphPlacement> {
+        let (ascent, line_height) = self.line_metrics(size);
+        let lines = wrap(text, layout.max_width, |previous, c| {
+            self.horizontal_metrics(previous, c, size)
+        });
+
+        let mut placements = Vec::new();
+        for (index, line) in lines.into_iter().enumerate() {
+            let align = match layout.align {
+                Align::Left => 0.0,
+                Align::Centre => 0.5,
+                Align::Right => 1.0,
+            };
+            let offset = (layout.max_width.unwrap_or(0.0) - line.width) * align;
+            let baseline = ascent + index as f32 * line_height * layout.line_spacing;
+
+            for (c, x) in line.glyphs {
+                let (glyph, scale) = match self.glyph(data, c, size) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                placements.push(GlyphPlacement {
+                    page: glyph.page,
+                    source: glyph.rect,
+                    position: (Vec2::new(offset + x, baseline) + glyph.offset * scale).round(),
+                    size: glyph.rect.size * scale,
+                });
+            }
+        }
+        placements
+    }
+
+    /// The kerning between `previous` and `c`, and the advance of `c`.
+    fn horizontal_metrics(&self, previous: Option<char>, c: char, size: f32) -> (f32, f32) {
+        match &self.kind {
+            FontKind::TrueType { font, .. } => (
+                previous
+                    .and_then(|previous| font.horizontal_kern(previous, c, size))
+                    .unwrap_or(0.0),
+                font.metrics(c, size).advance_width,
+            ),
+            FontKind::Bitmap { font, .. } => {
+                let scale = size / font.size;
+                (
+                    previous
+                        .and_then(|previous| font.kernings.get(&(previous, c)))
+                        .map_or(0.0, |kerning| kerning * scale),
+                    font.chars.get(&c).map_or(0.0, |char| char.advance * scale),
+                )
+            }
+        }
+    }
+
+    /// Gets the glyph of `c` at `size` pixels, and the amount it is scaled by when drawn.
+    fn glyph(&mut self, data: &GameData, c: char, size: f32) -> Option<(Glyph, f32)> {
+        match &mut self.kind {
+            FontKind::TrueType {
+                font,
+                atlas,
+                glyphs,
+            } => {
+                let glyph = glyphs.entry((c, size.to_bits())).or_insert_with(|| {
+                    let (metrics, coverage) = font.rasterize(c, size);
+                    if metrics.width == 0 || metrics.height == 0 {
+                        return None;
+                    }
+                    let image =
+                        RgbaImage::from_fn(metrics.width as u32, metrics.height as u32, |x, y| {
+                            let alpha = coverage[y as usize * metrics.width + x as usize];
+                            Rgba([255, 255, 255, alpha])
+                        });
+                    let entry = atlas.add(data, &DynamicImage::ImageRgba8(image)).ok()?;
+                    Some(Glyph {
+                        page: entry.page,
+                        rect: entry.rect,
+                        offset: Vec2::new(
+                            metrics.xmin as f32,
+                            -(metrics.ymin as f32 + metrics.height as f32),
+                        ),
+                    })
+                });
+                glyph.map(|glyph| (glyph, 1.0))
+            }
+            FontKind::Bitmap { font, .. } => {
+                let char = font.chars.get(&c)?;
+                Some((
+                    Glyph {
+                        page: char.page,
+                        rect: char.rect,
+                        offset: char.offset - Vec2::new(0.0, font.base),
+                    },
+                    size / font.size,
+                ))
+            }
+        }
+    }
+}
+
+/// A line of text where each character has the position of its pen on the line.
+#[derive(Debug, Default, PartialEq)]
+struct Line {
+    glyphs: Vec<(char, f32)>,
+    /// The position of the pen after the last character.
+    pen: f32,
+    /// The width of the line without any spaces at the end.
+    width: f32,
+}
+
+/// Breaks `text` into lines no wider than `max_width`, using `metrics` to get the kerning
+/// between a character and the one before it, and the advance of the character.
+fn wrap<F>(text: &str, max_width: Option<f32>, mut metrics: F) -> Vec<Line>
+where
+    F: FnMut(Option<char>, char) -> (f32, f32),
+{
+    let max_width = max_width.unwrap_or(f32::INFINITY);
+    let mut lines = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let mut line = Line::default();
+        for word in paragraph.split_inclusive(' ') {
+            // Move the whole word to the next line if it doesn't fit.
+            let mut previous = line.glyphs.last().map(|(c, _)| *c);
+            let mut end = line.pen;
+            for c in word.trim_end_matches(' ').chars() {
+                let (kerning, advance) = metrics(previous, c);
+                end += kerning + advance;
+                previous = Some(c);
+            }
+            if !line.glyphs.is_empty() && end > max_width {
+                lines.push(std::mem::take(&mut line));
+            }
+
+            for c in word.chars() {
+                let (mut kerning, mut advance) = metrics(line.glyphs.last().map(|(c, _)| *c), c);
+                // Words that are too long for a line of their own are split.
+                if c != ' ' && !line.glyphs.is_empty() && line.pen + kerning + advance > max_width {
+                    lines.push(std::mem::take(&mut line));
+                    (kerning, advance) = metrics(None, c);
+                }
+                line.glyphs.push((c, line.pen + kerning));
+                line.pen += kerning + advance;
+                if c != ' ' {
+                    line.width = line.pen;
+                }
+            }
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// The matrix that stretches a quad from 0 to 1 over `glyph` with y pointing up, and the
+/// instance data that draws it from a page of `page_size` pixels in `color`.
+fn glyph_instance(
+    glyph: &GlyphPlacement,
+    page_size: Vec2,
+    color: [f32; 4],
+) -> (Mat4, InstanceData) {
+    let corner = Vec3::new(glyph.position.x, -glyph.position.y - glyph.size.y, 0.0);
+    let matrix = Mat4::from_translation(corner) * Mat4::from_scale(glyph.size.extend(1.0));
+    let position = glyph.source.position / page_size;
+    let size = glyph.source.size / page_size;
+    let instance_data =
+        InstanceData::color(color).with_uv_rect([position.x, position.y, size.x, size.y]);
+    (matrix, instance_data)
+}
+
+/// Where text is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextSpace {
+    /// The position is in pixels from the top left corner of the window.
+    #[default]
+    Screen,
+    /// The position is in the 2D world seen by `TextRenderer::camera`, the same way sprites
+    /// are placed. Text in a 3D world is drawn by a `SimpleRenderer` with the models from
+    /// `Font::models` instead.
+    World,
+}
+
+/// A string drawn by a `TextRenderer`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Text {
+    /// The text to draw.
+    pub string: String,
+    /// The index of the font in `TextRenderer::fonts`.
+    pub font: usize,
+    /// The size of the text in pixels.
+    pub size: f32,
+    /// The position of the top left corner of the text.
+    pub position: Vec2,
+    /// The color of the text.
+    pub color: [f32; 4],
+    /// How the text is broken into lines and placed.
+    pub layout: TextLayout,
+    /// Whether the text is placed on the window or in the world.
+    pub space: TextSpace,
+    /// Text with a higher layer is drawn in front of text with a lower layer.
+    pub layer: i32,
+}
+
+impl Text {
+    /// Create white, left aligned `Text` in screen space.
+    pub fn new(font: usize, string: &str, size: f32, position: Vec2) -> Self {
+        Self {
+            string: string.to_owned(),
+            font,
+            size,
+            position,
+            color: [1.0, 1.0, 1.0, 1.0],
+            layout: TextLayout::default(),
+            space: TextSpace::Screen,
+            layer: 0,
+        }
+    }
+
+    /// Modifies the color of the text, then returns it.
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Modifies the layout of the text, then returns it.
+    pub fn with_layout(mut self, layout: TextLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Modifies where the text is drawn, then returns it.
+    pub fn with_space(mut self, space: TextSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    /// Modifies the layer of the text, then returns it.
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+/// A renderer that draws text, with the text in the world drawn behind the text on the
+/// screen. Each glyph is drawn as a sprite, so text sharing a font page is batched
+/// together.
+pub struct TextRenderer {
+    screen: SpriteRenderer,
+    world: SpriteRenderer,
+    /// The camera that text in world space is viewed through.
+    pub camera: SpriteCamera,
+    /// A sampler for nearest filters (magnified textures looked pixelated).
+    pub nearest_sampler: Sampler,
+    /// A sampler for linear filters (magnified textures looked blurry).
+    pub linear_sampler: Sampler,
+    /// The fonts that text can use, referred to by their index. Layouts are kept until their
+    /// text changes, so a font should be added rather than replace another one.
+    pub fonts: Vec<Font>,
+    /// Every string that will be drawn.
+    pub texts: Vec<Text>,
+    /// The layout of each text in `texts` from the last update.
+    layouts: Vec<CachedLayout>,
+}
+
+/// The glyphs of a text, and what they were laid out from.
+struct CachedLayout {
+    font: usize,
+    string: String,
+    size: f32,
+    layout: TextLayout,
+    glyphs: Vec<GlyphPlacement>,
+}
+
+impl CachedLayout {
+    /// Whether `text` would be laid out the same way.
+    fn matches(&self, text: &Text) -> bool {
+        self.font == text.font
+            && self.size == text.size
+            && self.layout == text.layout
+            && self.string == text.string
+    }
+}
+
+impl TextRenderer {
+    /// Create a `TextRenderer` with no fonts or text.
+    pub fn new(data: &GameData) -> Self {
+        Self {
+            screen: SpriteRenderer::new(data),
+            world: SpriteRenderer::new(data),
+            camera: SpriteCamera::default(),
+            nearest_sampler: SimpleRenderer::nearest_sampler(data),
+            linear_sampler: SimpleRenderer::linear_sampler(data),
+            fonts: Vec::new(),
+            texts: Vec::new(),
+            layouts: Vec::new(),
+        }
+    }
+
+    /// Adds a font for text to use and returns its index.
+    pub fn add_font(&mut self, font: Font) -> usize {
+        self.fonts.push(font);
+        self.fonts.len() - 1
+    }
+
+    /// Lays out `text` and returns the sprites of its glyphs, where `first_page` is the
+    /// index of the first page of its font in the textures of the sprite renderer.
+    pub fn sprites(&mut self, data: &GameData, text: &Text, first_page: usize) -> Vec<Sprite> {
+        let font = match self.fonts.get_mut(text.font) {
+            Some(font) => font,
+            None => return Vec::new(),
+        };
+        let glyphs = font.layout(data, &text.string, text.size, &text.layout);
+        glyph_sprites(text, first_page, &glyphs)
+    }
+}
+
+/// The sprites of the glyphs of `text`, where `first_page` is the index of the first page of
+/// its font in the textures of the sprite renderer.
+fn glyph_sprites(text: &Text, first_page: usize, glyphs: &[GlyphPlacement]) -> Vec<Sprite> {
+    glyphs
+        .iter()
+        .map(|glyph| {
+            Sprite::new(first_page + glyph.page, text.position + glyph.position)
+                .with_source(glyph.source)
+                .with_scale(glyph.size / glyph.source.size)
+                .with_color(text.color)
+                .with_layer(text.layer)
+        })
+        .collect()
+}
+
+impl Renderer for TextRenderer {
+    fn render<'a, 'b: 'a>(&'b self, render_pass: &'a mut wgpu::RenderPass<'b>) {
+        self.world.render(render_pass);
+        self.screen.render(render_pass);
+    }
+
+    fn update(&mut self, data: &GameData) {
+        let mut screen = Vec::new();
+        let mut world = Vec::new();
+        // Glyphs are added to the pages first, so that the pages of every font are known.
+        self.layouts.truncate(self.texts.len());
+        for (index, text) in self.texts.iter().enumerate() {
+            if self
+                .layouts
+                .get(index)
+                .is_some_and(|cached| cached.matches(text))
+            {
+                continue;
+            }
+            let cached = CachedLayout {
+                font: text.font,
+                string: text.string.clone(),
+                size: text.size,
+                layout: text.layout,
+                glyphs: match self.fonts.get_mut(text.font) {
+                    Some(font) => font.layout(data, &text.string, text.size, &text.layout),
+                    None => Vec::new(),
+                },
+            };
+            match self.layouts.get_mut(index) {
+                Some(old) => *old = cached,
+                None => self.layouts.push(cached),
+            }
+        }
+
+        let mut first_pages = Vec::with_capacity(self.fonts.len());
+        let mut textures = Vec::new();
+        for font in &self.fonts {
+            first_pages.push(textures.len());
+            textures.extend(font.pages().iter().cloned());
+        }
+        for (text, cached) in self.texts.iter().zip(&self.layouts) {
+            let first_page = first_pages.get(text.font).copied().unwrap_or(0);
+            let sprites = glyph_sprites(text, first_page, &cached.glyphs);
+            match text.space {
+                TextSpace::Screen => screen.extend(sprites),
+                TextSpace::World => world.extend(sprites),
+            }
+        }
+
+        self.screen.textures = textures.clone();
+        self.screen.sprites = screen;
+        self.world.textures = textures;
+        self.world.sprites = world;
+        self.world.camera = Some(self.camera);
+        self.screen.update(data);
+        self.world.update(data);
+    }
+
+    fn make_render_pass<'a>(
+        &'a self,
+        view: &'a TextureView,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        self.screen.make_render_pass(view, encoder)
+    }
+
+    fn resize(&mut self, data: &GameData) {
+        self.screen.resize(data);
+        self.world.resize(data);
+    }
+}
+
+#[test]
+fn text_wrap_test() {
+    // Every character is 10 pixels wide, and "AV" is kerned 2 pixels closer.
+    let metrics = |previous, c| {
+        let kerning = if previous == Some('A') && c == 'V' {
+            -2.0
+        } else {
+            0.0
+        };
+        (kerning, 10.0)
+    };
+    let text = |lines: &[Line]| {
+        lines
+            .iter()
+            .map(|line| line.glyphs.iter().map(|(c, _)| c).collect::<String>())
+            .collect::<Vec<String>>()
+    };
+
+    let lines = wrap("AV ab abc\nabcdefgh", Some(60.0), metrics);
+    assert_eq!(text(&lines), ["AV ab ", "abc", "abcdef", "gh"]);
+    assert_eq!(lines[0].glyphs[1], ('V', 8.0));
+    assert_eq!(lines[0].width, 48.0);
+
+    let lines = wrap("ab cd", None, metrics);
+    assert_eq!(text(&lines), ["ab cd"]);
+}
+
+#[test]
+fn bitmap_font_test() {
+    let font = r#"info face="Pixel Font" size=-16 bold=0
+common lineHeight=18 base=14 scaleW=128 scaleH=128 pages=1
+page id=0 file="pixel font.png"
+chars count=2
+char id=65 x=0 y=0 width=8 height=12 xoffset=1 yoffset=2 xadvance=9 page=0 chnl=15
+char id=86 x=8 y=0 width=8 height=12 xoffset=0 yoffset=2 xadvance=9 page=0 chnl=15
+kernings count=1
+kerning first=65 second=86 amount=-1"#
+        .parse::<BitmapFont>()
+        .unwrap();
+    assert_eq!(font.size, 16.0);
+    assert_eq!(font.base, 14.0);
+    assert_eq!(font.pages, ["pixel font.png"]);
+    assert_eq!(font.chars[&'A'].offset, Vec2::new(1.0, 2.0));
+    assert_eq!(font.chars[&'V'].rect, Rect::new(8.0, 0.0, 8.0, 12.0));
+    assert_eq!(font.kernings[&('A', 'V')], -1.0);
+}
+
+#[test]
+fn glyph_instance_test() {
+    let glyph = GlyphPlacement {
+        page: 0,
+        source: Rect::new(16.0, 0.0, 8.0, 16.0),
+        position: Vec2::new(10.0, 20.0),
+        size: Vec2::new(4.0, 8.0),
+    };
+    let (matrix, instance_data) = glyph_instance(&glyph, Vec2::splat(64.0), [1.0; 4]);
+    // The bottom of the quad is at the bottom of the glyph, below the top of the text.
+    assert_eq!(
+        matrix.transform_point3(Vec3::ZERO),
+        Vec3::new(10.0, -28.0, 0.0)
+    );
+    assert_eq!(
+        matrix.transform_point3(Vec3::new(1.0, 1.0, 0.0)),
+        Vec3::new(14.0, -20.0, 0.0)
+    );
+    assert_eq!(instance_data.uv_rect, [0.25, 0.0, 0.125, 0.25]);
+}
@@ -0,0 +1,1067 @@
+//! Loading maps made with the Tiled map editor from `.tmx` and `.tmj` files, including the
+//! tilesets they use in `.tsx` and `.tsj` files.
+
+use std::{fmt::Debug, io::Read, path::Path, str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use glam::{IVec2, UVec2, Vec2};
+use roxmltree::{Document, Node};
+use serde::Deserialize;
+
+use crate::tilemap::{Properties, Property, Tile, TileFrame, TileLayer, Tileset};
+
+/// The shape of an `Object`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectShape {
+    /// A rectangle covering the size of the object.
+    Rectangle,
+    /// An ellipse that fits inside the size of the object.
+    Ellipse,
+    /// A single point at the position of the object.
+    Point,
+    /// A closed shape with corners relative to the position of the object.
+    Polygon(Vec<Vec2>),
+    /// An open line through points relative to the position of the object.
+    Polyline(Vec<Vec2>),
+    /// Text that fits inside the size of the object.
+    Text(String),
+}
+
+/// Something placed freely in an object layer, such as a spawn point or a trigger area.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Object {
+    /// The ID of the object, which is unique in the map.
+    pub id: u32,
+    /// The name of the object.
+    pub name: String,
+    /// The class of the object, which used to be called its type.
+    pub class: String,
+    /// The position of the object in pixels, including the offset of its layer. This is the
+    /// top left corner of most objects, but the bottom left corner of tile objects.
+    pub position: Vec2,
+    /// The size of the object in pixels.
+    pub size: Vec2,
+    /// The clockwise rotation of the object around its position in radians.
+    pub rotation: f32,
+    /// If the object is shown.
+    pub visible: bool,
+    /// The tile the object draws, if it is a tile object.
+    pub tile: Option<Tile>,
+    /// The shape of the object.
+    pub shape: ObjectShape,
+    /// The custom properties of the object.
+    pub properties: Properties,
+}
+
+/// A layer of objects.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectLayer {
+    /// The name of the layer.
+    pub name: String,
+    /// If the layer is shown.
+    pub visible: bool,
+    /// The objects in the layer.
+    pub objects: Vec<Object>,
+    /// The custom properties of the layer.
+    pub properties: Properties,
+}
+
+/// A layer of a `TiledMap`. Layers inside groups are moved out of their group, taking on the
+/// offset, opacity and visibility of the group.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+/// An orthogonal map made with Tiled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledMap {
+    /// The number of tiles in each row and column of the map.
+    pub size: UVec2,
+    /// The size of each cell of the grid in pixels.
+    pub tile_size: UVec2,
+    /// The color behind the map, if it has one.
+    pub background: Option<[f32; 4]>,
+    /// The tilesets used by the map, with the paths of their images relative to the working
+    /// directory.
+    pub tilesets: Vec<Tileset>,
+    /// Every layer from back to front.
+    pub layers: Vec<Layer>,
+    /// The custom properties of the map.
+    pub properties: Properties,
+}
+
+impl TiledMap {
+    /// Loads a map from a `.tmx` or `.tmj` file, decided by its extension, along with any
+    /// tilesets in other files.
+    pub fn load<P: AsRef<Path> + Debug>(path: P) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(&path).with_context(|| format!("Could not read {path:?}"))?;
+        let directory = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+        match path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("tmj" | "json") => Self::from_tmj(&contents, directory),
+            _ => Self::from_tmx(&contents, directory),
+        }
+        .with_context(|| format!("Could not load map {path:?}"))
+    }
+
+    /// Loads a map from the contents of a `.tmx` file, where files it uses are relative to
+    /// `directory`.
+    pub fn from_tmx(xml: &str, directory: &Path) -> Result<Self> {
+        let document = Document::parse(xml)?;
+        let map = document.root_element();
+        check_orientation(map.attribute("orientation"))?;
+
+        let mut tilesets = Vec::new();
+        for node in map.children().filter(|node| node.has_tag_name("tileset")) {
+            let first_gid = attribute(node, "firstgid")?;
+            tilesets.push(match node.attribute("source") {
+                Some(source) => load_tileset(&directory.join(source), first_gid)?,
+                None => tmx_tileset(node, first_gid, directory)?,
+            });
+        }
+
+        let mut layers = Vec::new();
+        tmx_layers(map, Group::default(), directory, &mut layers)?;
+
+        Ok(Self {
+            size: UVec2::new(attribute(map, "width")?, attribute(map, "height")?),
+            tile_size: UVec2::new(attribute(map, "tilewidth")?, attribute(map, "tileheight")?),
+            background: map.attribute("backgroundcolor").map(color).transpose()?,
+            tilesets,
+            layers,
+            properties: tmx_properties(map, directory)?,
+        })
+    }
+
+    /// Loads a map from the contents of a `.tmj` file, where files it uses are relative to
+    /// `directory`.
+    pub fn from_tmj(json: &str, directory: &Path) -> Result<Self> {
+        let map: JsonMap = serde_json::from_str(json)?;
+        check_orientation(map.orientation.as_deref())?;
+
+        let tilesets = map
+            .tilesets
+            .into_iter()
+            .map(|tileset| {
+                let first_gid = tileset.firstgid;
+                match &tileset.source {
+                    Some(source) => load_tileset(&directory.join(source), first_gid),
+                    None => tileset.into_tileset(first_gid, directory),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut layers = Vec::new();
+        for layer in map.layers {
+            layer.flatten(Group::default(), directory, &mut layers)?;
+        }
+
+        Ok(Self {
+            size: UVec2::new(map.width, map.height),
+            tile_size: UVec2::new(map.tilewidth, map.tileheight),
+            background: map.backgroundcolor.as_deref().map(color).transpose()?,
+            tilesets,
+            layers,
+            properties: json_properties(map.properties, directory)?,
+        })
+    }
+
+    /// Every tile layer from back to front.
+    pub fn tile_layers(&self) -> impl Iterator<Item = &TileLayer> {
+        self.layers.iter().filter_map(|layer| match layer {
+            Layer::Tiles(layer) => Some(layer),
+            Layer::Objects(_) => None,
+        })
+    }
+
+    /// Every object layer from back to front.
+    pub fn object_layers(&self) -> impl Iterator<Item = &ObjectLayer> {
+        self.layers.iter().filter_map(|layer| match layer {
+            Layer::Objects(layer) => Some(layer),
+            Layer::Tiles(_) => None,
+        })
+    }
+
+    /// Finds the first object called `name` in any object layer.
+    pub fn object(&self, name: &str) -> Option<&Object> {
+        self.object_layers()
+            .flat_map(|layer| &layer.objects)
+            .find(|object| object.name == name)
+    }
+}
+
+fn check_orientation(orientation: Option<&str>) -> Result<()> {
+    match orientation {
+        None | Some("orthogonal") => Ok(()),
+        Some(orientation) => bail!("Maps with {orientation} orientation aren't supported"),
+    }
+}
+
+/// Loads a tileset from a `.tsx` or `.tsj` file, decided by its extension.
+fn load_tileset(path: &Path, first_gid: u32) -> Result<Tileset> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("Could not read {path:?}"))?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tsj" | "json") => {
+            serde_json::from_str::<JsonTileset>(&contents)?.into_tileset(first_gid, directory)
+        }
+        _ => tmx_tileset(
+            Document::parse(&contents)?.root_element(),
+            first_gid,
+            directory,
+        ),
+    }
+    .with_context(|| format!("Could not load tileset {path:?}"))
+}
+
+/// The offset, opacity and visibility that a group gives to the layers inside it.
+#[derive(Clone, Copy, Debug)]
+struct Group {
+    offset: Vec2,
+    opacity: f32,
+    visible: bool,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            opacity: 1.0,
+            visible: true,
+        }
+    }
+}
+
+impl Group {
+    /// The group of a layer inside this one.
+    fn inner(&self, offset: Vec2, opacity: f32, visible: bool) -> Self {
+        Self {
+            offset: self.offset + offset,
+            opacity: self.opacity * opacity,
+            visible: self.visible && visible,
+        }
+    }
+
+    /// Makes a tile layer from the chunks of its tiles, which are the whole layer in maps
+    /// that aren't infinite.
+    fn tile_layer(
+        &self,
+        name: &str,
+        tint: Option<&str>,
+        chunks: Vec<(IVec2, UVec2, Vec<u32>)>,
+        properties: Properties,
+    ) -> Result<TileLayer> {
+        let start = chunks
+            .iter()
+            .map(|(position, _, _)| *position)
+            .reduce(IVec2::min)
+            .unwrap_or_default();
+        let end = chunks
+            .iter()
+            .map(|(position, size, _)| *position + size.as_ivec2())
+            .reduce(IVec2::max)
+            .unwrap_or_default();
+
+        let mut layer = TileLayer::new(name, (end - start).as_uvec2());
+        layer.origin = start;
+        for (position, size, gids) in chunks {
+            if gids.len() != (size.x * size.y) as usize {
+                bail!(
+                    "Layer {name} has {} tiles instead of {}",
+                    gids.len(),
+                    size.x * size.y
+                );
+            }
+            let offset = (position - start).as_uvec2();
+            for (index, gid) in gids.into_iter().enumerate() {
+                let cell = UVec2::new(index as u32 % size.x, index as u32 / size.x);
+                layer.set(offset + cell, Tile::from_gid(gid));
+            }
+        }
+
+        let tint = tint.map(color).transpose()?.unwrap_or([1.0; 4]);
+        layer.color = [tint[0], tint[1], tint[2], tint[3] * self.opacity];
+        layer.offset = self.offset;
+        layer.visible = self.visible;
+        layer.properties = properties;
+        Ok(layer)
+    }
+}
+
+/// Parses a color in the form `#RRGGBB` or `#AARRGGBB`.
+fn color(text: &str) -> Result<[f32; 4]> {
+    let hex = text.trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16).with_context(|| format!("Invalid color {text}"))?;
+    let channel = |shift: u32| ((value >> shift) & 0xff) as f32 / 255.0;
+    match hex.len() {
+        6 => Ok([channel(16), channel(8), channel(0), 1.0]),
+        8 => Ok([channel(16), channel(8), channel(0), channel(24)]),
+        _ => bail!("Invalid color {text}"),
+    }
+}
+
+/// Decodes the global IDs of tiles stored as CSV or as compressed or uncompressed base64.
+fn decode_tiles(text: &str, encoding: Option<&str>, compression: Option<&str>) -> Result<Vec<u32>> {
+    match encoding {
+        Some("csv") => text
+            .split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| gid.parse().with_context(|| format!("Invalid tile {gid}")))
+            .collect(),
+        Some("base64") => {
+            let text = text
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>();
+            let compressed = base64::decode(text)?;
+            let mut bytes = Vec::new();
+            match compression {
+                None | Some("") => bytes = compressed,
+                Some("zlib") => {
+                    flate2::read::ZlibDecoder::new(compressed.as_slice())
+                        .read_to_end(&mut bytes)?;
+                }
+                Some("gzip") => {
+                    flate2::read::GzDecoder::new(compressed.as_slice()).read_to_end(&mut bytes)?;
+                }
+                Some(compression) => bail!("Tiles compressed with {compression} aren't supported"),
+            }
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        Some(encoding) => bail!("Tiles encoded with {encoding} aren't supported"),
+        None => bail!("Tiles have no encoding"),
+    }
+}
+
+/// Makes a property of the type `kind` from its value written as text.
+fn property(kind: &str, value: &str, directory: &Path) -> Result<Property> {
+    let invalid = || anyhow!("Invalid {kind} property {value}");
+    Ok(match kind {
+        "bool" => Property::Bool(value.parse().map_err(|_| invalid())?),
+        "int" => Property::Int(value.parse().map_err(|_| invalid())?),
+        "float" => Property::Float(value.parse().map_err(|_| invalid())?),
+        "color" if value.is_empty() => Property::Color([0.0; 4]),
+        "color" => Property::Color(color(value)?),
+        "file" => Property::File(directory.join(value)),
+        "object" => Property::Object(value.parse().map_err(|_| invalid())?),
+        _ => Property::String(value.to_owned()),
+    })
+}
+
+/// Gets the attribute `name` of `node`, returning an error if it is missing or invalid.
+fn attribute<T: FromStr>(node: Node, name: &str) -> Result<T> {
+    let tag = node.tag_name().name();
+    node.attribute(name)
+        .with_context(|| format!("<{tag}> is missing {name}"))?
+        .parse()
+        .map_err(|_| anyhow!("<{tag}> has an invalid {name}"))
+}
+
+/// Gets the attribute `name` of `node`, or `default` if it is missing.
+fn attribute_or<T: FromStr>(node: Node, name: &str, default: T) -> Result<T> {
+    match node.attribute(name) {
+        Some(_) => attribute(node, name),
+        None => Ok(default),
+    }
+}
+
+fn tmx_properties(node: Node, directory: &Path) -> Result<Properties> {
+    let mut properties = Properties::new();
+    let list = node
+        .children()
+        .filter(|node| node.has_tag_name("properties"));
+    for property in list.flat_map(|list| list.children()) {
+        if !property.has_tag_name("property") {
+            continue;
+        }
+        let name = property
+            .attribute("name")
+            .context("<property> is missing name")?;
+        let kind = property.attribute("type").unwrap_or("string");
+        let value = match kind {
+            "class" => Property::Class(tmx_properties(property, directory)?),
+            _ => {
+                // Text with more than one line is stored inside the element.
+                let value = property
+                    .attribute("value")
+                    .or_else(|| property.text())
+                    .unwrap_or_default();
+                self::property(kind, value, directory)?
+            }
+        };
+        properties.insert(name.to_owned(), value);
+    }
+    Ok(properties)
+}
+
+fn tmx_tileset(node: Node, first_gid: u32, directory: &Path) -> Result<Tileset> {
+    let image = node
+        .children()
+        .find(|node| node.has_tag_name("image"))
+        .context("Tilesets made of separate images aren't supported")?;
+    let tile_size = UVec2::new(
+        attribute(node, "tilewidth")?,
+        attribute(node, "tileheight")?,
+    );
+    let image_size = UVec2::new(attribute(image, "width")?, attribute(image, "height")?);
+
+    let mut tileset = Tileset::new(first_gid, image_size, tile_size).with_spacing(
+        attribute_or(node, "margin", 0)?,
+        attribute_or(node, "spacing", 0)?,
+    );
+    tileset.name = node.attribute("name").unwrap_or_default().to_owned();
+    tileset.image = directory.join(attribute::<String>(image, "source")?);
+    tileset.columns = attribute_or(node, "columns", tileset.columns)?;
+    tileset.tile_count = attribute_or(node, "tilecount", tileset.tile_count)?;
+    tileset.properties = tmx_properties(node, directory)?;
+    if let Some(offset) = node.children().find(|node| node.has_tag_name("tileoffset")) {
+        tileset.offset = Vec2::new(
+            attribute_or(offset, "x", 0.0)?,
+            attribute_or(offset, "y", 0.0)?,
+        );
+    }
+
+    for tile in node.children().filter(|node| node.has_tag_name("tile")) {
+        let id = attribute(tile, "id")?;
+        let properties = tmx_properties(tile, directory)?;
+        if !properties.is_empty() {
+            tileset.tile_properties.insert(id, properties);
+        }
+        if let Some(animation) = tile.children().find(|node| node.has_tag_name("animation")) {
+            let frames = animation
+                .children()
+                .filter(|node| node.has_tag_name("frame"))
+                .map(|frame| {
+                    Ok(TileFrame {
+                        tile: attribute(frame, "tileid")?,
+                        duration: Duration::from_millis(attribute(frame, "duration")?),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            tileset.animations.insert(id, frames);
+        }
+    }
+
+    Ok(tileset)
+}
+
+/// Adds the layers inside `node` to `layers`, moving layers in groups out of them.
+fn tmx_layers(node: Node, group: Group, directory: &Path, layers: &mut Vec<Layer>) -> Result<()> {
+    for node in node.children().filter(Node::is_element) {
+        let name = node.attribute("name").unwrap_or_default();
+        let group = group.inner(
+            Vec2::new(
+                attribute_or(node, "offsetx", 0.0)?,
+                attribute_or(node, "offsety", 0.0)?,
+            ),
+            attribute_or(node, "opacity", 1.0)?,
+            attribute_or(node, "visible", 1)? != 0,
+        );
+
+        match node.tag_name().name() {
+            "layer" => {
+                let data = node
+                    .children()
+                    .find(|node| node.has_tag_name("data"))
+                    .with_context(|| format!("Layer {name} has no data"))?;
+                let encoding = data.attribute("encoding");
+                let compression = data.attribute("compression");
+                let decode = |node: Node| -> Result<Vec<u32>> {
+                    match encoding {
+                        Some(_) => {
+                            decode_tiles(node.text().unwrap_or_default(), encoding, compression)
+                        }
+                        None => node
+                            .children()
+                            .filter(|node| node.has_tag_name("tile"))
+                            .map(|tile| attribute_or(tile, "gid", 0))
+                            .collect(),
+                    }
+                };
+
+                let mut chunks = Vec::new();
+                for chunk in data.children().filter(|node| node.has_tag_name("chunk")) {
+                    chunks.push((
+                        IVec2::new(attribute(chunk, "x")?, attribute(chunk, "y")?),
+                        UVec2::new(attribute(chunk, "width")?, attribute(chunk, "height")?),
+                        decode(chunk)?,
+                    ));
+                }
+                if chunks.is_empty() {
+                    chunks.push((
+                        IVec2::ZERO,
+                        UVec2::new(attribute(node, "width")?, attribute(node, "height")?),
+                        decode(data)?,
+                    ));
+                }
+
+                layers.push(Layer::Tiles(group.tile_layer(
+                    name,
+                    node.attribute("tintcolor"),
+                    chunks,
+                    tmx_properties(node, directory)?,
+                )?));
+            }
+            "objectgroup" => {
+                let objects = node
+                    .children()
+                    .filter(|node| node.has_tag_name("object"))
+                    .map(|object| tmx_object(object, group.offset, directory))
+                    .collect::<Result<Vec<_>>>()?;
+                layers.push(Layer::Objects(ObjectLayer {
+                    name: name.to_owned(),
+                    visible: group.visible,
+                    objects,
+                    properties: tmx_properties(node, directory)?,
+                }));
+            }
+            "group" => tmx_layers(node, group, directory, layers)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn tmx_object(node: Node, offset: Vec2, directory: &Path) -> Result<Object> {
+    let points = |text: &str| -> Result<Vec<Vec2>> {
+        text.split_whitespace()
+            .map(|point| {
+                let (x, y) = point.split_once(',').context("Invalid point")?;
+                Ok(Vec2::new(x.parse()?, y.parse()?))
+            })
+            .collect()
+    };
+
+    let mut shape = ObjectShape::Rectangle;
+    for child in node.children() {
+        shape = match child.tag_name().name() {
+            "ellipse" => ObjectShape::Ellipse,
+            "point" => ObjectShape::Point,
+            "polygon" => {
+                ObjectShape::Polygon(points(child.attribute("points").unwrap_or_default())?)
+            }
+            "polyline" => {
+                ObjectShape::Polyline(points(child.attribute("points").unwrap_or_default())?)
+            }
+            "text" => ObjectShape::Text(child.text().unwrap_or_default().to_owned()),
+            _ => continue,
+        };
+    }
+
+    Ok(Object {
+        id: attribute_or(node, "id", 0)?,
+        name: node.attribute("name").unwrap_or_default().to_owned(),
+        class: node
+            .attribute("class")
+            .or_else(|| node.attribute("type"))
+            .unwrap_or_default()
+            .to_owned(),
+        position: offset + Vec2::new(attribute_or(node, "x", 0.0)?, attribute_or(node, "y", 0.0)?),
+        size: Vec2::new(
+            attribute_or(node, "width", 0.0)?,
+            attribute_or(node, "height", 0.0)?,
+        ),
+        rotation: attribute_or(node, "rotation", 0.0f32)?.to_radians(),
+        visible: attribute_or(node, "visible", 1)? != 0,
+        tile: node
+            .attribute("gid")
+            .map(|_| attribute(node, "gid"))
+            .transpose()?
+            .map(Tile::from_gid),
+        shape,
+        properties: tmx_properties(node, directory)?,
+    })
+}
+
+fn json_properties(properties: Vec<JsonProperty>, directory: &Path) -> Result<Properties> {
+    properties
+        .into_iter()
+        .map(|property| {
+            let value = json_property(&property.kind, property.value, directory)?;
+            Ok((property.name, value))
+        })
+        .collect()
+}
+
+fn json_property(kind: &str, value: serde_json::Value, directory: &Path) -> Result<Property> {
+    use serde_json::Value;
+
+    Ok(match (kind, value) {
+        (_, Value::Bool(value)) => Property::Bool(value),
+        ("int" | "object", Value::Number(number)) => {
+            let number = number
+                .as_i64()
+                .with_context(|| format!("Invalid {kind} property"))?;
+            match kind {
+                "int" => Property::Int(number),
+                _ => Property::Object(number as u32),
+            }
+        }
+        (_, Value::Number(number)) => match number.as_i64() {
+            Some(number) if kind != "float" => Property::Int(number),
+            _ => Property::Float(number.as_f64().unwrap_or_default()),
+        },
+        (_, Value::String(value)) => property(kind, &value, directory)?,
+        // The members of classes don't have their types written.
+        (_, Value::Object(members)) => Property::Class(
+            members
+                .into_iter()
+                .map(|(name, value)| Ok((name, json_property("", value, directory)?)))
+                .collect::<Result<Properties>>()?,
+        ),
+        (_, value) => bail!("Invalid {kind} property {value}"),
+    })
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+fn default_property_kind() -> String {
+    "string".to_owned()
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    orientation: Option<String>,
+    backgroundcolor: Option<String>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    tilesets: Vec<JsonTileset>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    #[serde(default = "default_property_kind", rename = "type")]
+    kind: String,
+    value: serde_json::Value,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct JsonTileset {
+    firstgid: u32,
+    source: Option<String>,
+    name: String,
+    image: Option<String>,
+    imagewidth: u32,
+    imageheight: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    margin: u32,
+    spacing: u32,
+    columns: Option<u32>,
+    tilecount: Option<u32>,
+    tileoffset: Option<JsonPoint>,
+    properties: Vec<JsonProperty>,
+    tiles: Vec<JsonTile>,
+}
+
+impl JsonTileset {
+    fn into_tileset(self, first_gid: u32, directory: &Path) -> Result<Tileset> {
+        let image = self
+            .image
+            .context("Tilesets made of separate images aren't supported")?;
+        let mut tileset = Tileset::new(
+            first_gid,
+            UVec2::new(self.imagewidth, self.imageheight),
+            UVec2::new(self.tilewidth, self.tileheight),
+        )
+        .with_spacing(self.margin, self.spacing);
+        tileset.name = self.name;
+        tileset.image = directory.join(image);
+        tileset.columns = self.columns.unwrap_or(tileset.columns);
+        tileset.tile_count = self.tilecount.unwrap_or(tileset.tile_count);
+        tileset.offset = self.tileoffset.map_or(Vec2::ZERO, Vec2::from);
+        tileset.properties = json_properties(self.properties, directory)?;
+
+        for tile in self.tiles {
+            if !tile.properties.is_empty() {
+                tileset
+                    .tile_properties
+                    .insert(tile.id, json_properties(tile.properties, directory)?);
+            }
+            if !tile.animation.is_empty() {
+                let frames = tile
+                    .animation
+                    .into_iter()
+                    .map(|frame| TileFrame {
+                        tile: frame.tileid,
+                        duration: Duration::from_millis(frame.duration),
+                    })
+                    .collect();
+                tileset.animations.insert(tile.id, frames);
+            }
+        }
+
+        Ok(tileset)
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonTile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    animation: Vec<JsonFrame>,
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    tileid: u32,
+    duration: u64,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+struct JsonPoint {
+    x: f32,
+    y: f32,
+}
+
+impl From<JsonPoint> for Vec2 {
+    fn from(point: JsonPoint) -> Self {
+        Vec2::new(point.x, point.y)
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default = "default_opacity")]
+    opacity: f32,
+    #[serde(default = "default_visible")]
+    visible: bool,
+    tintcolor: Option<String>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    data: Option<JsonData>,
+    encoding: Option<String>,
+    compression: Option<String>,
+    chunks: Option<Vec<JsonChunk>>,
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+}
+
+impl JsonLayer {
+    /// Adds the layer to `layers`, moving layers in groups out of them.
+    fn flatten(self, group: Group, directory: &Path, layers: &mut Vec<Layer>) -> Result<()> {
+        let group = group.inner(
+            Vec2::new(self.offsetx, self.offsety),
+            self.opacity,
+            self.visible,
+        );
+        let properties = json_properties(self.properties, directory)?;
+        let encoding = self.encoding.as_deref().or(Some("csv"));
+        let compression = self.compression.as_deref();
+        let decode = |data: JsonData| match data {
+            JsonData::Tiles(gids) => Ok(gids),
+            JsonData::Encoded(text) => decode_tiles(&text, encoding, compression),
+        };
+
+        match self.kind.as_str() {
+            "tilelayer" => {
+                let chunks = match (self.chunks, self.data) {
+                    (Some(chunks), _) => chunks
+                        .into_iter()
+                        .map(|chunk| {
+                            Ok((
+                                IVec2::new(chunk.x, chunk.y),
+                                UVec2::new(chunk.width, chunk.height),
+                                decode(chunk.data)?,
+                            ))
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                    (None, Some(data)) => {
+                        vec![(
+                            IVec2::ZERO,
+                            UVec2::new(self.width, self.height),
+                            decode(data)?,
+                        )]
+                    }
+                    (None, None) => bail!("Layer {} has no data", self.name),
+                };
+                layers.push(Layer::Tiles(group.tile_layer(
+                    &self.name,
+                    self.tintcolor.as_deref(),
+                    chunks,
+                    properties,
+                )?));
+            }
+            "objectgroup" => {
+                let objects = self
+                    .objects
+                    .into_iter()
+                    .map(|object| object.into_object(group.offset, directory))
+                    .collect::<Result<Vec<_>>>()?;
+                layers.push(Layer::Objects(ObjectLayer {
+                    name: self.name,
+                    visible: group.visible,
+                    objects,
+                    properties,
+                }));
+            }
+            "group" => {
+                for layer in self.layers {
+                    layer.flatten(group, directory, layers)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonData {
+    Tiles(Vec<u32>),
+    Encoded(String),
+}
+
+#[derive(Deserialize)]
+struct JsonChunk {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    data: JsonData,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct JsonObject {
+    id: u32,
+    name: String,
+    class: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    rotation: f32,
+    #[serde(default = "default_visible")]
+    visible: bool,
+    gid: Option<u32>,
+    ellipse: bool,
+    point: bool,
+    polygon: Option<Vec<JsonPoint>>,
+    polyline: Option<Vec<JsonPoint>>,
+    text: Option<JsonText>,
+    properties: Vec<JsonProperty>,
+}
+
+impl JsonObject {
+    fn into_object(self, offset: Vec2, directory: &Path) -> Result<Object> {
+        let points = |points: Vec<JsonPoint>| points.into_iter().map(Vec2::from).collect();
+        let shape = if self.ellipse {
+            ObjectShape::Ellipse
+        } else if self.point {
+            ObjectShape::Point
+        } else if let Some(polygon) = self.polygon {
+            ObjectShape::Polygon(points(polygon))
+        } else if let Some(polyline) = self.polyline {
+            ObjectShape::Polyline(points(polyline))
+        } else if let Some(text) = self.text {
+            ObjectShape::Text(text.text)
+        } else {
+            ObjectShape::Rectangle
+        };
+
+        Ok(Object {
+            id: self.id,
+            name: self.name,
+            class: self.class.or(self.kind).unwrap_or_default(),
+            position: offset + Vec2::new(self.x, self.y),
+            size: Vec2::new(self.width, self.height),
+            rotation: self.rotation.to_radians(),
+            visible: self.visible,
+            tile: self.gid.map(Tile::from_gid),
+            shape,
+            properties: json_properties(self.properties, directory)?,
+        })
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct JsonText {
+    text: String,
+}
+
+#[test]
+fn tmx_test() {
+    let map = TiledMap::from_tmx(
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.9" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16" infinite="0" backgroundcolor="#80ff0000">
+ <properties>
+  <property name="music" type="file" value="music/level.ogg"/>
+  <property name="gravity" type="float" value="9.8"/>
+ </properties>
+ <tileset firstgid="1" name="terrain" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+  <image source="terrain.png" width="32" height="32"/>
+  <tile id="2">
+   <properties>
+    <property name="solid" type="bool" value="true"/>
+   </properties>
+   <animation>
+    <frame tileid="2" duration="100"/>
+    <frame tileid="3" duration="200"/>
+   </animation>
+  </tile>
+ </tileset>
+ <group name="world" offsetx="8" opacity="0.5">
+  <layer id="1" name="ground" width="3" height="2" visible="0">
+   <data encoding="csv">
+1,2,0,
+3,2147483652,4
+</data>
+  </layer>
+ </group>
+ <objectgroup id="2" name="things">
+  <object id="1" name="spawn" type="player" x="20" y="30">
+   <point/>
+  </object>
+  <object id="2" name="door" x="0" y="0" width="16" height="32" rotation="90">
+   <properties>
+    <property name="target" type="object" value="1"/>
+    <property name="note">Goes to
+the cellar</property>
+   </properties>
+  </object>
+  <object id="3" x="1" y="2">
+   <polygon points="0,0 4,0 0,-4"/>
+  </object>
+ </objectgroup>
+</map>"##,
+        Path::new("maps"),
+    )
+    .unwrap();
+
+    assert_eq!(map.size, UVec2::new(3, 2));
+    assert_eq!(map.background, Some([1.0, 0.0, 0.0, 128.0 / 255.0]));
+    assert_eq!(
+        map.properties["music"],
+        Property::File(Path::new("maps/music/level.ogg").to_owned())
+    );
+    assert_eq!(map.properties["gravity"], Property::Float(9.8));
+
+    let tileset = &map.tilesets[0];
+    assert_eq!(tileset.image, Path::new("maps/terrain.png"));
+    assert_eq!(tileset.tile_properties[&2]["solid"], Property::Bool(true));
+    assert_eq!(tileset.frame(2, Duration::from_millis(150)), 3);
+
+    let layer = map.tile_layers().next().unwrap();
+    assert_eq!(layer.offset, Vec2::new(8.0, 0.0));
+    assert_eq!(layer.color[3], 0.5);
+    assert!(!layer.visible);
+    assert_eq!(layer.get(UVec2::new(2, 0)), Some(Tile::EMPTY));
+    let flipped = layer.get(UVec2::new(1, 1)).unwrap();
+    assert_eq!(flipped.id, 4);
+    assert!(flipped.flip.horizontal);
+
+    assert_eq!(map.object("spawn").unwrap().shape, ObjectShape::Point);
+    assert_eq!(map.object("spawn").unwrap().class, "player");
+    let door = map.object("door").unwrap();
+    assert_eq!(door.properties["target"], Property::Object(1));
+    assert_eq!(
+        door.properties["note"],
+        Property::String("Goes to\nthe cellar".to_owned())
+    );
+    assert_eq!(
+        map.object_layers().next().unwrap().objects[2].shape,
+        ObjectShape::Polygon(vec![Vec2::ZERO, Vec2::new(4.0, 0.0), Vec2::new(0.0, -4.0)])
+    );
+}
+
+#[test]
+fn tmj_test() {
+    let map = TiledMap::from_tmj(
+        r##"{
+ "width": 2, "height": 2, "tilewidth": 8, "tileheight": 8,
+ "orientation": "orthogonal", "infinite": true,
+ "tilesets": [{
+  "firstgid": 1, "name": "tiles", "image": "tiles.png",
+  "imagewidth": 16, "imageheight": 8, "tilewidth": 8, "tileheight": 8,
+  "tileoffset": { "x": 0, "y": 4 }
+ }],
+ "layers": [
+  {
+   "type": "tilelayer", "name": "ground", "tintcolor": "#ff00ff",
+   "encoding": "base64", "compression": "zlib",
+   "chunks": [
+    { "x": -2, "y": 0, "width": 2, "height": 1, "data": "eJxjZGBgYAJiAAAYAAQ=" },
+    { "x": 0, "y": 0, "width": 1, "height": 1, "data": "eJxjYmBgAAAADAAD" }
+   ],
+   "properties": [{ "name": "depth", "type": "int", "value": 3 }]
+  },
+  {
+   "type": "objectgroup", "name": "things", "offsetx": 4,
+   "objects": [{
+    "id": 5, "name": "sign", "class": "text", "x": 1, "y": 2, "gid": 1073741825,
+    "text": { "text": "Hello" },
+    "properties": [{ "name": "style", "type": "class", "value": { "bold": true } }]
+   }]
+  }
+ ]
+}"##,
+        Path::new(""),
+    )
+    .unwrap();
+
+    assert_eq!(map.tilesets[0].tile_count, 2);
+    assert_eq!(map.tilesets[0].offset, Vec2::new(0.0, 4.0));
+
+    let layer = map.tile_layers().next().unwrap();
+    assert_eq!(layer.origin, IVec2::new(-2, 0));
+    assert_eq!(layer.size, UVec2::new(3, 1));
+    let ids = layer.tiles.iter().map(|tile| tile.id).collect::<Vec<u32>>();
+    assert_eq!(ids, [1, 2, 2]);
+    assert_eq!(layer.color, [1.0, 0.0, 1.0, 1.0]);
+    assert_eq!(layer.properties["depth"], Property::Int(3));
+
+    let sign = map.object("sign").unwrap();
+    assert_eq!(sign.position, Vec2::new(5.0, 2.0));
+    assert_eq!(sign.shape, ObjectShape::Text("Hello".to_owned()));
+    assert!(sign.tile.unwrap().flip.vertical);
+    let style = Properties::from([("bold".to_owned(), Property::Bool(true))]);
+    assert_eq!(sign.properties["style"], Property::Class(style));
+}
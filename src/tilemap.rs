@@ -0,0 +1,725 @@
+//! Drawing grids of tiles from tileset textures, split into chunks that are only rebuilt when
+//! they change.
+
+use std::{
+    collections::HashMap, f32::consts::FRAC_PI_2, ops::Range, path::PathBuf, time::Duration,
+};
+
+use anyhow::{Context, Result};
+use glam::{IVec2, UVec2, Vec2};
+use image::DynamicImage;
+use wgpu::{RenderPipeline, Sampler, TextureView};
+
+use crate::{
+    graphics::Renderer,
+    instances::StreamingBuffer,
+    math::Rect,
+    renderers::{SimpleRenderer, Texture},
+    sprites::{batch, Sprite, SpriteCamera, SpriteInstance, SpriteRenderer, SpriteView},
+    tiled::TiledMap,
+    GameData,
+};
+
+/// The number of tiles along each side of a chunk.
+pub const CHUNK_SIZE: u32 = 32;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+
+/// A custom property of a map, layer, tileset, tile or object.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Property {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Color([f32; 4]),
+    File(PathBuf),
+    /// The ID of an object, where 0 is no object.
+    Object(u32),
+    /// A set of properties of a custom class.
+    Class(Properties),
+}
+
+/// Custom properties by their name.
+pub type Properties = HashMap<String, Property>;
+
+/// How a tile is mirrored. When all three are used the diagonal flip happens first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TileFlip {
+    /// If the tile is mirrored horizontally.
+    pub horizontal: bool,
+    /// If the tile is mirrored vertically.
+    pub vertical: bool,
+    /// If the tile is mirrored along the line from its top left to its bottom right corner.
+    pub diagonal: bool,
+}
+
+impl TileFlip {
+    /// The rotation and the horizontal and vertical mirroring of a sprite drawn around its
+    /// centre that has the same result as the flip.
+    fn orientation(&self) -> (f32, bool, bool) {
+        if self.diagonal {
+            (FRAC_PI_2, self.vertical, !self.horizontal)
+        } else {
+            (0.0, self.horizontal, self.vertical)
+        }
+    }
+}
+
+/// A single cell of a `TileLayer`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tile {
+    /// The global ID of the tile, which is the `first_gid` of its tileset plus the index of
+    /// the tile in the tileset. 0 is an empty cell.
+    pub id: u32,
+    /// How the tile is mirrored.
+    pub flip: TileFlip,
+}
+
+impl Tile {
+    /// An empty cell.
+    pub const EMPTY: Self = Self {
+        id: 0,
+        flip: TileFlip {
+            horizontal: false,
+            vertical: false,
+            diagonal: false,
+        },
+    };
+
+    /// Create a `Tile` of the global ID `id` that isn't mirrored.
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            flip: TileFlip::default(),
+        }
+    }
+
+    /// Create a `Tile` from a global ID with flip flags in its highest bits, as it is stored
+    /// in Tiled maps.
+    pub fn from_gid(gid: u32) -> Self {
+        Self {
+            id: gid
+                & !(FLIPPED_HORIZONTALLY
+                    | FLIPPED_VERTICALLY
+                    | FLIPPED_DIAGONALLY
+                    | ROTATED_HEXAGONAL),
+            flip: TileFlip {
+                horizontal: gid & FLIPPED_HORIZONTALLY != 0,
+                vertical: gid & FLIPPED_VERTICALLY != 0,
+                diagonal: gid & FLIPPED_DIAGONALLY != 0,
+            },
+        }
+    }
+
+    /// Modifies how the tile is mirrored, then returns it.
+    pub fn with_flip(mut self, flip: TileFlip) -> Self {
+        self.flip = flip;
+        self
+    }
+
+    /// Returns `true` if the cell has no tile.
+    pub fn is_empty(&self) -> bool {
+        self.id == 0
+    }
+}
+
+/// A frame of an animated tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileFrame {
+    /// The index of the tile in the tileset that is shown.
+    pub tile: u32,
+    /// How long the frame is shown for.
+    pub duration: Duration,
+}
+
+/// A grid of tiles on a single image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tileset {
+    /// The name of the tileset.
+    pub name: String,
+    /// The global ID of the first tile. The global IDs of tilesets used together must not
+    /// overlap.
+    pub first_gid: u32,
+    /// The path of the image the tiles are on.
+    pub image: PathBuf,
+    /// The size of the image in pixels.
+    pub image_size: UVec2,
+    /// The size of each tile in pixels.
+    pub tile_size: UVec2,
+    /// The number of pixels around the edge of the image before the first tiles.
+    pub margin: u32,
+    /// The number of pixels between each tile.
+    pub spacing: u32,
+    /// The number of tiles in each row.
+    pub columns: u32,
+    /// The number of tiles in the tileset.
+    pub tile_count: u32,
+    /// How far each tile is moved when it is drawn, in pixels.
+    pub offset: Vec2,
+    /// The frames of animated tiles, by the index of the tile.
+    pub animations: HashMap<u32, Vec<TileFrame>>,
+    /// The custom properties of the tileset.
+    pub properties: Properties,
+    /// The custom properties of each tile, by the index of the tile.
+    pub tile_properties: HashMap<u32, Properties>,
+}
+
+impl Tileset {
+    /// Create a `Tileset` that splits an image of `image_size` pixels into tiles of
+    /// `tile_size`.
+    pub fn new(first_gid: u32, image_size: UVec2, tile_size: UVec2) -> Self {
+        let mut tileset = Self {
+            name: String::new(),
+            first_gid,
+            image: PathBuf::new(),
+            image_size,
+            tile_size,
+            margin: 0,
+            spacing: 0,
+            columns: 0,
+            tile_count: 0,
+            offset: Vec2::ZERO,
+            animations: HashMap::new(),
+            properties: Properties::new(),
+            tile_properties: HashMap::new(),
+        };
+        tileset.fit_grid();
+        tileset
+    }
+
+    /// Modifies the margin and spacing of the tileset, then returns it.
+    pub fn with_spacing(mut self, margin: u32, spacing: u32) -> Self {
+        self.margin = margin;
+        self.spacing = spacing;
+        self.fit_grid();
+        self
+    }
+
+    /// Modifies the frames of the tile at `tile`, then returns the tileset.
+    pub fn with_animation(mut self, tile: u32, frames: Vec<TileFrame>) -> Self {
+        self.animations.insert(tile, frames);
+        self
+    }
+
+    /// Sets the number of columns and tiles to as many as fit on the image.
+    fn fit_grid(&mut self) {
+        let fit = |image: u32, tile: u32| {
+            (image + self.spacing).saturating_sub(self.margin * 2) / (tile + self.spacing).max(1)
+        };
+        self.columns = fit(self.image_size.x, self.tile_size.x);
+        self.tile_count = self.columns * fit(self.image_size.y, self.tile_size.y);
+    }
+
+    /// Returns `true` if the global ID `id` is part of the tileset.
+    pub fn contains(&self, id: u32) -> bool {
+        id >= self.first_gid && id - self.first_gid < self.tile_count
+    }
+
+    /// The area of the image covered by the tile at `tile` in pixels.
+    pub fn source(&self, tile: u32) -> Rect {
+        let columns = self.columns.max(1);
+        let cell = UVec2::new(tile % columns, tile / columns);
+        Rect {
+            position: (cell * (self.tile_size + self.spacing) + self.margin).as_vec2(),
+            size: self.tile_size.as_vec2(),
+        }
+    }
+
+    /// The tile shown in place of the tile at `tile` after `time` has passed, which is
+    /// different if the tile is animated.
+    pub fn frame(&self, tile: u32, time: Duration) -> u32 {
+        let frames = match self.animations.get(&tile) {
+            Some(frames) if !frames.is_empty() => frames,
+            _ => return tile,
+        };
+        let total = frames.iter().map(|frame| frame.duration).sum::<Duration>();
+        if total.is_zero() {
+            return frames[0].tile;
+        }
+
+        let mut time = Duration::from_nanos((time.as_nanos() % total.as_nanos()) as u64);
+        for frame in frames {
+            if time < frame.duration {
+                return frame.tile;
+            }
+            time -= frame.duration;
+        }
+        frames[frames.len() - 1].tile
+    }
+}
+
+/// A grid of tiles drawn together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileLayer {
+    /// The name of the layer.
+    pub name: String,
+    /// The number of tiles in each row and column.
+    pub size: UVec2,
+    /// The grid position of the first tile. This is only different from zero for parts of
+    /// maps that don't start at the origin.
+    pub origin: IVec2,
+    /// Every tile in the layer, row by row.
+    pub tiles: Vec<Tile>,
+    /// How far the layer is moved when it is drawn, in pixels.
+    pub offset: Vec2,
+    /// The color every tile is multiplied by.
+    pub color: [f32; 4],
+    /// If the layer is drawn.
+    pub visible: bool,
+    /// The custom properties of the layer.
+    pub properties: Properties,
+}
+
+impl TileLayer {
+    /// Create an empty `TileLayer` of `size` tiles.
+    pub fn new(name: &str, size: UVec2) -> Self {
+        Self {
+            name: name.to_owned(),
+            size,
+            origin: IVec2::ZERO,
+            tiles: vec![Tile::EMPTY; (size.x * size.y) as usize],
+            offset: Vec2::ZERO,
+            color: [1.0, 1.0, 1.0, 1.0],
+            visible: true,
+            properties: Properties::new(),
+        }
+    }
+
+    /// Returns the tile at `position`, or `None` if it is outside the layer.
+    pub fn get(&self, position: UVec2) -> Option<Tile> {
+        self.index(position).map(|index| self.tiles[index])
+    }
+
+    /// Replaces the tile at `position`, returning `false` if it is outside the layer.
+    pub fn set(&mut self, position: UVec2, tile: Tile) -> bool {
+        match self.index(position) {
+            Some(index) => {
+                self.tiles[index] = tile;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn index(&self, position: UVec2) -> Option<usize> {
+        let index = (position.y * self.size.x + position.x) as usize;
+        (position.x < self.size.x && position.y < self.size.y && index < self.tiles.len())
+            .then_some(index)
+    }
+
+    /// The number of chunks in each row and column of the layer.
+    fn chunks(&self) -> UVec2 {
+        (self.size + CHUNK_SIZE - 1) / CHUNK_SIZE
+    }
+}
+
+/// The sprite that draws the tile at `position` in `layer`, with its texture as the index of
+/// its tileset, and whether the tile is animated.
+fn tile_sprite(
+    tilesets: &[Tileset],
+    tile_size: UVec2,
+    layer: &TileLayer,
+    position: UVec2,
+    time: Duration,
+) -> Option<(Sprite, bool)> {
+    let tile = layer.get(position)?;
+    if tile.is_empty() {
+        return None;
+    }
+    let (index, tileset) = tilesets
+        .iter()
+        .enumerate()
+        .filter(|(_, tileset)| tileset.contains(tile.id))
+        .max_by_key(|(_, tileset)| tileset.first_gid)?;
+
+    let local = tile.id - tileset.first_gid;
+    let size = tileset.tile_size.as_vec2();
+    // Tiles that are taller than the grid stick out of the top of their cell.
+    let cell =
+        layer.offset + ((layer.origin + position.as_ivec2()) * tile_size.as_ivec2()).as_vec2();
+    let top_left = cell + Vec2::new(0.0, tile_size.y as f32 - size.y) + tileset.offset;
+    let (rotation, flip_x, flip_y) = tile.flip.orientation();
+
+    Some((
+        Sprite::new(index, top_left + size / 2.0)
+            .with_origin(Vec2::splat(0.5))
+            .with_source(tileset.source(tileset.frame(local, time)))
+            .with_rotation(rotation)
+            .with_flip(flip_x, flip_y)
+            .with_color(layer.color),
+        tileset.animations.contains_key(&local),
+    ))
+}
+
+/// The tiles of one part of a layer, drawn with one draw call for each tileset.
+struct Chunk {
+    instances: StreamingBuffer,
+    batches: Vec<(usize, Range<u32>)>,
+    /// The area covered by the tiles of the chunk.
+    bounds: Rect,
+    animated: bool,
+    dirty: bool,
+}
+
+/// A renderer that draws layers of tiles in pixel coordinates. Each layer is split into
+/// chunks of `CHUNK_SIZE` by `CHUNK_SIZE` tiles, which are only rebuilt when a tile in them
+/// changes or when they show an animated tile, and are only drawn when they are in view.
+pub struct TilemapRenderer {
+    pipeline: RenderPipeline,
+    view: SpriteView,
+    tile_size: UVec2,
+    tilesets: Vec<Tileset>,
+    textures: Vec<Texture>,
+    layers: Vec<(TileLayer, Vec<Chunk>)>,
+    /// The area of the world in view.
+    visible: Rect,
+    /// How long tiles have been animated for.
+    pub time: Duration,
+    /// The camera that the tiles are viewed through. If this is `None` then the top left
+    /// corner of the window is at the origin.
+    pub camera: Option<SpriteCamera>,
+    /// A sampler for nearest filters (magnified textures looked pixelated).
+    pub nearest_sampler: Sampler,
+    /// A sampler for linear filters (magnified textures looked blurry).
+    pub linear_sampler: Sampler,
+}
+
+impl TilemapRenderer {
+    /// Create a `TilemapRenderer` with no tilesets or layers, that places tiles on a grid of
+    /// cells of `tile_size` pixels.
+    pub fn new(data: &GameData, tile_size: UVec2) -> Self {
+        Self {
+            pipeline: SpriteRenderer::pipeline(data),
+            view: SpriteView::new(data),
+            tile_size,
+            tilesets: Vec::new(),
+            textures: Vec::new(),
+            layers: Vec::new(),
+            visible: Rect::default(),
+            time: Duration::ZERO,
+            camera: None,
+            nearest_sampler: SimpleRenderer::nearest_sampler(data),
+            linear_sampler: SimpleRenderer::linear_sampler(data),
+        }
+    }
+
+    /// Create a `TilemapRenderer` that draws the tile layers of `map`, loading the images of
+    /// its tilesets with the nearest sampler.
+    pub fn from_map(data: &GameData, map: &TiledMap) -> Result<Self> {
+        let mut renderer = Self::new(data, map.tile_size);
+        for tileset in &map.tilesets {
+            let image = image::open(&tileset.image)
+                .with_context(|| format!("Could not load tileset image {:?}", tileset.image))?;
+            let texture = Texture::new(
+                data,
+                &DynamicImage::from(image.to_rgba8()),
+                &renderer.nearest_sampler,
+            );
+            renderer.add_tileset(tileset.clone(), texture);
+        }
+        for layer in map.tile_layers() {
+            renderer.add_layer(layer.clone());
+        }
+        Ok(renderer)
+    }
+
+    /// The size of each cell of the grid in pixels.
+    pub fn tile_size(&self) -> UVec2 {
+        self.tile_size
+    }
+
+    /// Adds a tileset drawn from `texture` and returns its index.
+    pub fn add_tileset(&mut self, tileset: Tileset, texture: Texture) -> usize {
+        self.tilesets.push(tileset);
+        self.textures.push(texture);
+        self.mark_dirty();
+        self.tilesets.len() - 1
+    }
+
+    /// The tilesets that tiles are drawn from.
+    pub fn tilesets(&self) -> &[Tileset] {
+        &self.tilesets
+    }
+
+    /// Adds a layer in front of the others and returns its index.
+    pub fn add_layer(&mut self, layer: TileLayer) -> usize {
+        self.layers.push((layer, Vec::new()));
+        self.layers.len() - 1
+    }
+
+    /// The layers drawn, from back to front.
+    pub fn layers(&self) -> impl Iterator<Item = &TileLayer> {
+        self.layers.iter().map(|(layer, _)| layer)
+    }
+
+    /// Gets the layer at `index` to modify, which rebuilds all of its chunks. Use `set_tile`
+    /// to only rebuild the chunk of a single tile.
+    pub fn layer_mut(&mut self, index: usize) -> Option<&mut TileLayer> {
+        let (layer, chunks) = self.layers.get_mut(index)?;
+        for chunk in chunks {
+            chunk.dirty = true;
+        }
+        Some(layer)
+    }
+
+    /// Replaces the tile at `position` in the layer at `layer`, returning `false` if there is
+    /// no such tile.
+    pub fn set_tile(&mut self, layer: usize, position: UVec2, tile: Tile) -> bool {
+        let (layer, chunks) = match self.layers.get_mut(layer) {
+            Some(layer) => layer,
+            None => return false,
+        };
+        if !layer.set(position, tile) {
+            return false;
+        }
+        let chunk = position / CHUNK_SIZE;
+        if let Some(chunk) = chunks.get_mut((chunk.y * layer.chunks().x + chunk.x) as usize) {
+            chunk.dirty = true;
+        }
+        true
+    }
+
+    /// The position of the tile of the layer at `layer` that covers `point` in the world,
+    /// ignoring tiles that are bigger than the grid.
+    pub fn tile_position(&self, layer: usize, point: Vec2) -> Option<UVec2> {
+        let (layer, _) = self.layers.get(layer)?;
+        let cell = ((point - layer.offset) / self.tile_size.as_vec2())
+            .floor()
+            .as_ivec2()
+            - layer.origin;
+        (cell.cmpge(IVec2::ZERO).all() && cell.as_uvec2().cmplt(layer.size).all())
+            .then(|| cell.as_uvec2())
+    }
+
+    fn mark_dirty(&mut self) {
+        for (_, chunks) in &mut self.layers {
+            for chunk in chunks {
+                chunk.dirty = true;
+            }
+        }
+    }
+
+    /// Rebuilds the instances of the chunk at `chunk` in `layer`.
+    fn build_chunk(&self, data: &GameData, layer: &TileLayer, chunk: &mut Chunk, index: u32) {
+        let start = UVec2::new(index % layer.chunks().x, index / layer.chunks().x) * CHUNK_SIZE;
+        let end = (start + CHUNK_SIZE).min(layer.size);
+
+        let mut sprites = Vec::new();
+        let mut bounds: Option<(Vec2, Vec2)> = None;
+        chunk.animated = false;
+        for y in start.y..end.y {
+            for x in start.x..end.x {
+                let (sprite, animated) = match tile_sprite(
+                    &self.tilesets,
+                    self.tile_size,
+                    layer,
+                    UVec2::new(x, y),
+                    self.time,
+                ) {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                chunk.animated |= animated;
+
+                // Covers the tile whichever way it is rotated.
+                let half =
+                    Vec2::splat(self.tilesets[sprite.texture].tile_size.max_element() as f32) / 2.0;
+                bounds = Some(match bounds {
+                    Some((min, max)) => (
+                        min.min(sprite.position - half),
+                        max.max(sprite.position + half),
+                    ),
+                    None => (sprite.position - half, sprite.position + half),
+                });
+                sprites.push(sprite);
+            }
+        }
+
+        let (order, batches) = batch(&sprites);
+        let instances = order
+            .into_iter()
+            .map(|index| {
+                let sprite = &sprites[index];
+                sprite.instance(self.textures[sprite.texture].size.as_vec2())
+            })
+            .collect::<Vec<SpriteInstance>>();
+        chunk
+            .instances
+            .write(data, bytemuck::cast_slice(&instances));
+        chunk.batches = batches;
+        chunk.bounds = bounds.map_or(Rect::default(), |(min, max)| Rect {
+            position: min,
+            size: max - min,
+        });
+        chunk.dirty = false;
+    }
+}
+
+impl Renderer for TilemapRenderer {
+    fn render<'a, 'b: 'a>(&'b self, render_pass: &'a mut wgpu::RenderPass<'b>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.view.bind_group, &[]);
+        for (layer, chunks) in &self.layers {
+            if !layer.visible {
+                continue;
+            }
+            for chunk in chunks {
+                if chunk.batches.is_empty() || !chunk.bounds.overlaps(&self.visible) {
+                    continue;
+                }
+                render_pass.set_vertex_buffer(0, chunk.instances.buffer.slice(..));
+                for (tileset, range) in &chunk.batches {
+                    render_pass.set_bind_group(1, &self.textures[*tileset].diffuse, &[]);
+                    render_pass.draw(0..6, range.clone());
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, data: &GameData) {
+        self.time += data.delta_time;
+        let window_size = data.get_window_size().as_vec2();
+        self.visible = match self.camera {
+            Some(camera) => {
+                let size = window_size / camera.zoom;
+                Rect {
+                    position: camera.position - size / 2.0,
+                    size,
+                }
+            }
+            None => Rect {
+                position: Vec2::ZERO,
+                size: window_size,
+            },
+        };
+
+        let mut layers = std::mem::take(&mut self.layers);
+        for (layer, chunks) in &mut layers {
+            let count = layer.chunks();
+            if chunks.len() != (count.x * count.y) as usize {
+                *chunks = (0..count.x * count.y)
+                    .map(|_| Chunk {
+                        instances: StreamingBuffer::new(data, &[]),
+                        batches: Vec::new(),
+                        bounds: Rect::default(),
+                        animated: false,
+                        dirty: true,
+                    })
+                    .collect();
+            }
+
+            for (index, chunk) in chunks.iter_mut().enumerate() {
+                // Animated chunks out of view are rebuilt once they come into view.
+                let animate =
+                    chunk.animated && layer.visible && chunk.bounds.overlaps(&self.visible);
+                if chunk.dirty || animate {
+                    self.build_chunk(data, layer, chunk, index as u32);
+                }
+            }
+        }
+        self.layers = layers;
+
+        self.view.update(data, self.camera);
+    }
+
+    fn make_render_pass<'a>(
+        &'a self,
+        view: &'a TextureView,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    fn resize(&mut self, data: &GameData) {
+        self.view.resize(data);
+    }
+}
+
+#[test]
+fn tile_flip_test() {
+    use glam::Vec2Swizzles;
+
+    // Mirrors a point the way Tiled does, diagonally first.
+    let tiled = |flip: TileFlip, mut point: Vec2| {
+        if flip.diagonal {
+            point = point.yx();
+        }
+        if flip.horizontal {
+            point.x = -point.x;
+        }
+        if flip.vertical {
+            point.y = -point.y;
+        }
+        point
+    };
+    // Mirrors and then rotates a point the way the sprite shader does.
+    let sprite = |flip: TileFlip, mut point: Vec2| {
+        let (rotation, flip_x, flip_y) = flip.orientation();
+        if flip_x {
+            point.x = -point.x;
+        }
+        if flip_y {
+            point.y = -point.y;
+        }
+        Vec2::from_angle(rotation).rotate(point)
+    };
+
+    for gid in 0..8 {
+        let tile = Tile::from_gid(1 | gid << 29);
+        assert_eq!(tile.id, 1);
+        let point = Vec2::new(1.0, 2.0);
+        let expected = tiled(tile.flip, point);
+        assert!((sprite(tile.flip, point) - expected).length() < 1e-6);
+    }
+}
+
+#[test]
+fn tile_sprite_test() {
+    let tileset = Tileset::new(1, UVec2::new(34, 50), UVec2::new(16, 24))
+        .with_spacing(1, 0)
+        .with_animation(
+            0,
+            vec![
+                TileFrame {
+                    tile: 0,
+                    duration: Duration::from_millis(100),
+                },
+                TileFrame {
+                    tile: 3,
+                    duration: Duration::from_millis(100),
+                },
+            ],
+        );
+    assert_eq!((tileset.columns, tileset.tile_count), (2, 4));
+
+    let mut layer = TileLayer::new("", UVec2::new(2, 2));
+    layer.set(UVec2::new(1, 1), Tile::new(1));
+    let (sprite, animated) = tile_sprite(
+        &[tileset],
+        UVec2::new(16, 16),
+        &layer,
+        UVec2::new(1, 1),
+        Duration::from_millis(150),
+    )
+    .unwrap();
+    assert!(animated);
+    // The tile is taller than its cell, so it sticks out of the top.
+    assert_eq!(sprite.position, Vec2::new(24.0, 12.0 + 8.0));
+    assert_eq!(sprite.source, Some(Rect::new(17.0, 25.0, 16.0, 24.0)));
+    assert!(tile_sprite(&[], UVec2::ONE, &layer, UVec2::ZERO, Duration::ZERO).is_none());
+}